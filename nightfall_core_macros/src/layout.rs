@@ -0,0 +1,205 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Field, Fields, Ident};
+
+#[derive(Clone, Copy)]
+pub enum Layout {
+    Std140,
+    Std430,
+}
+impl Layout {
+    fn name(self) -> &'static str {
+        match self {
+            Layout::Std140 => "std140",
+            Layout::Std430 => "std430",
+        }
+    }
+    pub fn trait_path(self) -> TokenStream {
+        match self {
+            Layout::Std140 => quote! { nightfall_core::glsl::Std140 },
+            Layout::Std430 => quote! { nightfall_core::glsl::Std430 },
+        }
+    }
+    /// std140 rounds the alignment of structs up to a vec4.
+    fn min_struct_align(self) -> usize {
+        match self {
+            Layout::Std140 => 16,
+            Layout::Std430 => 1,
+        }
+    }
+}
+
+pub fn is_padding(field: &Field) -> bool {
+    field.attrs.iter().any(|attr| attr.path().is_ident("padding"))
+}
+
+pub fn is_repr_c(attrs: &[Attribute]) -> bool {
+    attrs.iter().filter(|attr| attr.path().is_ident("repr")).any(|attr| {
+        let mut repr_c = false;
+        let _ = attr.parse_nested_meta(|meta| {
+            repr_c |= meta.path.is_ident("C");
+            Ok(())
+        });
+        repr_c
+    })
+}
+
+/// the named fields of a non generic `#[repr(C)]` struct.
+pub fn layout_fields<'a>(ast: &'a DeriveInput, derive: &str) -> syn::Result<impl Iterator<Item = &'a Field>> {
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&ast.generics, format!("{} can not be derived for generic structs", derive)));
+    }
+    if !is_repr_c(&ast.attrs) {
+        return Err(syn::Error::new_spanned(&ast.ident, format!("{} requires #[repr(C)] so the field order matches the shader", derive)));
+    }
+    match &ast.data {
+        Data::Struct(structure) => match &structure.fields {
+            Fields::Named(fields) => Ok(fields.named.iter()),
+            _ => Err(syn::Error::new_spanned(ast, format!("{} can only be derived for structs with named fields", derive))),
+        },
+        _ => Err(syn::Error::new_spanned(ast, format!("{} can only be derived for structs", derive))),
+    }
+}
+
+/// statements for a `const` block that assert every non padding field sits at
+/// the offset `layout` expects. Leaves the end of the last field in `offset`.
+pub fn offset_checks<'a>(name: &Ident, fields: impl Iterator<Item = &'a Field>, layout: Layout) -> TokenStream {
+    let layout_trait = layout.trait_path();
+    let layout_name = layout.name();
+    let checks = fields.filter(|field| !is_padding(field)).map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        quote! {
+            offset = nightfall_core::glsl::align_up(offset, <#ty as #layout_trait>::ALIGN);
+            assert!(
+                ::core::mem::offset_of!(#name, #ident) == offset,
+                concat!("`", stringify!(#name), "::", stringify!(#ident), "` is not at its ", #layout_name, " offset, add padding before it")
+            );
+            assert!(
+                ::core::mem::size_of::<#ty>() == <#ty as #layout_trait>::SIZE,
+                concat!("`", stringify!(#name), "::", stringify!(#ident), "` does not have the same size as its ", #layout_name, " counterpart")
+            );
+            offset += <#ty as #layout_trait>::SIZE;
+        }
+    });
+    quote! {
+        let mut offset: usize = 0;
+        #(#checks)*
+    }
+}
+
+/// an expression for the alignment of a struct made of `types`.
+fn struct_align(types: &[&syn::Type], layout: Layout) -> TokenStream {
+    let layout_trait = layout.trait_path();
+    let min_align = layout.min_struct_align();
+    if types.is_empty() {
+        return quote! { #min_align };
+    }
+    quote! {{
+        let mut align: usize = #min_align;
+        #(
+            if <#types as #layout_trait>::ALIGN > align {
+                align = <#types as #layout_trait>::ALIGN;
+            }
+        )*
+        align
+    }}
+}
+
+/// an expression for where the last of `types` ends when laid out by `layout`.
+fn struct_end(types: &[&syn::Type], layout: Layout) -> TokenStream {
+    let layout_trait = layout.trait_path();
+    if types.is_empty() {
+        return quote! { 0usize };
+    }
+    quote! {{
+        let mut offset: usize = 0;
+        #(
+            offset = nightfall_core::glsl::align_up(offset, <#types as #layout_trait>::ALIGN) + <#types as #layout_trait>::SIZE;
+        )*
+        offset
+    }}
+}
+
+pub fn impl_layout_derive(ast: &DeriveInput, layout: Layout) -> syn::Result<TokenStream> {
+    let derive = match layout {
+        Layout::Std140 => "Std140",
+        Layout::Std430 => "Std430",
+    };
+    let name = &ast.ident;
+    let fields = layout_fields(ast, derive)?.filter(|field| !is_padding(field)).collect::<Vec<_>>();
+    let checks = offset_checks(name, fields.iter().copied(), layout);
+    let layout_trait = layout.trait_path();
+    let layout_name = layout.name();
+    let types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+    let align = struct_align(&types, layout);
+    let end = struct_end(&types, layout);
+    Ok(quote! {
+        unsafe impl #layout_trait for #name {
+            const ALIGN: usize = #align;
+            const SIZE: usize = nightfall_core::glsl::align_up(#end, <Self as #layout_trait>::ALIGN);
+        }
+        #[allow(unused_assignments, unused_mut, unused_variables)]
+        const _: () = {
+            #checks
+            assert!(
+                ::core::mem::size_of::<#name>() == <#name as #layout_trait>::SIZE,
+                concat!("`", stringify!(#name), "` needs trailing padding to match its ", #layout_name, " size")
+            );
+        };
+    })
+}
+
+/// `#[glsl_layout(std140)]`: inserts a `#[padding]` field before every field
+/// and at the end of the struct, then derives the matching layout trait.
+pub fn impl_glsl_layout(attr: TokenStream, item: DeriveInput) -> syn::Result<TokenStream> {
+    let layout = match attr.to_string().as_str() {
+        "std140" => Layout::Std140,
+        "std430" => Layout::Std430,
+        _ => return Err(syn::Error::new_spanned(attr, "expected `std140` or `std430`")),
+    };
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&item.generics, "glsl_layout can not be used on generic structs"));
+    }
+    let Data::Struct(structure) = &item.data else {
+        return Err(syn::Error::new_spanned(&item, "glsl_layout can only be used on structs"));
+    };
+    let Fields::Named(named) = &structure.fields else {
+        return Err(syn::Error::new_spanned(&item.ident, "glsl_layout can only be used on structs with named fields"));
+    };
+    let layout_trait = layout.trait_path();
+    let vis = &item.vis;
+    let mut fields = vec![];
+    let mut previous: Vec<&syn::Type> = vec![];
+    for field in &named.named {
+        if is_padding(field) {
+            return Err(syn::Error::new_spanned(field, "glsl_layout inserts the padding itself, remove this field"));
+        }
+        let ty = &field.ty;
+        let end = struct_end(&previous, layout);
+        let pad = Ident::new(&format!("_pad_{}", field.ident.as_ref().unwrap()), Span::call_site());
+        fields.push(quote! {
+            #[padding]
+            #vis #pad: nightfall_core::glsl::Padding<{ nightfall_core::glsl::align_up(#end, <#ty as #layout_trait>::ALIGN) - #end }>
+        });
+        fields.push(quote! { #field });
+        previous.push(ty);
+    }
+    let end = struct_end(&previous, layout);
+    let align = struct_align(&previous, layout);
+    fields.push(quote! {
+        #[padding]
+        #vis _pad_end: nightfall_core::glsl::Padding<{ nightfall_core::glsl::align_up(#end, #align) - #end }>
+    });
+    let repr = (!is_repr_c(&item.attrs)).then(|| quote! { #[repr(C)] });
+    let attrs = &item.attrs;
+    let ident = &item.ident;
+    Ok(quote! {
+        #[derive(#layout_trait)]
+        #(#attrs)*
+        #repr
+        #vis struct #ident {
+            #(#fields),*
+        }
+    })
+}
@@ -1,80 +1,53 @@
 #![allow(unused)]
+mod resources;
+mod push_constants;
+mod layout;
+mod vertex;
 use proc_macro::TokenStream;
-use quote::TokenStreamExt;
-use syn::{Attribute, Data, DeriveInput, Fields, Meta};
-fn grab_format(attr: &Attribute) -> proc_macro2::TokenStream {
-    let &Attribute { 
-        pound_token: _, 
-        style: _, 
-        bracket_token: _, 
-        meta 
-    } = &attr;
-    match meta {
-        Meta::List(val) => {
-            return val.tokens.clone();
-        }
-        _ => {
-            panic!("Try using a list")
-        }
+use syn::{meta::ParseNestedMeta, DeriveInput, Ident};
+/// parses `stages(VERTEX, FRAGMENT)` into the `ShaderStageFlags` constant names.
+fn parse_stages(meta: &ParseNestedMeta) -> syn::Result<Vec<Ident>> {
+    let mut stages = vec![];
+    meta.parse_nested_meta(|stage| {
+        let stage = stage.path.get_ident().ok_or_else(|| stage.error("expected a shader stage such as VERTEX or FRAGMENT"))?;
+        stages.push(stage.clone());
+        Ok(())
+    })?;
+    if stages.is_empty() {
+        return Err(meta.error("expected at least one shader stage"));
     }
+    Ok(stages)
 }
-fn attribute_tokens(fields: Fields) -> proc_macro2::TokenStream {
-    let mut token_start = quote::quote! {
-        let mut offset: u32 = 0;
-        let mut attributes = Vec::new();
-    };
-    let tokens = fields.iter().enumerate().map(|(i, field)|{
-        let ty = &field.ty;
-        let attrs = &field.attrs[0];
-        let format = grab_format(attrs);
-        quote::quote! {
-            attributes.push(
-                ash::vk::VertexInputAttributeDescription {
-                    binding,
-                    location: #i as u32,
-                    offset: offset,
-                    format: #format,
-                });
-                offset += std::mem::size_of::<#ty>() as u32;
-        }
-    }).fold(proc_macro2::TokenStream::new(), |mut a, b|{ 
-        a.append_all(b);
-        a
-    });
-    token_start.append_all(tokens);
-    token_start
+#[proc_macro_derive(Vertex, attributes(format, location, normalized, per_instance))]
+pub fn derive(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as DeriveInput);
+    vertex::impl_vertex(&ast).unwrap_or_else(syn::Error::into_compile_error).into()
 }
-fn impl_vertex_macro(ast: &DeriveInput) -> TokenStream {
-    let name = &ast.ident;
-    let fields = if let Data::Struct(structure) = &ast.data {
-        structure.fields.clone()
-    } else {
-        panic!("Not a Struct")
-    };
-    let token_start = attribute_tokens(fields);
-    
-    let gen = quote::quote! {
-        impl Vertex for #name {
-            fn binding(binding: u32, rate: ash::vk::VertexInputRate) -> ash::vk::VertexInputBindingDescription {
-                ash::vk::VertexInputBindingDescription {
-                    binding,
-                    input_rate: rate,
-                    stride: std::mem::size_of::<Self>() as u32,
-                }
-            }
-            fn attributes(binding: u32) -> Vec<ash::vk::VertexInputAttributeDescription> {
-                // #token_start
-                #token_start
-                attributes
-            }
-        }
-    };
-    gen.into()
+#[proc_macro_derive(ShaderResources, attributes(descriptor))]
+pub fn derive_shader_resources(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as DeriveInput);
+    resources::impl_shader_resources(&ast).unwrap_or_else(syn::Error::into_compile_error).into()
 }
-#[proc_macro_derive(Vertex, attributes(format))]
-pub fn derive(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    impl_vertex_macro(&ast)
+
+#[proc_macro_derive(PushConstants, attributes(push_constants, padding))]
+pub fn derive_push_constants(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as DeriveInput);
+    push_constants::impl_push_constants(&ast).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+#[proc_macro_derive(Std140, attributes(padding))]
+pub fn derive_std140(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as DeriveInput);
+    layout::impl_layout_derive(&ast, layout::Layout::Std140).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+#[proc_macro_derive(Std430, attributes(padding))]
+pub fn derive_std430(input: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(input as DeriveInput);
+    layout::impl_layout_derive(&ast, layout::Layout::Std430).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+#[proc_macro_attribute]
+pub fn glsl_layout(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = syn::parse_macro_input!(item as DeriveInput);
+    layout::impl_glsl_layout(attr.into(), item).unwrap_or_else(syn::Error::into_compile_error).into()
 }
 
 #[cfg(test)]
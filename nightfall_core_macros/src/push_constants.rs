@@ -0,0 +1,51 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Attribute, DeriveInput, Ident};
+
+use crate::layout::{is_padding, layout_fields, offset_checks, Layout};
+
+/// reads the `stages(...)` of a `#[push_constants(...)]` attribute, if any.
+fn parse_attribute(attrs: &[Attribute]) -> syn::Result<Option<Vec<Ident>>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("push_constants")) else {
+        return Ok(None);
+    };
+    let mut stages = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("stages") {
+            stages = Some(crate::parse_stages(&meta)?);
+            Ok(())
+        } else {
+            Err(meta.error("unknown push_constants argument, expected stages(...)"))
+        }
+    })?;
+    Ok(stages)
+}
+
+pub fn impl_push_constants(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let fields = layout_fields(ast, "PushConstants")?.filter(|field| !is_padding(field)).collect::<Vec<_>>();
+    let default_stages = parse_attribute(&ast.attrs)?.unwrap_or_else(|| vec![Ident::new("ALL", Span::call_site())]);
+    let checks = offset_checks(name, fields.iter().copied(), Layout::Std430);
+    let mut members = vec![];
+    for field in &fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let stages = parse_attribute(&field.attrs)?.unwrap_or_else(|| default_stages.clone());
+        members.push(quote! {
+            ash::vk::PushConstantRange {
+                stage_flags: ash::vk::ShaderStageFlags::from_raw(#(nightfall_core::pipeline::shader::ShaderStageFlags::#stages.as_raw())|*),
+                offset: ::core::mem::offset_of!(#name, #ident) as u32,
+                size: ::core::mem::size_of::<#ty>() as u32,
+            }
+        });
+    }
+    Ok(quote! {
+        #[allow(unused_assignments, unused_mut, unused_variables)]
+        const _: () = {
+            #checks
+        };
+        unsafe impl nightfall_core::pipeline::layout::PushConstants for #name {
+            const MEMBERS: &'static [ash::vk::PushConstantRange] = &[#(#members),*];
+        }
+    })
+}
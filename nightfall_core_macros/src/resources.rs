@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Data, DeriveInput, Field, Fields, Ident, LitInt};
+
+/// the descriptor kinds accepted by `#[descriptor(...)]`, along with the
+/// `DescriptorType` constant and the `DescriptorWriter` method used for them.
+const DESCRIPTOR_KINDS: &[(&str, &str, &str)] = &[
+    ("uniform_buffer", "UNIFORM_BUFFER", "push_uniform_buffer"),
+    ("uniform_buffer_dynamic", "UNIFORM_BUFFER_DYNAMIC", "push_uniform_buffer_dynamic"),
+    ("storage_buffer", "STORAGE_BUFFER", "push_storage_buffer"),
+    ("storage_buffer_dynamic", "STORAGE_BUFFER_DYNAMIC", "push_storage_buffer_dynamic"),
+    ("combined_image_sampler", "COMBINED_IMAGE_SAMPLER", "push_image_buffer"),
+    ("sampled_image", "SAMPLED_IMAGE", "push_sampled_image"),
+    ("storage_image", "STORAGE_IMAGE", "push_storage_image"),
+];
+
+struct DescriptorAttribute {
+    field: Ident,
+    set: u32,
+    binding: u32,
+    count: u32,
+    kind: (&'static str, &'static str, &'static str),
+    stages: Vec<Ident>,
+}
+
+fn parse_descriptor(field: &Field) -> syn::Result<Option<DescriptorAttribute>> {
+    let Some(attr) = field.attrs.iter().find(|attr| attr.path().is_ident("descriptor")) else {
+        return Ok(None);
+    };
+    let ident = field.ident.clone().ok_or_else(|| syn::Error::new_spanned(field, "ShaderResources requires named fields"))?;
+    let mut set = None;
+    let mut binding = None;
+    let mut count = 1;
+    let mut kind = None;
+    let mut stages = vec![];
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("set") {
+            set = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?);
+        } else if meta.path.is_ident("binding") {
+            binding = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?);
+        } else if meta.path.is_ident("count") {
+            count = meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?;
+        } else if meta.path.is_ident("stages") {
+            stages = crate::parse_stages(&meta)?;
+        } else if let Some(found) = DESCRIPTOR_KINDS.iter().find(|(name, _, _)| meta.path.is_ident(name)) {
+            if kind.is_some() {
+                return Err(meta.error("a descriptor can only have one type"));
+            }
+            kind = Some(*found);
+        } else {
+            let expected = DESCRIPTOR_KINDS.iter().map(|(name, _, _)| *name).collect::<Vec<_>>().join(", ");
+            return Err(meta.error(format!("unknown descriptor argument, expected set, binding, count, stages or one of: {}", expected)));
+        }
+        Ok(())
+    })?;
+    let set = set.ok_or_else(|| syn::Error::new_spanned(attr, "missing `set = N`"))?;
+    let binding = binding.ok_or_else(|| syn::Error::new_spanned(attr, "missing `binding = N`"))?;
+    let kind = kind.ok_or_else(|| syn::Error::new_spanned(attr, "missing descriptor type, e.g. `uniform_buffer`"))?;
+    if count == 0 {
+        return Err(syn::Error::new_spanned(attr, "descriptor count must be at least 1"));
+    }
+    if stages.is_empty() {
+        stages.push(Ident::new("ALL", Span::call_site()));
+    }
+    Ok(Some(DescriptorAttribute { field: ident, set, binding, count, kind, stages }))
+}
+
+pub fn impl_shader_resources(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let fields = match &ast.data {
+        Data::Struct(structure) => match &structure.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(ast, "ShaderResources can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(ast, "ShaderResources can only be derived for structs")),
+    };
+    let mut sets: BTreeMap<u32, Vec<DescriptorAttribute>> = BTreeMap::new();
+    for field in fields {
+        let Some(descriptor) = parse_descriptor(field)? else {
+            continue;
+        };
+        let bindings = sets.entry(descriptor.set).or_default();
+        if let Some(existing) = bindings.iter().find(|other| other.binding == descriptor.binding) {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("set {} binding {} is already used by `{}`", descriptor.set, descriptor.binding, existing.field),
+            ));
+        }
+        bindings.push(descriptor);
+    }
+    let set_numbers = sets.keys().collect::<Vec<_>>();
+    let layout_arms = sets.iter().map(|(set, bindings)| {
+        let bindings = bindings.iter().map(|descriptor| {
+            let binding = descriptor.binding;
+            let count = descriptor.count;
+            let ty = Ident::new(descriptor.kind.1, Span::call_site());
+            let stages = &descriptor.stages;
+            quote! {
+                .add_binding(#binding, nightfall_core::descriptors::DescriptorType::#ty, #count, #(nightfall_core::pipeline::shader::ShaderStageFlags::#stages)|*)
+            }
+        });
+        quote! {
+            #set => nightfall_core::descriptors::DescriptorLayoutBuilder::new()#(#bindings)*,
+        }
+    });
+    let writes = sets.values().enumerate().flat_map(|(idx, bindings)| {
+        bindings.iter().map(move |descriptor| {
+            let binding = descriptor.binding;
+            let count = descriptor.count;
+            let field = &descriptor.field;
+            let method = Ident::new(descriptor.kind.2, Span::call_site());
+            let info = if count == 1 {
+                quote! { (&self.#field).into() }
+            } else {
+                quote! { (&self.#field[0]).into() }
+            };
+            quote! {
+                writer.#method(sets[#idx], #count, #binding, 0, #info);
+            }
+        })
+    });
+    let set_count = set_numbers.len();
+    let set_count_lit = LitInt::new(&set_count.to_string(), Span::call_site());
+    Ok(quote! {
+        impl nightfall_core::descriptors::ShaderResources for #name {
+            const SETS: &'static [u32] = &[#(#set_numbers),*];
+            fn layout_builder(set: u32) -> nightfall_core::descriptors::DescriptorLayoutBuilder {
+                match set {
+                    #(#layout_arms)*
+                    _ => panic!("{} has no descriptors in set {}", stringify!(#name), set),
+                }
+            }
+            fn fill_writer(&self, writer: &mut nightfall_core::descriptors::DescriptorWriter, sets: &[ash::vk::DescriptorSet]) {
+                assert!(sets.len() >= #set_count_lit, "{} expects {} descriptor sets but {} were given", stringify!(#name), #set_count_lit, sets.len());
+                #(#writes)*
+            }
+        }
+    })
+}
@@ -0,0 +1,274 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{Attribute, Data, DeriveInput, Expr, ExprLit, Field, Fields, Ident, Lit, Meta, Type, TypePath};
+
+/// a format and the number of locations each column uses, repeated `columns`
+/// times `stride` bytes apart (matrices are one attribute per column).
+struct VertexFormat {
+    format: TokenStream,
+    columns: u32,
+    stride: u32,
+    locations_per_column: u32,
+}
+
+impl VertexFormat {
+    fn single(format: &str, size: u32) -> Self {
+        let format = Ident::new(format, Span::call_site());
+        Self { format: quote! { ash::vk::Format::#format }, columns: 1, stride: size, locations_per_column: 1 }
+    }
+    fn matrix(format: &str, columns: u32, stride: u32) -> Self {
+        let format = Ident::new(format, Span::call_site());
+        Self { format: quote! { ash::vk::Format::#format }, columns, stride, locations_per_column: 1 }
+    }
+}
+
+/// the segments of a plain path without generic arguments or a qualified self.
+fn path_segments(path: &TypePath) -> Option<Vec<String>> {
+    if path.qself.is_some() || path.path.segments.iter().any(|segment| !segment.arguments.is_none()) {
+        return None;
+    }
+    Some(path.path.segments.iter().map(|segment| segment.ident.to_string()).collect())
+}
+
+/// the name of a primitive written bare or as `core::primitive::*`.
+fn primitive_name(path: &TypePath) -> Option<String> {
+    match path_segments(path)?.as_slice() {
+        [name] => Some(name.clone()),
+        [root, primitive, name] if (root == "core" || root == "std") && primitive == "primitive" => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// the name of a [`nightfall_core::glsl`] type, only recognised by its full
+/// path since a proc macro can't tell which `Vec3` a bare name refers to.
+fn glsl_name(path: &TypePath) -> Option<String> {
+    match path_segments(path)?.as_slice() {
+        [root, glsl, name] if root == "nightfall_core" && glsl == "glsl" => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// the name of a scalar type and its array length, e.g. `[f32; 3]` is `("f32", 3)`.
+fn scalar_components(ty: &Type) -> Option<(String, u32)> {
+    match ty {
+        Type::Path(path) => Some((primitive_name(path)?, 1)),
+        Type::Array(array) => {
+            let Expr::Lit(ExprLit { lit: Lit::Int(len), .. }) = &array.len else {
+                return None;
+            };
+            let (scalar, 1) = scalar_components(&array.elem)? else {
+                return None;
+            };
+            Some((scalar, len.base10_parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+fn scalar_format(scalar: &str, components: u32, normalized: bool) -> Option<VertexFormat> {
+    const CHANNELS: [&str; 4] = ["R", "G", "B", "A"];
+    let (bits, kind) = match (scalar, normalized) {
+        ("f32", false) => (32, "SFLOAT"),
+        ("f64", false) => (64, "SFLOAT"),
+        ("u32", false) => (32, "UINT"),
+        ("i32", false) => (32, "SINT"),
+        ("u16", false) => (16, "UINT"),
+        ("i16", false) => (16, "SINT"),
+        ("u8", false) => (8, "UINT"),
+        ("i8", false) => (8, "SINT"),
+        ("u16", true) => (16, "UNORM"),
+        ("i16", true) => (16, "SNORM"),
+        ("u8", true) => (8, "UNORM"),
+        ("i8", true) => (8, "SNORM"),
+        _ => return None,
+    };
+    if !(1..=4).contains(&components) {
+        return None;
+    }
+    let format = CHANNELS[..components as usize].iter().map(|channel| format!("{}{}", channel, bits)).collect::<String>();
+    let mut format = VertexFormat::single(&format!("{}_{}", format, kind), bits / 8 * components);
+    // dvec3 and dvec4 take up two locations.
+    if bits == 64 && components > 2 {
+        format.locations_per_column = 2;
+    }
+    Some(format)
+}
+
+/// infers the format of the GLSL wrapper types, plain scalars, arrays of
+/// scalars and `[[f32; N]; M]` column major matrices.
+fn infer_format(ty: &Type, normalized: bool) -> Option<VertexFormat> {
+    if let Type::Path(path) = ty {
+        let Some(name) = glsl_name(path) else {
+            return scalar_format(&primitive_name(path)?, 1, normalized);
+        };
+        let format = match name.as_str() {
+            "Vec2" => VertexFormat::single("R32G32_SFLOAT", 8),
+            "Vec3" => VertexFormat::single("R32G32B32_SFLOAT", 12),
+            "Vec4" => VertexFormat::single("R32G32B32A32_SFLOAT", 16),
+            "IVec2" => VertexFormat::single("R32G32_SINT", 8),
+            "IVec3" => VertexFormat::single("R32G32B32_SINT", 12),
+            "IVec4" => VertexFormat::single("R32G32B32A32_SINT", 16),
+            "UVec2" => VertexFormat::single("R32G32_UINT", 8),
+            "UVec3" => VertexFormat::single("R32G32B32_UINT", 12),
+            "UVec4" => VertexFormat::single("R32G32B32A32_UINT", 16),
+            "Mat2" => VertexFormat::matrix("R32G32_SFLOAT", 2, 8),
+            // the columns of `glsl::Mat3` are padded to a vec4.
+            "Mat3" => VertexFormat::matrix("R32G32B32_SFLOAT", 3, 16),
+            "Mat4" => VertexFormat::matrix("R32G32B32A32_SFLOAT", 4, 16),
+            _ => return None,
+        };
+        return (!normalized).then_some(format);
+    }
+    let Type::Array(array) = ty else {
+        return None;
+    };
+    if let Some((scalar, components)) = scalar_components(ty) {
+        return scalar_format(&scalar, components, normalized);
+    }
+    // an array of columns
+    let Expr::Lit(ExprLit { lit: Lit::Int(columns), .. }) = &array.len else {
+        return None;
+    };
+    let columns = columns.base10_parse::<u32>().ok()?;
+    let column = scalar_format_for_column(&array.elem, normalized)?;
+    if !(2..=4).contains(&columns) {
+        return None;
+    }
+    Some(VertexFormat { columns, ..column })
+}
+
+fn scalar_format_for_column(ty: &Type, normalized: bool) -> Option<VertexFormat> {
+    let (scalar, components) = scalar_components(ty)?;
+    if components < 2 {
+        return None;
+    }
+    scalar_format(&scalar, components, normalized)
+}
+
+struct VertexField<'a> {
+    field: &'a Field,
+    format: VertexFormat,
+    location: Option<u32>,
+    /// whether the format was inferred, and the size of the field is known.
+    inferred: bool,
+}
+
+fn parse_field(field: &Field) -> syn::Result<VertexField<'_>> {
+    let mut format = None;
+    let mut location = None;
+    let mut normalized = false;
+    for attr in &field.attrs {
+        if attr.path().is_ident("format") {
+            let list = attr.meta.require_list()?;
+            let tokens = &list.tokens;
+            // a bare variant name is looked up on `vk::Format`, anything else is used as is.
+            let format_tokens = match syn::parse2::<Ident>(tokens.clone()) {
+                Ok(ident) => quote! { ash::vk::Format::#ident },
+                Err(_) => tokens.clone(),
+            };
+            format = Some(format_tokens);
+        } else if attr.path().is_ident("location") {
+            let Meta::NameValue(value) = &attr.meta else {
+                return Err(syn::Error::new_spanned(attr, "expected `#[location = N]`"));
+            };
+            let Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) = &value.value else {
+                return Err(syn::Error::new_spanned(&value.value, "expected an integer location"));
+            };
+            location = Some(lit.base10_parse::<u32>()?);
+        } else if attr.path().is_ident("normalized") {
+            attr.meta.require_path_only()?;
+            normalized = true;
+        }
+    }
+    let inferred = format.is_none();
+    let format = match format {
+        Some(format) => VertexFormat { format, columns: 1, stride: 0, locations_per_column: 1 },
+        None => infer_format(&field.ty, normalized).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &field.ty,
+                format!(
+                    "can not infer a vertex format for `{}`, add #[format(...)]. Formats are only inferred for primitives, arrays of them and the GLSL types written by their full path, e.g. `nightfall_core::glsl::Vec3`",
+                    field.ty.to_token_stream()
+                ),
+            )
+        })?,
+    };
+    Ok(VertexField { field, format, location, inferred })
+}
+
+fn is_per_instance(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("per_instance"))
+}
+
+pub fn impl_vertex(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let fields = match &ast.data {
+        Data::Struct(structure) => match &structure.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(ast, "Vertex can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(ast, "Vertex can only be derived for structs")),
+    };
+    let mut next_location = 0;
+    let mut used: Vec<(u32, &Ident)> = vec![];
+    let mut attributes = vec![];
+    let mut size_checks = vec![];
+    for field in fields {
+        let VertexField { field, format, location, inferred } = parse_field(field)?;
+        let ident = field.ident.as_ref().unwrap();
+        if inferred {
+            let ty = &field.ty;
+            let size = (format.columns * format.stride) as usize;
+            size_checks.push(quote! {
+                assert!(
+                    ::core::mem::size_of::<#ty>() == #size,
+                    concat!("`", stringify!(#ty), "` does not have the size of the vertex format inferred for `", stringify!(#ident), "`, add #[format(...)]")
+                );
+            });
+        }
+        let location = location.unwrap_or(next_location);
+        let count = format.columns * format.locations_per_column;
+        if let Some((_, other)) = used.iter().find(|(taken, _)| (location..location + count).contains(taken)) {
+            return Err(syn::Error::new_spanned(field, format!("location {} is already used by `{}`", location, other)));
+        }
+        used.extend((location..location + count).map(|taken| (taken, ident)));
+        next_location = location + count;
+        let vk_format = &format.format;
+        for column in 0..format.columns {
+            let location = location + column * format.locations_per_column;
+            let column_offset = column * format.stride;
+            attributes.push(quote! {
+                ash::vk::VertexInputAttributeDescription {
+                    binding,
+                    location: #location,
+                    offset: ::core::mem::offset_of!(Self, #ident) as u32 + #column_offset,
+                    format: #vk_format,
+                }
+            });
+        }
+    }
+    let input_rate = if is_per_instance(&ast.attrs) {
+        quote! { ash::vk::VertexInputRate::INSTANCE }
+    } else {
+        quote! { ash::vk::VertexInputRate::VERTEX }
+    };
+    Ok(quote! {
+        const _: () = {
+            #(#size_checks)*
+        };
+        impl #impl_generics nightfall_core::vertex::Vertex for #name #ty_generics #where_clause {
+            const INPUT_RATE: ash::vk::VertexInputRate = #input_rate;
+            fn binding(binding: u32, rate: ash::vk::VertexInputRate) -> ash::vk::VertexInputBindingDescription {
+                ash::vk::VertexInputBindingDescription {
+                    binding,
+                    input_rate: rate,
+                    stride: ::core::mem::size_of::<Self>() as u32,
+                }
+            }
+            fn attributes(binding: u32) -> Vec<ash::vk::VertexInputAttributeDescription> {
+                vec![#(#attributes),*]
+            }
+        }
+    })
+}
@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+# Rebuilds the precompiled SPIR-V next to the GLSL sources. Run from anywhere
+# after editing a shader and commit the .spv files along with the sources.
+# Needs glslangValidator on PATH, or set GLSLANG to its path.
+set -euo pipefail
+
+GLSLANG="${GLSLANG:-glslangValidator}"
+ROOT="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+
+compile() {
+    local dir="$1" source="$2" output="$3"
+    shift 3
+    echo "$dir/$output"
+    (cd "$ROOT/$dir" && "$GLSLANG" -V "$@" "$source" -o "$output")
+}
+
+primitives=src/primitives/shaders
+for shader in scan add radix_count radix_scatter compact; do
+    compile "$primitives" "$shader.comp" "$shader.spv" --target-env vulkan1.0
+done
+for shader in scan radix_scatter; do
+    compile "$primitives" "$shader.comp" "${shader}_subgroup.spv" --target-env vulkan1.1 -DSUBGROUPS
+done
+
+image=src/image/shaders
+compile "$image" downsample.comp downsample.spv --target-env vulkan1.0
+compile "$image" downsample.comp downsample_uint.spv --target-env vulkan1.0 -DUINT
+compile "$image" downsample.comp downsample_sint.spv --target-env vulkan1.0 -DSINT
@@ -1,4 +1,4 @@
-use std::{ops::{Add, Index, IndexMut, Range}, os::raw::c_void, ptr::NonNull, sync::Arc};
+use std::{ops::{Add, Index, IndexMut, Range}, os::raw::c_void, ptr::NonNull, sync::{Arc, PoisonError}};
 
 use ash::vk;
 
@@ -18,38 +18,36 @@ impl<T> MappedMemory<T> {
     ///
     /// [`MappedMemory`] is made to be used with buffers, if you want to work with [`DeviceMemory`] look into [`MappingState`].
     pub fn new(buffer: Arc<Buffer>, range: Range<usize>) -> Result<Self, VulkanError> {
-        // check if memory has already been mapped on this buffers memory
-        if let Some(mapped) = unsafe { buffer.memory.mapping_state.as_ptr().as_mut().unwrap() } {
-            // If the memory has already been mapped then try to see if its within range
-            if mapped.end() <= mapped.offset() + range.end as u64 {
+        let ptr = {
+            // the lock is held until the mapping is recorded so two threads can not map the same memory
+            let mut mapping_state = buffer.memory.mapping_state.lock().unwrap_or_else(PoisonError::into_inner);
+            // check if memory has already been mapped on this buffers memory
+            if let Some(mapped) = mapping_state.as_ref() {
+                // If the memory has already been mapped then try to see if its within range
+                if mapped.end() <= mapped.offset() + range.end as u64 {
+                    unsafe { NonNull::new_unchecked(mapped.ptr().as_ptr().add(range.start) as *mut _ as _) }
+                } else { // If it's not within range return error
+                    return Err(VulkanError::MemoryMapFailed);
+                }
+            } else {
+                // If it's not mapped then proceed like normal
                 unsafe {
-                    return Ok(
-                        MappedMemory {
-                            ptr: NonNull::new_unchecked(mapped.ptr().as_ptr().add(range.start) as *mut _ as _), 
-                            range,
-                            buffer,
-                    })
+                    let ptr = buffer.device.device.map_memory(buffer.memory.memory(), 
+                    range.start as u64, 
+                    range.end as u64, 
+                    vk::MemoryMapFlags::empty())
+                    .map_err(VulkanError::from)?;
+                    let mapped = NonNull::new_unchecked(ptr).cast::<T>();
+                    *mapping_state = Some(MappingState::new(mapped.cast::<_>(), range.start as u64..range.end as u64));
+                    mapped
                 }
-            } else { // If it's not within range return error
-                return Err(VulkanError::MemoryMapFailed);
-            }
-        } else {
-            // If it's not mapped then proceed like normal
-            unsafe {
-                let ptr = buffer.device.device.map_memory(buffer.memory.memory(), 
-                range.start as u64, 
-                range.end as u64, 
-                vk::MemoryMapFlags::empty())
-                .map_err(VulkanError::from)?;
-                let mapped = NonNull::new_unchecked(ptr).cast::<T>();
-                buffer.memory.mapping_state.set(Some(MappingState::new(mapped.cast::<_>(), range.start as u64..range.end as u64)));
-                Ok(MappedMemory {
-                    ptr: mapped, 
-                    range,
-                    buffer,
-                })
             }
-        }
+        };
+        Ok(MappedMemory {
+            ptr, 
+            range,
+            buffer,
+        })
     }
     pub unsafe fn offset_from_start(&self, ptr: usize) -> usize {
         let selfptr = self.ptr.as_ptr() as *mut _ as *mut T as usize;
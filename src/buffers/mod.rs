@@ -4,7 +4,7 @@ use ash::{vk::{self, Handle}, vk_bitflags_wrapped};
 mod map;
 pub use map::*;
 
-use crate::{device::LogicalDevice, error::VulkanError, memory::{DeviceMemory, DevicePointer}, AsNfptr, NfPtr};
+use crate::{descriptors::{DescriptorBufferInfo, UniformBufferInfo}, device::{DeferredHandle, LogicalDevice}, error::VulkanError, glsl::Std140, memory::{DeviceMemory, DevicePointer}, AsNfptr, NfPtr};
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkBufferUsageFlagBits.html>"]
@@ -182,6 +182,12 @@ impl Buffer {
     pub fn map_memory<T>(self: Arc<Self>, range: Range<usize>) -> Result<MappedMemory<T>, VulkanError> {
         MappedMemory::new(self.clone(), range)
     }
+    /// describes a `T` at `offset` for a uniform buffer descriptor, `T` has to
+    /// follow std140 as uniform blocks do.
+    pub fn uniform_buffer_info<T: Std140>(&self, offset: usize) -> UniformBufferInfo {
+        assert!(offset + T::SIZE <= self.size, "a {} byte uniform at offset {} does not fit in a {} byte buffer", T::SIZE, offset, self.size);
+        unsafe { UniformBufferInfo::from_raw(DescriptorBufferInfo { buffer: self.handle, offset: offset as u64, range: T::SIZE as u64 }) }
+    }
     pub fn offset(&self, offset: usize) -> BufferOffset {
         unsafe { BufferOffset::from_raw(self.handle, offset as u64) }
     }
@@ -189,7 +195,7 @@ impl Buffer {
 
 impl Drop for Buffer {
     fn drop(&mut self) {
-        unsafe { self.device.device.destroy_buffer(self.handle, None) };
+        self.device.destroy_deferred(DeferredHandle::Buffer(self.handle));
     }
 }
 
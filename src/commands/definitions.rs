@@ -2,7 +2,7 @@ use std::marker::PhantomData;
 
 use ash::{vk::{self, ClearValue, Framebuffer, Rect2D, RenderPass}, vk_bitflags_wrapped};
 
-use crate::{image::ImageSubresourceLayers, PNext};
+use crate::{image::{ImageAspectFlags, ImageSubresourceLayers}, PNext};
 
 #[repr(C)]
 #[cfg_attr(feature = "debug", derive(Debug))]
@@ -20,6 +20,20 @@ pub enum ClearColorValue {
     I32([i32; 4]),
     U32([u32; 4])
 }
+impl From<&ClearColorValue> for vk::ClearColorValue {
+    fn from(value: &ClearColorValue) -> Self {
+        match value {
+            ClearColorValue::F32(float32) => vk::ClearColorValue { float32: *float32 },
+            ClearColorValue::I32(int32) => vk::ClearColorValue { int32: *int32 },
+            ClearColorValue::U32(uint32) => vk::ClearColorValue { uint32: *uint32 },
+        }
+    }
+}
+impl From<&ClearDepthStencilValue> for vk::ClearDepthStencilValue {
+    fn from(value: &ClearDepthStencilValue) -> Self {
+        Self { depth: value.depth, stencil: value.stencil }
+    }
+}
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub enum ClearValues {
@@ -244,4 +258,78 @@ pub struct BufferImageCopy {
     pub image_subresource: ImageSubresourceLayers,
     pub image_offset: [i32; 3],
     pub image_extent: [u32; 3],
+}
+impl From<ImageCopy> for vk::ImageCopy {
+    fn from(value: ImageCopy) -> Self {
+        unsafe { std::mem::transmute::<ImageCopy, vk::ImageCopy>(value) }
+    }
+}
+impl From<BufferImageCopy> for vk::BufferImageCopy {
+    fn from(value: BufferImageCopy) -> Self {
+        unsafe { std::mem::transmute::<BufferImageCopy, vk::BufferImageCopy>(value) }
+    }
+}
+#[repr(C)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone, Default)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkImageBlit.html>"]
+pub struct ImageBlit {
+    pub src_subresource: ImageSubresourceLayers,
+    pub src_offsets: [[i32; 3]; 2],
+    pub dst_subresource: ImageSubresourceLayers,
+    pub dst_offsets: [[i32; 3]; 2],
+}
+impl From<ImageBlit> for vk::ImageBlit {
+    fn from(value: ImageBlit) -> Self {
+        unsafe { std::mem::transmute::<ImageBlit, vk::ImageBlit>(value) }
+    }
+}
+#[repr(C)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone, Default)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkImageResolve.html>"]
+pub struct ImageResolve {
+    pub src_subresource: ImageSubresourceLayers,
+    pub src_offset: [i32; 3],
+    pub dst_subresource: ImageSubresourceLayers,
+    pub dst_offset: [i32; 3],
+    pub extent: [u32; 3],
+}
+impl From<ImageResolve> for vk::ImageResolve {
+    fn from(value: ImageResolve) -> Self {
+        unsafe { std::mem::transmute::<ImageResolve, vk::ImageResolve>(value) }
+    }
+}
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkClearAttachment.html>"]
+pub struct ClearAttachment {
+    pub aspect_mask: ImageAspectFlags,
+    /// the index into the subpass color attachments, ignored for depth and stencil.
+    pub color_attachment: u32,
+    pub clear_value: ClearValues,
+}
+impl From<&ClearAttachment> for vk::ClearAttachment {
+    fn from(value: &ClearAttachment) -> Self {
+        Self {
+            aspect_mask: vk::ImageAspectFlags::from_raw(value.aspect_mask.0),
+            color_attachment: value.color_attachment,
+            clear_value: vk::ClearValue::from(&value.clear_value),
+        }
+    }
+}
+#[repr(C)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone, Default)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkClearRect.html>"]
+pub struct ClearRect {
+    pub offset: [i32; 2],
+    pub extent: [u32; 2],
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+}
+impl From<ClearRect> for vk::ClearRect {
+    fn from(value: ClearRect) -> Self {
+        unsafe { std::mem::transmute::<ClearRect, vk::ClearRect>(value) }
+    }
 }
\ No newline at end of file
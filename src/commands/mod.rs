@@ -1,8 +1,17 @@
-use std::{mem::MaybeUninit, ops::Sub, ptr::NonNull, sync::Arc};
+use std::{cell::{Cell, RefCell}, marker::PhantomData, mem::MaybeUninit, ops::Sub, ptr::NonNull, sync::Arc};
 
 use ash::{vk::{self, CopyBufferInfo2}, vk_bitflags_wrapped};
 mod definitions;
+mod pool_set;
+mod recording;
+mod secondary;
+mod tracking;
+mod transfer;
 pub use definitions::*;
+pub use pool_set::*;
+pub use recording::*;
+pub use secondary::*;
+pub use tracking::*;
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 #[repr(transparent)]
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkCommandBufferLevel.html>"]
@@ -30,7 +39,7 @@ impl CommandBufferResetFlags {
     #[doc = "Release resources owned by the buffer"]
     pub const RELEASE_RESOURCES: Self = Self(0b1);
 }
-use crate::{barriers::{BufferMemoryBarrier, ImageMemoryBarrier, MemoryBarrier}, device::LogicalDevice, error::VulkanError, image::{ImageLayout, PipelineStageFlags}, memory::DependencyFlags, pipeline::shader::ShaderStageFlags, NfPtr};
+use crate::{barriers::{BufferMemoryBarrier, ImageMemoryBarrier, MemoryBarrier}, buffers::BufferOffset, device::LogicalDevice, error::VulkanError, image::{ImageLayout, PipelineStageFlags}, memory::DependencyFlags, pipeline::{graphics::{DrawIndexedIndirectCommand, DrawIndirectCommand}, layout::{PipelineLayout, PushConstants}, shader::ShaderStageFlags}, NfPtr};
 
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -68,10 +77,14 @@ impl ExactSizeIterator for CommandBufferIter {
         unsafe { (self.end.offset_from(self.start)) as usize/std::mem::size_of::<vk::CommandBuffer>() } 
     }
 }
+/// Recording into a command buffer must be externally synchronized with its pool,
+/// so allocations are `Send` but not `Sync`.
 pub struct CommandPoolAllocation {
     pub(crate) command_buffer: vk::CommandBuffer,
     pub(crate) level: CommandBufferLevel,
     pub(crate) device: Arc<LogicalDevice>,
+    pub(crate) resources: RefCell<Vec<TrackedResource>>,
+    pub(crate) _not_sync: PhantomData<Cell<()>>,
 }
 impl CommandPoolAllocation {
     #[inline]
@@ -84,9 +97,13 @@ impl CommandPoolAllocation {
     }
     #[inline]
     pub fn empty(device: Arc<LogicalDevice>) -> Self {
-        Self { command_buffer: vk::CommandBuffer::null(), level: CommandBufferLevel::PRIMARY, device }
+        Self::from_raw(device, vk::CommandBuffer::null(), CommandBufferLevel::PRIMARY)
     }
-    pub fn begin(&self, info: CommandBufferBeginInfo) -> Result<(), VulkanError> {
+    #[inline]
+    pub(crate) fn from_raw(device: Arc<LogicalDevice>, command_buffer: vk::CommandBuffer, level: CommandBufferLevel) -> Self {
+        Self { command_buffer, level, device, resources: RefCell::new(vec![]), _not_sync: PhantomData }
+    }
+    pub(crate) fn begin(&self, info: CommandBufferBeginInfo) -> Result<(), VulkanError> {
         let inheritence: Option<vk::CommandBufferInheritanceInfo> = info.p_inheritance_info.map(Into::into);
         let p_inheritance_info = if let Some(inheritence) = &inheritence {
             inheritence as *const _
@@ -106,70 +123,50 @@ impl CommandPoolAllocation {
             ).result().map_err(|err|{ VulkanError::from(err) })
         }
     }
+    /// begins recording a primary command buffer through the typed [`CommandRecorder`],
+    /// which only exposes the commands valid in the current state.
+    /// The resources retained by a previous recording are released, the GPU
+    /// must be done with them.
+    pub fn record(&mut self, flags: CommandBufferUsageFlags) -> Result<CommandRecorder<'_>, VulkanError> {
+        self.resources.get_mut().clear();
+        CommandRecorder::begin(self, flags)
+    }
     #[inline]
-    pub fn end(&self) -> Result<(), VulkanError> {
+    pub(crate) fn end(&self) -> Result<(), VulkanError> {
         unsafe { (self.device.fns.v1_0.end_command_buffer)(
                     self.get_command_buffer()
                 ).result().map_err(VulkanError::from)
             }
     }
+    /// returns the command buffer to the initial state, releasing what it retained.
     #[inline]
     pub fn reset(&self, flags: CommandBufferResetFlags) -> Result<(), VulkanError> {
+        self.resources.borrow_mut().clear();
         unsafe { self.device.device.reset_command_buffer(self.command_buffer, vk::CommandBufferResetFlags::from_raw(flags.0)).map_err(VulkanError::from) }
     }
     #[inline]
-    pub fn push_constants<P>(&self, layout: vk::PipelineLayout, stage_flags: ShaderStageFlags, offset: u32, constant: &P) {
+    pub(crate) fn push_constants<P>(&self, layout: vk::PipelineLayout, stage_flags: ShaderStageFlags, offset: u32, constant: &P) {
         self.device.push_constants(self.command_buffer, layout, stage_flags, offset, constant)
     }
-    pub fn set_viewport(&self, first_viewport: u32, viewports: &[vk::Viewport]) {
+    /// pushes a derived push constant block added to `layout` at `offset`,
+    /// with the stages of every range it overlaps.
+    pub(crate) fn push_constant_block<P: PushConstants>(&self, layout: &PipelineLayout, offset: u32, constants: &P) {
+        let bytes = unsafe { std::slice::from_raw_parts(constants as *const P as *const u8, std::mem::size_of::<P>()) };
+        for update in layout.push_constant_updates(offset, bytes.len() as u32) {
+            let range = (update.offset - offset) as usize..(update.offset - offset + update.size) as usize;
+            unsafe { self.device.device.cmd_push_constants(self.command_buffer, layout.layout, update.stage_flags, update.offset, &bytes[range]) };
+        }
+    }
+    pub(crate) fn set_viewport(&self, first_viewport: u32, viewports: &[vk::Viewport]) {
         unsafe { self.device.device.cmd_set_viewport(self.command_buffer, first_viewport, viewports) }
     }
-    pub fn set_scissor(&self, first_scissor: u32, scissors: &[vk::Rect2D]) {
+    pub(crate) fn set_scissor(&self, first_scissor: u32, scissors: &[vk::Rect2D]) {
         unsafe { self.device.device.cmd_set_scissor(self.command_buffer, first_scissor, scissors) }
     }
-    pub fn copy_buffer(&self, src: vk::Buffer, dst: vk::Buffer, regions: &[BufferCopy]) {
-        unsafe { 
-            self.device.device.cmd_copy_buffer(
-                self.command_buffer, 
-                src, 
-                dst, 
-                std::mem::transmute_copy::<&[BufferCopy], &[vk::BufferCopy]>(&regions)
-            ) 
-        };
-    }
-    pub fn copy_image(&self, src: vk::Image, dst: vk::Image, src_layout: ImageLayout, dst_layout: ImageLayout, regions: &[ImageCopy]) {
-        unsafe {
-            self.device.device.cmd_copy_image(
-                self.command_buffer, 
-                src, 
-                vk::ImageLayout::from_raw(src_layout.0), 
-                dst, 
-                vk::ImageLayout::from_raw(dst_layout.0), 
-                std::mem::transmute_copy::<&[ImageCopy], &[vk::ImageCopy]>(&regions),
-            );
-        }
-    }
-    pub fn buffer_to_image(&self, src: vk::Buffer, dst: vk::Image, layout: ImageLayout, regions: &[BufferImageCopy]) {
-        unsafe {
-            self.device.device.cmd_copy_buffer_to_image(
-                self.command_buffer, 
-                src, 
-                dst, 
-                vk::ImageLayout::from_raw(layout.0), 
-                std::mem::transmute_copy::<&[BufferImageCopy], &[vk::BufferImageCopy]>(&regions)
-            );
-        }
-    }
-    // vkCmdCopyBufferToImage, 
-    // vkCmdCopyBufferToImage2, 
-    // vkCmdCopyBufferToImage2KHR, 
-    // vkCmdCopyImageToBuffer, 
-    // vkCmdCopyImageToBuffer2, 
-    // vkCmdCopyImageToBuffer2KHR, 
-    pub fn begin_render_pass(&self, info: vk::RenderPassBeginInfo, subpass_contents: SubpassContents) -> Result<(), VulkanError> {
+    pub(crate) fn begin_render_pass(&self, info: vk::RenderPassBeginInfo, subpass_contents: SubpassContents) -> Result<(), VulkanError> {
         // let begin_info: vk::RenderPassBeginInfo = info.into();
         if subpass_contents == SubpassContents::INLINE_AND_SECONDARY_COMMAND_BUFFERS && !self.device.enabled_extensions.ext_nested_command_buffer {
-            return Err(VulkanError::ExtensionNotPresent);
+            return Err(VulkanError::ExtensionNotPresent(vec!["VK_EXT_nested_command_buffer"]));
         }
         let contents: vk::SubpassContents = subpass_contents.into();
         unsafe {
@@ -181,10 +178,17 @@ impl CommandPoolAllocation {
         }
         Ok(())
     }
-    pub fn end_render_pass(&self) {
+    /// executes secondary command buffers, taking over the resources they retain.
+    pub(crate) fn execute_secondary<A, S>(&self, command_buffers: &[ExecutableSecondaryCommandBuffer<A, S>]) {
+        debug_assert!(self.level == CommandBufferLevel::PRIMARY, "secondary command buffers can only be executed from a primary command buffer");
+        let handles = command_buffers.iter().map(|buffer| buffer.get_command_buffer()).collect::<smallvec::SmallVec<[_; 4]>>();
+        unsafe { self.device.device.cmd_execute_commands(self.command_buffer, &handles) };
+        self.resources.borrow_mut().extend(command_buffers.iter().flat_map(|buffer| buffer.resources.iter().cloned()));
+    }
+    pub(crate) fn end_render_pass(&self) {
         unsafe { self.device.device.cmd_end_render_pass(self.command_buffer) };
     }
-    pub fn pipeline_barrier(&self, 
+    pub(crate) fn pipeline_barrier(&self, 
         src_stage_mask: PipelineStageFlags, 
         dst_stage_mask: PipelineStageFlags, 
         dependency_flags: DependencyFlags,
@@ -206,35 +210,67 @@ impl CommandPoolAllocation {
             )
         }
     }
-    pub unsafe fn bind_vertex_nfptrs(&self, first_binding: u32, nfptr: &[NfPtr]) {
+    pub(crate) unsafe fn bind_vertex_nfptrs(&self, first_binding: u32, nfptr: &[NfPtr]) {
         let mut buffers = vec![];
         let mut offsets = vec![];
         for ptr in nfptr {
             buffers.push(ptr.buffer());
             offsets.push(ptr.offset() as u64);
         }
-        self.bind_vertex_buffers(0, &buffers, &offsets);
+        self.bind_vertex_buffers(first_binding, &buffers, &offsets);
     }
-    pub unsafe fn bind_index_nfptr(&self, first_binding: u32, nfptr: NfPtr, index_type: vk::IndexType) {
+    pub(crate) unsafe fn bind_index_nfptr(&self, nfptr: NfPtr, index_type: vk::IndexType) {
         self.bind_index_buffers(nfptr.buffer(), nfptr.offset() as u64, index_type)
     }
-    pub fn bind_vertex_buffers(&self, first_binding: u32, buffers: &[vk::Buffer], offsets: &[vk::DeviceSize]) {
+    pub(crate) fn bind_vertex_buffers(&self, first_binding: u32, buffers: &[vk::Buffer], offsets: &[vk::DeviceSize]) {
         unsafe { self.device.device.cmd_bind_vertex_buffers(self.command_buffer, first_binding, buffers, offsets) }
     }
-    pub fn bind_index_buffers(&self, buffer: vk::Buffer, offset: vk::DeviceSize, index_type: vk::IndexType) {
+    pub(crate) fn bind_index_buffers(&self, buffer: vk::Buffer, offset: vk::DeviceSize, index_type: vk::IndexType) {
         unsafe { self.device.device.cmd_bind_index_buffer(self.command_buffer, buffer, offset, index_type); }
     }
-    pub fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) {
+    pub(crate) fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) {
         unsafe { self.device.device.cmd_draw(self.command_buffer, vertex_count, instance_count, first_vertex, first_instance) };
     }
-    pub fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) {
+    pub(crate) fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) {
         unsafe { self.device.device.cmd_draw_indexed(self.command_buffer, index_count, instance_count, first_index, vertex_offset, first_instance) };
         
     }
+    /// Draws `draw_count` tightly packed [`DrawIndirectCommand`]s starting at
+    /// `indirect`, more than one needs the `multi_draw_indirect` feature.
+    pub(crate) fn draw_indirect(&self, indirect: BufferOffset, draw_count: u32) {
+        debug_assert!(draw_count <= 1 || self.device.enabled_features.multi_draw_indirect, "multi_draw_indirect is not enabled");
+        unsafe { self.device.device.cmd_draw_indirect(self.command_buffer, indirect.handle, indirect.offset, draw_count, std::mem::size_of::<DrawIndirectCommand>() as u32) };
+    }
+    /// Draws `draw_count` tightly packed [`DrawIndexedIndirectCommand`]s starting
+    /// at `indirect`, more than one needs the `multi_draw_indirect` feature.
+    pub(crate) fn draw_indexed_indirect(&self, indirect: BufferOffset, draw_count: u32) {
+        debug_assert!(draw_count <= 1 || self.device.enabled_features.multi_draw_indirect, "multi_draw_indirect is not enabled");
+        unsafe { self.device.device.cmd_draw_indexed_indirect(self.command_buffer, indirect.handle, indirect.offset, draw_count, std::mem::size_of::<DrawIndexedIndirectCommand>() as u32) };
+    }
+    /// Like [`draw_indirect`](Self::draw_indirect) with the draw count read from
+    /// a `u32` at `count` and clamped to `max_draw_count`. Needs the
+    /// `draw_indirect_count` feature of Vulkan 1.2 or `VK_KHR_draw_indirect_count`.
+    pub(crate) fn draw_indirect_count(&self, indirect: BufferOffset, count: BufferOffset, max_draw_count: u32) {
+        debug_assert!(self.device.enabled_features.draw_indirect_count, "draw_indirect_count is not enabled");
+        unsafe { self.device.cmd_draw_indirect_count(self.command_buffer, indirect, count, max_draw_count, std::mem::size_of::<DrawIndirectCommand>() as u32) };
+    }
+    /// Like [`draw_indexed_indirect`](Self::draw_indexed_indirect) with the draw
+    /// count read from a `u32` at `count` and clamped to `max_draw_count`. Needs
+    /// the `draw_indirect_count` feature of Vulkan 1.2 or `VK_KHR_draw_indirect_count`.
+    pub(crate) fn draw_indexed_indirect_count(&self, indirect: BufferOffset, count: BufferOffset, max_draw_count: u32) {
+        debug_assert!(self.device.enabled_features.draw_indirect_count, "draw_indirect_count is not enabled");
+        unsafe { self.device.cmd_draw_indexed_indirect_count(self.command_buffer, indirect, count, max_draw_count, std::mem::size_of::<DrawIndexedIndirectCommand>() as u32) };
+    }
+    pub(crate) fn dispatch_indirect(&self, indirect: BufferOffset) {
+        unsafe { self.device.device.cmd_dispatch_indirect(self.command_buffer, indirect.handle, indirect.offset) };
+    }
 }
+/// Command pools must be externally synchronized, so they can be moved to
+/// another thread but not shared between threads.
 pub struct CommandPool {
     device: Arc<LogicalDevice>,
-    pub(crate) command_pool: vk::CommandPool
+    pub(crate) command_pool: vk::CommandPool,
+    _not_sync: PhantomData<Cell<()>>,
 }
 impl CommandPool {
     pub fn new(device: Arc<LogicalDevice>, flags: CommandPoolCreateFlags, family_index: u32) -> Result<Self, VulkanError> {
@@ -253,7 +289,8 @@ impl CommandPool {
         unsafe {
             Ok(Self { 
                 device,
-                command_pool: output.assume_init() 
+                command_pool: output.assume_init(),
+                _not_sync: PhantomData,
             })
         }
     }
@@ -283,11 +320,12 @@ impl CommandPool {
         };
         let device = self.device.clone();
 
-        Ok(out.into_iter().map(move |command_buffer| CommandPoolAllocation {
-            command_buffer,
-            device: device.clone(),
-            level,
-        }))
+        Ok(out.into_iter().map(move |command_buffer| CommandPoolAllocation::from_raw(device.clone(), command_buffer, level)))
+    }
+    /// returns every command buffer allocated from the pool to the initial state.
+    pub fn reset(&self, release_resources: bool) -> Result<(), VulkanError> {
+        let flags = if release_resources { vk::CommandPoolResetFlags::RELEASE_RESOURCES } else { vk::CommandPoolResetFlags::empty() };
+        unsafe { self.device.device.reset_command_pool(self.command_pool, flags).map_err(VulkanError::from) }
     }
     pub unsafe fn free_command_buffers(&self, command_buffers: &[vk::CommandBuffer]) {
         (self.device.fns.v1_0.free_command_buffers)(
@@ -0,0 +1,182 @@
+use std::{borrow::Borrow, collections::{hash_map::Entry, HashMap}, marker::PhantomData, ops::Deref, sync::{Arc, Mutex, PoisonError}, thread::{self, ThreadId}};
+
+use ash::vk;
+
+use crate::{device::LogicalDevice, error::VulkanError};
+
+use super::{CommandBufferLevel, CommandBufferUsageFlags, CommandPool, CommandPoolAllocation, CommandPoolCreateFlags, CommandRecorder, RecordedSecondaryCommandBuffer, TrackedResource};
+
+struct FramePool {
+    pool: CommandPool,
+    // command buffers allocated so far, indexed by level, reused once the pool is reset
+    buffers: [Vec<vk::CommandBuffer>; 2],
+    used: [usize; 2],
+}
+impl FramePool {
+    fn next(&mut self, level: CommandBufferLevel) -> Result<vk::CommandBuffer, VulkanError> {
+        let slot = level.as_raw() as usize;
+        if self.used[slot] == self.buffers[slot].len() {
+            let allocation = unsafe { self.pool.allocate_command_buffers(level, 1)?.next().unwrap() };
+            self.buffers[slot].push(allocation.command_buffer);
+        }
+        let command_buffer = self.buffers[slot][self.used[slot]];
+        self.used[slot] += 1;
+        Ok(command_buffer)
+    }
+    fn reset(&mut self) -> Result<(), VulkanError> {
+        self.pool.reset(false)?;
+        self.used = [0, 0];
+        Ok(())
+    }
+}
+
+/// Command pools created lazily for every thread and queue family that records
+/// commands, with one pool per frame in flight so a frame's command buffers are
+/// recycled together by [`CommandPoolSet::begin_frame`].
+///
+/// A pool is only ever used by the thread that created it, which is what Vulkan's
+/// external synchronization rules for command pools require. Command buffers are
+/// handed out as [`PooledCommandBuffer`]s which can not leave the recording thread;
+/// once finished they become [`RecordedCommandBuffer`]s which can be sent to the
+/// thread that submits them or executes them from a primary command buffer.
+/// Primary buffers are recorded with [`PooledCommandBuffer::record_primary`], secondary
+/// ones through a [`SecondaryCommandBuffer`](super::SecondaryCommandBuffer).
+pub struct CommandPoolSet {
+    device: Arc<LogicalDevice>,
+    flags: CommandPoolCreateFlags,
+    frames_in_flight: usize,
+    frame: usize,
+    pools: Mutex<HashMap<(ThreadId, u32), Vec<FramePool>>>,
+}
+impl CommandPoolSet {
+    pub fn new(device: Arc<LogicalDevice>, frames_in_flight: usize) -> Self {
+        Self::with_flags(device, frames_in_flight, CommandPoolCreateFlags::TRANSIENT)
+    }
+    pub fn with_flags(device: Arc<LogicalDevice>, frames_in_flight: usize, flags: CommandPoolCreateFlags) -> Self {
+        assert!(frames_in_flight > 0, "a command pool set needs at least one frame in flight");
+        Self {
+            device,
+            flags,
+            frames_in_flight,
+            frame: 0,
+            pools: Mutex::new(HashMap::new()),
+        }
+    }
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames_in_flight
+    }
+    #[inline]
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+    /// switches to the pools of `frame_index` and resets them, returning every command
+    /// buffer allocated from them during that frame. The GPU must be done with the
+    /// frame's command buffers, e.g. by waiting on its fence first.
+    pub fn begin_frame(&mut self, frame_index: usize) -> Result<(), VulkanError> {
+        self.frame = frame_index % self.frames_in_flight;
+        let pools = self.pools.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for frames in pools.values_mut() {
+            frames[self.frame].reset()?;
+        }
+        Ok(())
+    }
+    /// a command buffer from the calling thread's pool for `queue_family_index`.
+    pub fn allocate(&self, queue_family_index: u32, level: CommandBufferLevel) -> Result<PooledCommandBuffer<'_>, VulkanError> {
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
+        let frames = match pools.entry((thread::current().id(), queue_family_index)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let frames = (0..self.frames_in_flight)
+                    .map(|_| Ok(FramePool {
+                        pool: CommandPool::new(self.device.clone(), self.flags, queue_family_index)?,
+                        buffers: [vec![], vec![]],
+                        used: [0, 0],
+                    }))
+                    .collect::<Result<Vec<_>, VulkanError>>()?;
+                entry.insert(frames)
+            }
+        };
+        let command_buffer = frames[self.frame].next(level)?;
+        Ok(PooledCommandBuffer {
+            allocation: CommandPoolAllocation::from_raw(self.device.clone(), command_buffer, level),
+            _marker: PhantomData,
+        })
+    }
+    #[inline]
+    pub fn primary(&self, queue_family_index: u32) -> Result<PooledCommandBuffer<'_>, VulkanError> {
+        self.allocate(queue_family_index, CommandBufferLevel::PRIMARY)
+    }
+    #[inline]
+    pub fn secondary(&self, queue_family_index: u32) -> Result<PooledCommandBuffer<'_>, VulkanError> {
+        self.allocate(queue_family_index, CommandBufferLevel::SECONDARY)
+    }
+}
+
+/// A command buffer from a [`CommandPoolSet`], valid until the next
+/// [`CommandPoolSet::begin_frame`] for the frame it was allocated in.
+pub struct PooledCommandBuffer<'a> {
+    allocation: CommandPoolAllocation,
+    // tied to the set and to the thread owning the pool
+    _marker: PhantomData<(&'a CommandPoolSet, *const ())>,
+}
+impl<'a> PooledCommandBuffer<'a> {
+    /// records a primary command buffer with `record` and ends it, giving a
+    /// handle that may be sent to the thread submitting it.
+    pub fn record_primary(mut self, flags: CommandBufferUsageFlags, record: impl FnOnce(&mut CommandRecorder<'_>)) -> Result<RecordedCommandBuffer<'a>, VulkanError> {
+        let mut recorder = self.allocation.record(flags)?;
+        record(&mut recorder);
+        recorder.end()?;
+        Ok(self.into_recorded())
+    }
+    /// ends recording, giving a handle that may be sent to other threads.
+    pub(crate) fn finish(self) -> Result<RecordedCommandBuffer<'a>, VulkanError> {
+        self.allocation.end()?;
+        Ok(self.into_recorded())
+    }
+    fn into_recorded(self) -> RecordedCommandBuffer<'a> {
+        RecordedCommandBuffer {
+            command_buffer: self.allocation.command_buffer,
+            level: self.allocation.level,
+            resources: self.allocation.take_resources(),
+            _set: PhantomData,
+        }
+    }
+}
+impl Deref for PooledCommandBuffer<'_> {
+    type Target = CommandPoolAllocation;
+    fn deref(&self) -> &Self::Target {
+        &self.allocation
+    }
+}
+impl Borrow<CommandPoolAllocation> for PooledCommandBuffer<'_> {
+    fn borrow(&self) -> &CommandPoolAllocation {
+        &self.allocation
+    }
+}
+
+/// A command buffer which finished recording, ready to be submitted on any thread.
+#[derive(Clone)]
+pub struct RecordedCommandBuffer<'a> {
+    command_buffer: vk::CommandBuffer,
+    level: CommandBufferLevel,
+    pub(crate) resources: Vec<TrackedResource>,
+    _set: PhantomData<&'a CommandPoolSet>,
+}
+impl RecordedCommandBuffer<'_> {
+    #[inline]
+    pub fn get_command_buffer(&self) -> vk::CommandBuffer {
+        self.command_buffer
+    }
+    #[inline]
+    pub fn level(&self) -> CommandBufferLevel {
+        self.level
+    }
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CommandPoolSet>();
+    assert_send_sync::<RecordedCommandBuffer<'static>>();
+    assert_send_sync::<RecordedSecondaryCommandBuffer<'static>>();
+};
@@ -0,0 +1,354 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use ash::vk;
+
+use crate::{buffers::{Buffer, BufferOffset}, barriers::{BufferMemoryBarrier, ImageMemoryBarrier, MemoryBarrier}, error::VulkanError, descriptors::DescriptorSetAllocation, image::{ImageLayout, ImageSubresourceRange, PipelineStageFlags, RawImage}, memory::DependencyFlags, pipeline::{compute::ComputePipeline, graphics::GraphicsPipeline, layout::{PipelineLayout, PushConstants}, shader::ShaderStageFlags}, render::Framebuffer};
+
+use super::{BufferCopy, BufferImageCopy, ClearAttachment, ClearColorValue, ClearDepthStencilValue, ClearRect, CommandBufferLevel, CommandBufferUsageFlags, CommandPoolAllocation, ExecutableSecondaryCommandBuffer, ImageBlit, ImageCopy, ImageResolve, InsideRenderPass, OutsideRenderPass, SubpassContents};
+
+/// Commands valid both inside and outside of a render pass. Every resource a
+/// command uses is retained until the command buffer's submission completes.
+macro_rules! shared_commands {
+    ($ty:ty $(, $param:ident $(: $bound:path)?)*) => {
+        impl<$($param $(: $bound)?),*> $ty {
+            #[inline]
+            pub fn get_command_buffer(&self) -> vk::CommandBuffer {
+                self.allocation().get_command_buffer()
+            }
+            /// keeps `resource` alive until the command buffer's submission completes.
+            pub fn retain<T: Send + Sync + 'static>(&self, resource: &Arc<T>) {
+                self.allocation().retain(resource)
+            }
+            pub fn set_viewport(&self, first_viewport: u32, viewports: &[vk::Viewport]) {
+                self.allocation().set_viewport(first_viewport, viewports)
+            }
+            pub fn set_scissor(&self, first_scissor: u32, scissors: &[vk::Rect2D]) {
+                self.allocation().set_scissor(first_scissor, scissors)
+            }
+            pub fn push_constants<P>(&self, layout: vk::PipelineLayout, stage_flags: ShaderStageFlags, offset: u32, constant: &P) {
+                self.allocation().push_constants(layout, stage_flags, offset, constant)
+            }
+            pub fn push_constant_block<P: PushConstants>(&self, layout: &PipelineLayout, offset: u32, constants: &P) {
+                self.allocation().push_constant_block(layout, offset, constants)
+            }
+            pub fn bind_graphics_pipeline(&self, pipeline: &Arc<GraphicsPipeline>) {
+                pipeline.bind(self.get_command_buffer());
+                self.retain(pipeline);
+            }
+            pub fn bind_compute_pipeline(&self, pipeline: &Arc<ComputePipeline>) {
+                pipeline.bind(self.get_command_buffer());
+                self.retain(pipeline);
+            }
+            pub fn bind_descriptor_sets(&self, bind_point: vk::PipelineBindPoint, layout: vk::PipelineLayout, first_set: u32, sets: &[Arc<DescriptorSetAllocation>], dynamic_offsets: &[u32]) {
+                let allocation = self.allocation();
+                let handles = sets.iter().map(|set| set.set()).collect::<smallvec::SmallVec<[_; 4]>>();
+                unsafe { allocation.device.device.cmd_bind_descriptor_sets(allocation.command_buffer, bind_point, layout, first_set, &handles, dynamic_offsets) };
+                for set in sets {
+                    self.retain(set);
+                }
+            }
+            pub fn bind_vertex_buffers(&self, first_binding: u32, buffers: &[(Arc<Buffer>, vk::DeviceSize)]) {
+                let handles = buffers.iter().map(|(buffer, _)| buffer.handle()).collect::<smallvec::SmallVec<[_; 4]>>();
+                let offsets = buffers.iter().map(|(_, offset)| *offset).collect::<smallvec::SmallVec<[_; 4]>>();
+                self.allocation().bind_vertex_buffers(first_binding, &handles, &offsets);
+                for (buffer, _) in buffers {
+                    self.retain(buffer);
+                }
+            }
+            pub fn bind_index_buffer(&self, buffer: &Arc<Buffer>, offset: vk::DeviceSize, index_type: vk::IndexType) {
+                self.allocation().bind_index_buffers(buffer.handle(), offset, index_type);
+                self.retain(buffer);
+            }
+            /// binds vertex buffers by pointer, retaining `owner`.
+            /// # Safety
+            /// every pointer must point into a buffer kept alive by `owner`.
+            pub unsafe fn bind_vertex_nfptrs<B: Send + Sync + 'static>(&self, first_binding: u32, owner: &Arc<B>, nfptrs: &[crate::NfPtr]) {
+                self.allocation().bind_vertex_nfptrs(first_binding, nfptrs);
+                self.retain(owner);
+            }
+            /// binds an index buffer by pointer, retaining `owner`.
+            /// # Safety
+            /// the pointer must point into a buffer kept alive by `owner`.
+            pub unsafe fn bind_index_nfptr<B: Send + Sync + 'static>(&self, owner: &Arc<B>, nfptr: crate::NfPtr, index_type: vk::IndexType) {
+                self.allocation().bind_index_nfptr(nfptr, index_type);
+                self.retain(owner);
+            }
+        }
+    };
+}
+
+/// Commands only valid outside of a render pass.
+macro_rules! outside_commands {
+    ($ty:ty $(, $param:ident $(: $bound:path)?)*) => {
+        impl<$($param $(: $bound)?),*> $ty {
+            pub fn copy_buffer(&self, src: &Arc<Buffer>, dst: &Arc<Buffer>, regions: &[BufferCopy]) {
+                self.allocation().copy_buffer(src.handle(), dst.handle(), regions);
+                self.retain(src);
+                self.retain(dst);
+            }
+            pub fn copy_image(&self, src: &Arc<RawImage>, dst: &Arc<RawImage>, src_layout: ImageLayout, dst_layout: ImageLayout, regions: &[ImageCopy]) {
+                self.allocation().copy_image(src.handle(), dst.handle(), src_layout, dst_layout, regions);
+                self.retain(src);
+                self.retain(dst);
+            }
+            pub fn buffer_to_image(&self, src: &Arc<Buffer>, dst: &Arc<RawImage>, layout: ImageLayout, regions: &[BufferImageCopy]) {
+                self.allocation().buffer_to_image(src.handle(), dst.handle(), layout, regions);
+                self.retain(src);
+                self.retain(dst);
+            }
+            pub fn copy_image_to_buffer(&self, src: &Arc<RawImage>, layout: ImageLayout, dst: &Arc<Buffer>, regions: &[BufferImageCopy]) {
+                self.allocation().copy_image_to_buffer(src.handle(), layout, dst.handle(), regions);
+                self.retain(src);
+                self.retain(dst);
+            }
+            pub fn blit_image(&self, src: &Arc<RawImage>, src_layout: ImageLayout, dst: &Arc<RawImage>, dst_layout: ImageLayout, regions: &[ImageBlit], filter: vk::Filter) {
+                self.allocation().blit_image(src.handle(), src_layout, dst.handle(), dst_layout, regions, filter);
+                self.retain(src);
+                self.retain(dst);
+            }
+            pub fn resolve_image(&self, src: &Arc<RawImage>, src_layout: ImageLayout, dst: &Arc<RawImage>, dst_layout: ImageLayout, regions: &[ImageResolve]) {
+                self.allocation().resolve_image(src.handle(), src_layout, dst.handle(), dst_layout, regions);
+                self.retain(src);
+                self.retain(dst);
+            }
+            pub fn fill_buffer(&self, dst: &Arc<Buffer>, offset: u64, size: u64, data: u32) {
+                self.allocation().fill_buffer(dst.handle(), offset, size, data);
+                self.retain(dst);
+            }
+            pub fn update_buffer<T: bytemuck::Pod>(&self, dst: &Arc<Buffer>, offset: u64, data: &[T]) {
+                self.allocation().update_buffer(dst.handle(), offset, data);
+                self.retain(dst);
+            }
+            pub fn clear_color_image(&self, image: &Arc<RawImage>, layout: ImageLayout, color: &ClearColorValue, ranges: &[ImageSubresourceRange]) {
+                self.allocation().clear_color_image(image.handle(), layout, color, ranges);
+                self.retain(image);
+            }
+            pub fn clear_depth_stencil_image(&self, image: &Arc<RawImage>, layout: ImageLayout, value: &ClearDepthStencilValue, ranges: &[ImageSubresourceRange]) {
+                self.allocation().clear_depth_stencil_image(image.handle(), layout, value, ranges);
+                self.retain(image);
+            }
+            pub fn pipeline_barrier(&self,
+                src_stage_mask: PipelineStageFlags,
+                dst_stage_mask: PipelineStageFlags,
+                dependency_flags: DependencyFlags,
+                memory_barriers: &[MemoryBarrier],
+                buffer_memory_barriers: &[BufferMemoryBarrier],
+                image_memory_barriers: &[ImageMemoryBarrier]) {
+                self.allocation().pipeline_barrier(src_stage_mask, dst_stage_mask, dependency_flags, memory_barriers, buffer_memory_barriers, image_memory_barriers)
+            }
+            pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+                let allocation = self.allocation();
+                unsafe { allocation.device.device.cmd_dispatch(allocation.command_buffer, x, y, z) }
+            }
+            /// dispatches the [`DispatchIndirectCommand`](crate::pipeline::compute::DispatchIndirectCommand) at `offset` in `buffer`.
+            pub fn dispatch_indirect(&self, buffer: &Arc<Buffer>, offset: vk::DeviceSize) {
+                self.allocation().dispatch_indirect(BufferOffset { handle: buffer.handle(), offset });
+                self.retain(buffer);
+            }
+        }
+    };
+}
+
+/// Commands only valid inside of a render pass.
+macro_rules! draw_commands {
+    ($ty:ty $(, $param:ident $(: $bound:path)?)*) => {
+        impl<$($param $(: $bound)?),*> $ty {
+            pub fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) {
+                self.allocation().draw(vertex_count, instance_count, first_vertex, first_instance)
+            }
+            pub fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) {
+                self.allocation().draw_indexed(index_count, instance_count, first_index, vertex_offset, first_instance)
+            }
+            /// Draws `draw_count` tightly packed [`DrawIndirectCommand`](crate::pipeline::graphics::DrawIndirectCommand)s
+            /// starting at `offset` in `buffer`, more than one needs the `multi_draw_indirect` feature.
+            pub fn draw_indirect(&self, buffer: &Arc<Buffer>, offset: vk::DeviceSize, draw_count: u32) {
+                self.allocation().draw_indirect(BufferOffset { handle: buffer.handle(), offset }, draw_count);
+                self.retain(buffer);
+            }
+            /// Draws `draw_count` tightly packed [`DrawIndexedIndirectCommand`](crate::pipeline::graphics::DrawIndexedIndirectCommand)s
+            /// starting at `offset` in `buffer`, more than one needs the `multi_draw_indirect` feature.
+            pub fn draw_indexed_indirect(&self, buffer: &Arc<Buffer>, offset: vk::DeviceSize, draw_count: u32) {
+                self.allocation().draw_indexed_indirect(BufferOffset { handle: buffer.handle(), offset }, draw_count);
+                self.retain(buffer);
+            }
+            /// Like `draw_indirect` with the draw count read from a `u32` at
+            /// `count_offset` in `count_buffer` and clamped to `max_draw_count`.
+            pub fn draw_indirect_count(&self, buffer: &Arc<Buffer>, offset: vk::DeviceSize, count_buffer: &Arc<Buffer>, count_offset: vk::DeviceSize, max_draw_count: u32) {
+                self.allocation().draw_indirect_count(BufferOffset { handle: buffer.handle(), offset }, BufferOffset { handle: count_buffer.handle(), offset: count_offset }, max_draw_count);
+                self.retain(buffer);
+                self.retain(count_buffer);
+            }
+            /// Like `draw_indexed_indirect` with the draw count read from a `u32`
+            /// at `count_offset` in `count_buffer` and clamped to `max_draw_count`.
+            pub fn draw_indexed_indirect_count(&self, buffer: &Arc<Buffer>, offset: vk::DeviceSize, count_buffer: &Arc<Buffer>, count_offset: vk::DeviceSize, max_draw_count: u32) {
+                self.allocation().draw_indexed_indirect_count(BufferOffset { handle: buffer.handle(), offset }, BufferOffset { handle: count_buffer.handle(), offset: count_offset }, max_draw_count);
+                self.retain(buffer);
+                self.retain(count_buffer);
+            }
+            pub fn clear_attachments(&self, attachments: &[ClearAttachment], rects: &[ClearRect]) {
+                self.allocation().clear_attachments(attachments, rects)
+            }
+        }
+    };
+}
+pub(crate) use {draw_commands, outside_commands, shared_commands};
+
+/// A primary command buffer in the recording state, created by
+/// [`CommandPoolAllocation::record`]. Only commands valid outside of a render
+/// pass are available; draws are recorded through the [`RenderPassScope`]
+/// returned by [`CommandRecorder::begin_render_pass`], and [`CommandRecorder::end`]
+/// can not be called while a render pass is open.
+///
+/// The recorder borrows the allocation mutably, so nothing else can record
+/// into it until recording ended:
+/// ```
+/// # use nightfall_core::commands::{CommandBufferUsageFlags, CommandPoolAllocation};
+/// # use nightfall_core::error::VulkanError;
+/// # fn record(allocation: &mut CommandPoolAllocation, framebuffer: &std::sync::Arc<nightfall_core::render::Framebuffer>, area: ash::vk::Rect2D) -> Result<(), VulkanError> {
+/// let mut recorder = allocation.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+/// let pass = recorder.begin_render_pass(framebuffer, area, &[])?;
+/// pass.draw(3, 1, 0, 0);
+/// pass.end();
+/// let executable = recorder.end()?;
+/// # Ok(()) }
+/// ```
+/// Drawing outside of a render pass doesn't compile:
+/// ```compile_fail
+/// # use nightfall_core::commands::{CommandBufferUsageFlags, CommandPoolAllocation};
+/// # use nightfall_core::error::VulkanError;
+/// # fn record(allocation: &mut CommandPoolAllocation) -> Result<(), VulkanError> {
+/// let recorder = allocation.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+/// recorder.draw(3, 1, 0, 0);
+/// # Ok(()) }
+/// ```
+/// nor does ending the command buffer while a render pass is open:
+/// ```compile_fail
+/// # use nightfall_core::commands::{CommandBufferUsageFlags, CommandPoolAllocation};
+/// # use nightfall_core::error::VulkanError;
+/// # fn record(allocation: &mut CommandPoolAllocation, framebuffer: &std::sync::Arc<nightfall_core::render::Framebuffer>, area: ash::vk::Rect2D) -> Result<(), VulkanError> {
+/// let mut recorder = allocation.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+/// let pass = recorder.begin_render_pass(framebuffer, area, &[])?;
+/// recorder.end()?;
+/// pass.draw(3, 1, 0, 0);
+/// # Ok(()) }
+/// ```
+/// nor recording after it ended:
+/// ```compile_fail
+/// # use nightfall_core::commands::{CommandBufferUsageFlags, CommandPoolAllocation};
+/// # use nightfall_core::error::VulkanError;
+/// # fn record(allocation: &mut CommandPoolAllocation) -> Result<(), VulkanError> {
+/// let recorder = allocation.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+/// let executable = recorder.end()?;
+/// recorder.dispatch(1, 1, 1);
+/// # Ok(()) }
+/// ```
+pub struct CommandRecorder<'a> {
+    allocation: &'a mut CommandPoolAllocation,
+}
+impl<'a> CommandRecorder<'a> {
+    pub(crate) fn begin(allocation: &'a mut CommandPoolAllocation, flags: CommandBufferUsageFlags) -> Result<Self, VulkanError> {
+        assert!(allocation.level() == CommandBufferLevel::PRIMARY, "secondary command buffers are recorded with SecondaryCommandBuffer");
+        let begin_info = vk::CommandBufferBeginInfo {
+            flags: vk::CommandBufferUsageFlags::from_raw(flags.0),
+            ..Default::default()
+        };
+        unsafe {
+            (allocation.device.fns.v1_0.begin_command_buffer)(allocation.get_command_buffer(), &begin_info)
+                .result()
+                .map_err(VulkanError::from)?;
+        }
+        Ok(Self { allocation })
+    }
+    #[inline]
+    fn allocation(&self) -> &CommandPoolAllocation {
+        self.allocation
+    }
+    /// executes secondary command buffers which were begun outside of a render pass.
+    pub fn execute_commands<A>(&self, command_buffers: &[ExecutableSecondaryCommandBuffer<A, OutsideRenderPass>]) {
+        self.allocation.execute_secondary(command_buffers)
+    }
+    /// begins the framebuffer's render pass with its draws recorded inline.
+    pub fn begin_render_pass(&mut self, framebuffer: &Arc<Framebuffer>, render_area: vk::Rect2D, clear_values: &[vk::ClearValue]) -> Result<RenderPassScope<'_, 'a, InlineContents>, VulkanError> {
+        self.begin_render_pass_with(framebuffer, render_area, clear_values, SubpassContents::INLINE)?;
+        Ok(RenderPassScope { recorder: self, _contents: PhantomData })
+    }
+    /// begins the framebuffer's render pass with its subpasses recorded in
+    /// secondary command buffers.
+    pub fn begin_render_pass_secondary(&mut self, framebuffer: &Arc<Framebuffer>, render_area: vk::Rect2D, clear_values: &[vk::ClearValue]) -> Result<RenderPassScope<'_, 'a, SecondaryContents>, VulkanError> {
+        self.begin_render_pass_with(framebuffer, render_area, clear_values, SubpassContents::SECONDARY_COMMAND_BUFFERS)?;
+        Ok(RenderPassScope { recorder: self, _contents: PhantomData })
+    }
+    fn begin_render_pass_with(&self, framebuffer: &Arc<Framebuffer>, render_area: vk::Rect2D, clear_values: &[vk::ClearValue], contents: SubpassContents) -> Result<(), VulkanError> {
+        let info = vk::RenderPassBeginInfo {
+            render_pass: framebuffer.render_pass().handle(),
+            framebuffer: framebuffer.handle(),
+            render_area,
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+            ..Default::default()
+        };
+        self.allocation.begin_render_pass(info, contents)?;
+        // the framebuffer holds on to its render pass.
+        self.retain(framebuffer);
+        Ok(())
+    }
+    /// ends recording, the buffer can then be submitted.
+    pub fn end(self) -> Result<ExecutableCommandBuffer<'a>, VulkanError> {
+        self.allocation.end()?;
+        Ok(ExecutableCommandBuffer { allocation: self.allocation })
+    }
+}
+shared_commands!(CommandRecorder<'_>);
+outside_commands!(CommandRecorder<'_>);
+
+/// Marks a [`RenderPassScope`] whose commands are recorded inline.
+pub struct InlineContents;
+/// Marks a [`RenderPassScope`] whose commands come from secondary command buffers.
+pub struct SecondaryContents;
+
+/// An open render pass on a [`CommandRecorder`], ended by [`RenderPassScope::end`]
+/// or when the scope is dropped.
+pub struct RenderPassScope<'r, 'a, C> {
+    recorder: &'r mut CommandRecorder<'a>,
+    _contents: PhantomData<C>,
+}
+impl<C> RenderPassScope<'_, '_, C> {
+    #[inline]
+    fn allocation(&self) -> &CommandPoolAllocation {
+        self.recorder.allocation
+    }
+    pub fn end(self) {}
+}
+impl RenderPassScope<'_, '_, InlineContents> {
+    pub fn next_subpass(&mut self) {
+        unsafe { self.allocation().device.device.cmd_next_subpass(self.allocation().command_buffer, vk::SubpassContents::INLINE) }
+    }
+}
+shared_commands!(RenderPassScope<'_, '_, InlineContents>);
+draw_commands!(RenderPassScope<'_, '_, InlineContents>);
+impl RenderPassScope<'_, '_, SecondaryContents> {
+    /// executes secondary command buffers which continue the current subpass.
+    pub fn execute_commands<A>(&self, command_buffers: &[ExecutableSecondaryCommandBuffer<A, InsideRenderPass>]) {
+        self.allocation().execute_secondary(command_buffers)
+    }
+    pub fn next_subpass(&mut self) {
+        unsafe { self.allocation().device.device.cmd_next_subpass(self.allocation().command_buffer, vk::SubpassContents::SECONDARY_COMMAND_BUFFERS) }
+    }
+}
+impl<C> Drop for RenderPassScope<'_, '_, C> {
+    fn drop(&mut self) {
+        self.recorder.allocation.end_render_pass();
+    }
+}
+
+/// A primary command buffer which finished recording.
+#[derive(Clone, Copy)]
+pub struct ExecutableCommandBuffer<'a> {
+    pub(crate) allocation: &'a CommandPoolAllocation,
+}
+impl ExecutableCommandBuffer<'_> {
+    #[inline]
+    pub fn get_command_buffer(&self) -> vk::CommandBuffer {
+        self.allocation.get_command_buffer()
+    }
+}
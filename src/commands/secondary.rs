@@ -0,0 +1,162 @@
+use std::{borrow::Borrow, marker::PhantomData, sync::Arc};
+
+use ash::vk;
+
+use crate::{buffers::{Buffer, BufferOffset}, barriers::{BufferMemoryBarrier, ImageMemoryBarrier, MemoryBarrier}, descriptors::DescriptorSetAllocation, error::VulkanError, image::{ImageLayout, ImageSubresourceRange, PipelineStageFlags, RawImage, SampleCountFlags}, memory::DependencyFlags, pipeline::{compute::ComputePipeline, graphics::GraphicsPipeline, layout::{PipelineLayout, PushConstants}, shader::ShaderStageFlags}, render::{Framebuffer, RenderPass}, swapchain::Format};
+
+use super::{draw_commands, outside_commands, shared_commands, BufferCopy, BufferImageCopy, ClearAttachment, ClearColorValue, ClearDepthStencilValue, ClearRect, CommandBufferLevel, CommandBufferUsageFlags, CommandPoolAllocation, ImageBlit, ImageCopy, ImageResolve, PooledCommandBuffer, RecordedCommandBuffer, TrackedResource};
+
+/// The render pass state a secondary command buffer continues when it is executed.
+#[derive(Clone, Copy)]
+pub enum Inheritance<'a> {
+    /// executed inside `subpass` of `render_pass`, the framebuffer is optional
+    /// but may let the driver optimize the commands.
+    RenderPass {
+        render_pass: &'a RenderPass,
+        subpass: u32,
+        framebuffer: Option<&'a Framebuffer>,
+    },
+    /// executed inside a `vkCmdBeginRendering` scope with matching attachment formats.
+    DynamicRendering {
+        view_mask: u32,
+        color_formats: &'a [Format],
+        depth_format: Format,
+        stencil_format: Format,
+        samples: SampleCountFlags,
+    },
+}
+
+/// Marks a secondary command buffer executed outside of a render pass, which
+/// records the same commands as a [`CommandRecorder`](super::CommandRecorder).
+pub struct OutsideRenderPass;
+/// Marks a secondary command buffer which continues a render pass and records draws.
+pub struct InsideRenderPass;
+
+/// A secondary command buffer in the recording state. Secondaries begun with
+/// [`SecondaryCommandBuffer::begin`] record the commands valid outside of a
+/// render pass, those begun with [`SecondaryCommandBuffer::continue_render_pass`]
+/// record draws. Once ended they can only be executed where their state
+/// matches: from a [`CommandRecorder`](super::CommandRecorder) or from a
+/// [`RenderPassScope`](super::RenderPassScope) with secondary contents.
+///
+/// Any allocation can be used, including a [`PooledCommandBuffer`] from a
+/// [`CommandPoolSet`](super::CommandPoolSet) when recording on worker threads.
+pub struct SecondaryCommandBuffer<A: Borrow<CommandPoolAllocation> = CommandPoolAllocation, S = OutsideRenderPass> {
+    buffer: A,
+    _state: PhantomData<S>,
+}
+impl<A: Borrow<CommandPoolAllocation>> SecondaryCommandBuffer<A, OutsideRenderPass> {
+    /// begins recording `buffer` to be executed outside of any render pass, it
+    /// must have been allocated as [`CommandBufferLevel::SECONDARY`].
+    pub fn begin(buffer: A, flags: CommandBufferUsageFlags) -> Result<Self, VulkanError> {
+        Self::begin_with(buffer, &vk::CommandBufferInheritanceInfo::default(), flags)
+    }
+}
+impl<A: Borrow<CommandPoolAllocation>> SecondaryCommandBuffer<A, InsideRenderPass> {
+    /// begins recording `buffer` to continue the render pass described by `inheritance`.
+    pub fn continue_render_pass(buffer: A, inheritance: Inheritance<'_>, flags: CommandBufferUsageFlags) -> Result<Self, VulkanError> {
+        let allocation = buffer.borrow();
+        let color_formats;
+        let mut rendering = vk::CommandBufferInheritanceRenderingInfo::default();
+        let mut info = vk::CommandBufferInheritanceInfo::default();
+        match inheritance {
+            Inheritance::RenderPass { render_pass, subpass, framebuffer } => {
+                info.render_pass = render_pass.handle();
+                info.subpass = subpass;
+                info.framebuffer = framebuffer.map_or(vk::Framebuffer::null(), |framebuffer| framebuffer.handle());
+            }
+            Inheritance::DynamicRendering { view_mask, color_formats: formats, depth_format, stencil_format, samples } => {
+                if !allocation.device.enabled_features.dynamic_rendering {
+                    return Err(VulkanError::FeatureNotPresent(vec!["dynamic_rendering"]));
+                }
+                color_formats = formats.iter().map(|format| vk::Format::from_raw(format.0)).collect::<Vec<_>>();
+                rendering.view_mask = view_mask;
+                rendering.color_attachment_count = color_formats.len() as u32;
+                rendering.p_color_attachment_formats = color_formats.as_ptr();
+                rendering.depth_attachment_format = vk::Format::from_raw(depth_format.0);
+                rendering.stencil_attachment_format = vk::Format::from_raw(stencil_format.0);
+                rendering.rasterization_samples = vk::SampleCountFlags::from_raw(samples.0);
+                info.p_next = &rendering as *const _ as _;
+            }
+        }
+        Self::begin_with(buffer, &info, flags | CommandBufferUsageFlags::RENDER_PASS_CONTINUE)
+    }
+}
+impl<A: Borrow<CommandPoolAllocation>, S> SecondaryCommandBuffer<A, S> {
+    fn begin_with(buffer: A, info: &vk::CommandBufferInheritanceInfo, flags: CommandBufferUsageFlags) -> Result<Self, VulkanError> {
+        let allocation = buffer.borrow();
+        assert!(allocation.level() == CommandBufferLevel::SECONDARY, "only secondary command buffers can inherit render pass state");
+        let begin_info = vk::CommandBufferBeginInfo {
+            flags: vk::CommandBufferUsageFlags::from_raw(flags.0),
+            p_inheritance_info: info,
+            ..Default::default()
+        };
+        unsafe {
+            (allocation.device.fns.v1_0.begin_command_buffer)(allocation.get_command_buffer(), &begin_info)
+                .result()
+                .map_err(VulkanError::from)?;
+        }
+        Ok(Self { buffer, _state: PhantomData })
+    }
+    #[inline]
+    fn allocation(&self) -> &CommandPoolAllocation {
+        self.buffer.borrow()
+    }
+    /// ends recording, returning the buffer ready to be executed.
+    pub fn end(self) -> Result<ExecutableSecondaryCommandBuffer<A, S>, VulkanError> {
+        let allocation = self.buffer.borrow();
+        allocation.end()?;
+        Ok(ExecutableSecondaryCommandBuffer {
+            command_buffer: allocation.get_command_buffer(),
+            resources: allocation.take_resources(),
+            buffer: self.buffer,
+            _state: PhantomData,
+        })
+    }
+}
+impl<'a, S> SecondaryCommandBuffer<PooledCommandBuffer<'a>, S> {
+    /// ends recording, giving a handle the primary command buffer's thread can execute.
+    pub fn finish(self) -> Result<RecordedSecondaryCommandBuffer<'a, S>, VulkanError> {
+        let mut recorded = self.buffer.finish()?;
+        Ok(ExecutableSecondaryCommandBuffer {
+            command_buffer: recorded.get_command_buffer(),
+            resources: std::mem::take(&mut recorded.resources),
+            buffer: recorded,
+            _state: PhantomData,
+        })
+    }
+}
+shared_commands!(SecondaryCommandBuffer<A, S>, A: Borrow<CommandPoolAllocation>, S);
+outside_commands!(SecondaryCommandBuffer<A, OutsideRenderPass>, A: Borrow<CommandPoolAllocation>);
+draw_commands!(SecondaryCommandBuffer<A, InsideRenderPass>, A: Borrow<CommandPoolAllocation>);
+
+/// A secondary command buffer which finished recording, along with the
+/// resources it retains. `S` is the render pass state it was begun in, so it
+/// can't be executed where that state doesn't match:
+/// ```compile_fail
+/// # use nightfall_core::commands::{CommandBufferUsageFlags, CommandPoolAllocation, ExecutableSecondaryCommandBuffer, InsideRenderPass};
+/// # use nightfall_core::error::VulkanError;
+/// # fn record(allocation: &mut CommandPoolAllocation, draws: &[ExecutableSecondaryCommandBuffer<CommandPoolAllocation, InsideRenderPass>]) -> Result<(), VulkanError> {
+/// let recorder = allocation.record(CommandBufferUsageFlags::empty())?;
+/// recorder.execute_commands(draws);
+/// # Ok(()) }
+/// ```
+pub struct ExecutableSecondaryCommandBuffer<A = CommandPoolAllocation, S = OutsideRenderPass> {
+    command_buffer: vk::CommandBuffer,
+    pub(crate) resources: Vec<TrackedResource>,
+    buffer: A,
+    _state: PhantomData<S>,
+}
+/// A secondary command buffer from a [`CommandPoolSet`](super::CommandPoolSet)
+/// which finished recording, it may be sent to the thread executing it.
+pub type RecordedSecondaryCommandBuffer<'a, S = OutsideRenderPass> = ExecutableSecondaryCommandBuffer<RecordedCommandBuffer<'a>, S>;
+impl<A, S> ExecutableSecondaryCommandBuffer<A, S> {
+    #[inline]
+    pub fn get_command_buffer(&self) -> vk::CommandBuffer {
+        self.command_buffer
+    }
+    /// gives the buffer back, e.g. to reset and record it again.
+    pub fn into_inner(self) -> A {
+        self.buffer
+    }
+}
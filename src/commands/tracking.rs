@@ -0,0 +1,30 @@
+use std::{any::Any, sync::Arc};
+
+use super::CommandPoolAllocation;
+
+/// A resource kept alive by a command buffer until the GPU has finished with it.
+pub type TrackedResource = Arc<dyn Any + Send + Sync>;
+
+/// Every command recorded through a [`CommandRecorder`](super::CommandRecorder),
+/// a [`RenderPassScope`](super::RenderPassScope) or a [`SecondaryCommandBuffer`](super::SecondaryCommandBuffer)
+/// takes the resources it uses by `Arc` and retains them here. Submitting the
+/// buffer through a [`Submission`](crate::queue::Submission) hands them to the
+/// [`SubmissionGuard`](crate::queue::SubmissionGuard), which releases them once
+/// the submission's fence signals. The command buffer keeps its own references
+/// until it's recorded again or reset.
+impl CommandPoolAllocation {
+    /// keeps `resource` alive until the command buffer's submission completes.
+    pub fn retain<T: Send + Sync + 'static>(&self, resource: &Arc<T>) {
+        self.resources.borrow_mut().push(resource.clone());
+    }
+    /// takes the resources retained so far, leaving the command buffer with none.
+    pub(crate) fn take_resources(&self) -> Vec<TrackedResource> {
+        std::mem::take(&mut *self.resources.borrow_mut())
+    }
+    pub(crate) fn retained_resources(&self) -> Vec<TrackedResource> {
+        self.resources.borrow().clone()
+    }
+    pub fn retained_count(&self) -> usize {
+        self.resources.borrow().len()
+    }
+}
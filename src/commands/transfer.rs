@@ -0,0 +1,238 @@
+use ash::vk;
+use bytemuck::Pod;
+use smallvec::SmallVec;
+
+use crate::image::{ImageLayout, ImageSubresourceRange};
+
+use super::{BufferCopy, BufferImageCopy, ClearAttachment, ClearColorValue, ClearDepthStencilValue, ClearRect, CommandPoolAllocation, ImageBlit, ImageCopy, ImageResolve};
+
+/// Transfer commands. When both the instance and the device are on Vulkan 1.3
+/// they're recorded with the `vkCmd*2` entry points, which take the same
+/// regions through extensible structures.
+impl CommandPoolAllocation {
+    #[inline]
+    fn uses_copy_commands_2(&self) -> bool {
+        self.device.instance.version.supports_version_1_3() && self.device.physical_device.version.supports_version_1_3()
+    }
+    pub(crate) fn copy_buffer(&self, src: vk::Buffer, dst: vk::Buffer, regions: &[BufferCopy]) {
+        if self.uses_copy_commands_2() {
+            let regions = regions.iter().map(|region| vk::BufferCopy2 {
+                src_offset: region.src_offset,
+                dst_offset: region.dst_offset,
+                size: region.size,
+                ..Default::default()
+            }).collect::<SmallVec<[_; 4]>>();
+            let info = vk::CopyBufferInfo2 {
+                src_buffer: src,
+                dst_buffer: dst,
+                region_count: regions.len() as u32,
+                p_regions: regions.as_ptr(),
+                ..Default::default()
+            };
+            unsafe { self.device.device.cmd_copy_buffer2(self.command_buffer, &info) };
+        } else {
+            unsafe { self.device.device.cmd_copy_buffer(self.command_buffer, src, dst, std::mem::transmute_copy::<&[BufferCopy], &[vk::BufferCopy]>(&regions)) };
+        }
+    }
+    pub(crate) fn copy_image(&self, src: vk::Image, dst: vk::Image, src_layout: ImageLayout, dst_layout: ImageLayout, regions: &[ImageCopy]) {
+        if self.uses_copy_commands_2() {
+            let regions = regions.iter().map(|region| {
+                let region = vk::ImageCopy::from(*region);
+                vk::ImageCopy2 {
+                    src_subresource: region.src_subresource,
+                    src_offset: region.src_offset,
+                    dst_subresource: region.dst_subresource,
+                    dst_offset: region.dst_offset,
+                    extent: region.extent,
+                    ..Default::default()
+                }
+            }).collect::<SmallVec<[_; 4]>>();
+            let info = vk::CopyImageInfo2 {
+                src_image: src,
+                src_image_layout: vk::ImageLayout::from_raw(src_layout.0),
+                dst_image: dst,
+                dst_image_layout: vk::ImageLayout::from_raw(dst_layout.0),
+                region_count: regions.len() as u32,
+                p_regions: regions.as_ptr(),
+                ..Default::default()
+            };
+            unsafe { self.device.device.cmd_copy_image2(self.command_buffer, &info) };
+        } else {
+            unsafe {
+                self.device.device.cmd_copy_image(
+                    self.command_buffer,
+                    src,
+                    vk::ImageLayout::from_raw(src_layout.0),
+                    dst,
+                    vk::ImageLayout::from_raw(dst_layout.0),
+                    std::mem::transmute_copy::<&[ImageCopy], &[vk::ImageCopy]>(&regions),
+                )
+            };
+        }
+    }
+    pub(crate) fn buffer_to_image(&self, src: vk::Buffer, dst: vk::Image, layout: ImageLayout, regions: &[BufferImageCopy]) {
+        if self.uses_copy_commands_2() {
+            let regions = Self::buffer_image_copies_2(regions);
+            let info = vk::CopyBufferToImageInfo2 {
+                src_buffer: src,
+                dst_image: dst,
+                dst_image_layout: vk::ImageLayout::from_raw(layout.0),
+                region_count: regions.len() as u32,
+                p_regions: regions.as_ptr(),
+                ..Default::default()
+            };
+            unsafe { self.device.device.cmd_copy_buffer_to_image2(self.command_buffer, &info) };
+        } else {
+            unsafe {
+                self.device.device.cmd_copy_buffer_to_image(
+                    self.command_buffer,
+                    src,
+                    dst,
+                    vk::ImageLayout::from_raw(layout.0),
+                    std::mem::transmute_copy::<&[BufferImageCopy], &[vk::BufferImageCopy]>(&regions),
+                )
+            };
+        }
+    }
+    pub(crate) fn copy_image_to_buffer(&self, src: vk::Image, layout: ImageLayout, dst: vk::Buffer, regions: &[BufferImageCopy]) {
+        if self.uses_copy_commands_2() {
+            let regions = Self::buffer_image_copies_2(regions);
+            let info = vk::CopyImageToBufferInfo2 {
+                src_image: src,
+                src_image_layout: vk::ImageLayout::from_raw(layout.0),
+                dst_buffer: dst,
+                region_count: regions.len() as u32,
+                p_regions: regions.as_ptr(),
+                ..Default::default()
+            };
+            unsafe { self.device.device.cmd_copy_image_to_buffer2(self.command_buffer, &info) };
+        } else {
+            unsafe {
+                self.device.device.cmd_copy_image_to_buffer(
+                    self.command_buffer,
+                    src,
+                    vk::ImageLayout::from_raw(layout.0),
+                    dst,
+                    std::mem::transmute_copy::<&[BufferImageCopy], &[vk::BufferImageCopy]>(&regions),
+                )
+            };
+        }
+    }
+    fn buffer_image_copies_2(regions: &[BufferImageCopy]) -> SmallVec<[vk::BufferImageCopy2; 4]> {
+        regions.iter().map(|region| {
+            let region = vk::BufferImageCopy::from(*region);
+            vk::BufferImageCopy2 {
+                buffer_offset: region.buffer_offset,
+                buffer_row_length: region.buffer_row_length,
+                buffer_image_height: region.buffer_image_height,
+                image_subresource: region.image_subresource,
+                image_offset: region.image_offset,
+                image_extent: region.image_extent,
+                ..Default::default()
+            }
+        }).collect()
+    }
+    /// Scales and converts regions of `src` into `dst`. Both formats need the
+    /// `BLIT_SRC` and `BLIT_DST` format features, and `LINEAR` filtering needs
+    /// `SAMPLED_IMAGE_FILTER_LINEAR` on the source format.
+    pub(crate) fn blit_image(&self, src: vk::Image, src_layout: ImageLayout, dst: vk::Image, dst_layout: ImageLayout, regions: &[ImageBlit], filter: vk::Filter) {
+        if self.uses_copy_commands_2() {
+            let regions = regions.iter().map(|region| {
+                let region = vk::ImageBlit::from(*region);
+                vk::ImageBlit2 {
+                    src_subresource: region.src_subresource,
+                    src_offsets: region.src_offsets,
+                    dst_subresource: region.dst_subresource,
+                    dst_offsets: region.dst_offsets,
+                    ..Default::default()
+                }
+            }).collect::<SmallVec<[_; 4]>>();
+            let info = vk::BlitImageInfo2 {
+                src_image: src,
+                src_image_layout: vk::ImageLayout::from_raw(src_layout.0),
+                dst_image: dst,
+                dst_image_layout: vk::ImageLayout::from_raw(dst_layout.0),
+                region_count: regions.len() as u32,
+                p_regions: regions.as_ptr(),
+                filter,
+                ..Default::default()
+            };
+            unsafe { self.device.device.cmd_blit_image2(self.command_buffer, &info) };
+        } else {
+            unsafe {
+                self.device.device.cmd_blit_image(
+                    self.command_buffer,
+                    src,
+                    vk::ImageLayout::from_raw(src_layout.0),
+                    dst,
+                    vk::ImageLayout::from_raw(dst_layout.0),
+                    std::mem::transmute_copy::<&[ImageBlit], &[vk::ImageBlit]>(&regions),
+                    filter,
+                )
+            };
+        }
+    }
+    /// resolves a multisampled `src` into the single sampled `dst`.
+    pub(crate) fn resolve_image(&self, src: vk::Image, src_layout: ImageLayout, dst: vk::Image, dst_layout: ImageLayout, regions: &[ImageResolve]) {
+        if self.uses_copy_commands_2() {
+            let regions = regions.iter().map(|region| {
+                let region = vk::ImageResolve::from(*region);
+                vk::ImageResolve2 {
+                    src_subresource: region.src_subresource,
+                    src_offset: region.src_offset,
+                    dst_subresource: region.dst_subresource,
+                    dst_offset: region.dst_offset,
+                    extent: region.extent,
+                    ..Default::default()
+                }
+            }).collect::<SmallVec<[_; 4]>>();
+            let info = vk::ResolveImageInfo2 {
+                src_image: src,
+                src_image_layout: vk::ImageLayout::from_raw(src_layout.0),
+                dst_image: dst,
+                dst_image_layout: vk::ImageLayout::from_raw(dst_layout.0),
+                region_count: regions.len() as u32,
+                p_regions: regions.as_ptr(),
+                ..Default::default()
+            };
+            unsafe { self.device.device.cmd_resolve_image2(self.command_buffer, &info) };
+        } else {
+            unsafe {
+                self.device.device.cmd_resolve_image(
+                    self.command_buffer,
+                    src,
+                    vk::ImageLayout::from_raw(src_layout.0),
+                    dst,
+                    vk::ImageLayout::from_raw(dst_layout.0),
+                    std::mem::transmute_copy::<&[ImageResolve], &[vk::ImageResolve]>(&regions),
+                )
+            };
+        }
+    }
+    /// fills `size` bytes at `offset` with repeated copies of `data`, `size` may be `vk::WHOLE_SIZE`.
+    pub(crate) fn fill_buffer(&self, dst: vk::Buffer, offset: u64, size: u64, data: u32) {
+        debug_assert!(offset.is_multiple_of(4), "fill_buffer offsets must be a multiple of 4");
+        unsafe { self.device.device.cmd_fill_buffer(self.command_buffer, dst, offset, size, data) };
+    }
+    /// Writes `data` into `dst` at `offset` inline in the command buffer. The
+    /// data must be at most 65536 bytes and, like the offset, a multiple of 4.
+    pub(crate) fn update_buffer<T: Pod>(&self, dst: vk::Buffer, offset: u64, data: &[T]) {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        debug_assert!(offset.is_multiple_of(4) && bytes.len().is_multiple_of(4), "update_buffer offsets and sizes must be a multiple of 4");
+        debug_assert!(bytes.len() <= 65536, "update_buffer can write at most 65536 bytes");
+        unsafe { self.device.device.cmd_update_buffer(self.command_buffer, dst, offset, bytes) };
+    }
+    pub(crate) fn clear_color_image(&self, image: vk::Image, layout: ImageLayout, color: &ClearColorValue, ranges: &[ImageSubresourceRange]) {
+        let ranges = ranges.iter().map(|range| vk::ImageSubresourceRange::from(*range)).collect::<SmallVec<[_; 4]>>();
+        unsafe { self.device.device.cmd_clear_color_image(self.command_buffer, image, vk::ImageLayout::from_raw(layout.0), &color.into(), &ranges) };
+    }
+    pub(crate) fn clear_depth_stencil_image(&self, image: vk::Image, layout: ImageLayout, value: &ClearDepthStencilValue, ranges: &[ImageSubresourceRange]) {
+        let ranges = ranges.iter().map(|range| vk::ImageSubresourceRange::from(*range)).collect::<SmallVec<[_; 4]>>();
+        unsafe { self.device.device.cmd_clear_depth_stencil_image(self.command_buffer, image, vk::ImageLayout::from_raw(layout.0), &value.into(), &ranges) };
+    }
+    /// clears regions of the current subpass attachments, only valid inside a render pass.
+    pub(crate) fn clear_attachments(&self, attachments: &[ClearAttachment], rects: &[ClearRect]) {
+        let attachments = attachments.iter().map(vk::ClearAttachment::from).collect::<SmallVec<[_; 4]>>();
+        unsafe { self.device.device.cmd_clear_attachments(self.command_buffer, &attachments, std::mem::transmute_copy::<&[ClearRect], &[vk::ClearRect]>(&rects)) };
+    }
+}
@@ -0,0 +1,284 @@
+use std::{marker::PhantomData, sync::{atomic::{AtomicU64, Ordering}, Arc}};
+
+use ash::vk;
+use bytemuck::Pod;
+
+use crate::{
+    barriers::MemoryBarrier,
+    buffers::{Buffer, BufferCreateInfo, BufferUsageFlags, MemoryPropertyFlags},
+    commands::{CommandBufferLevel, CommandBufferResetFlags, CommandBufferUsageFlags, CommandPool, CommandPoolAllocation, CommandPoolCreateFlags},
+    descriptors::{DescriptorBufferInfo, DescriptorLayout, DescriptorPool, DescriptorSetAllocation, DescriptorType, DescriptorWriter},
+    error::{NightfallError, VulkanError},
+    image::PipelineStageFlags,
+    memory::{AccessFlags, DependencyFlags},
+    pipeline::{compute::ComputePipeline, layout::{PipelineLayout, PipelineLayoutBuilder}, shader::{Shader, ShaderStageFlags}},
+    queue::{Queue, QueueFlags},
+    sync::Fence,
+};
+
+/// Describes the interface of a compute shader run through a [`ComputeJob`]:
+/// storage buffers at bindings `0..count` of set 0 and an optional push
+/// constant block.
+pub struct ComputeJobBuilder {
+    storage_buffers: u32,
+    push_constant_size: u32,
+    layout: PipelineLayoutBuilder,
+}
+impl Default for ComputeJobBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl ComputeJobBuilder {
+    pub fn new() -> Self {
+        Self { storage_buffers: 0, push_constant_size: 0, layout: PipelineLayout::builder() }
+    }
+    /// the number of storage buffers the shader declares, at bindings `0..count`.
+    pub fn storage_buffers(mut self, count: u32) -> Self {
+        self.storage_buffers = count;
+        self
+    }
+    /// a push constant block of type `P` at offset 0.
+    pub fn push_constants<P: Pod>(mut self) -> Self {
+        self.push_constant_size = std::mem::size_of::<P>() as u32;
+        self.layout = self.layout.add_push_constant::<P>(ShaderStageFlags::COMPUTE);
+        self
+    }
+    pub fn build(self, queue: Arc<Queue>, shader: Arc<Shader>) -> Result<ComputeJob, NightfallError> {
+        if !queue.queue_flags().contains(QueueFlags::COMPUTE) {
+            return Err(VulkanError::NoSuitableQueueFamily(QueueFlags::COMPUTE).into());
+        }
+        if self.storage_buffers == 0 {
+            return Err(NightfallError::NoInputWasGiven);
+        }
+        let device = queue.device();
+        let descriptor_layout = (0..self.storage_buffers)
+            .fold(DescriptorLayout::builder(), |builder, binding| builder.add_binding(binding, DescriptorType::STORAGE_BUFFER, 1, ShaderStageFlags::COMPUTE))
+            .build(device.clone());
+        let layout = self.layout.add_descriptor_layout(descriptor_layout.layout()).try_build(device.clone())?;
+        let pipeline = ComputePipeline::new(device.clone(), layout, shader)?;
+        let descriptor_pool = DescriptorPool::builder()
+            .add_pool_size(DescriptorType::STORAGE_BUFFER, self.storage_buffers)
+            .set_max_sets(1)
+            .build(device.clone());
+        let set = descriptor_pool.allocate(std::slice::from_ref(&descriptor_layout))?.next().unwrap();
+        let command_pool = CommandPool::new(device.clone(), CommandPoolCreateFlags::RESET_COMMAND_BUFFER, queue.family_index())?;
+        let command_buffer = unsafe { command_pool.allocate_command_buffers(CommandBufferLevel::PRIMARY, 1)?.next().unwrap() };
+        Ok(ComputeJob {
+            fence: Fence::new(device, false),
+            queue,
+            pipeline: Arc::new(pipeline),
+            _descriptor_layout: descriptor_layout,
+            set: Arc::new(set),
+            storage_buffers: self.storage_buffers,
+            push_constant_size: self.push_constant_size,
+            command_buffer,
+            command_pool,
+        })
+    }
+}
+
+/// A compute shader ready to run over host data without touching pipelines,
+/// descriptors or command buffers. Each [`run`](Self::run) uploads its inputs
+/// into host visible storage buffers, dispatches, waits for the GPU and hands
+/// back the buffers for reading.
+/// ```ignore
+/// let mut job = ComputeJob::builder().storage_buffers(2).build(queue, shader)?;
+/// let mut run = job.run();
+/// run.input(&values);
+/// let squared = run.output::<f32>(values.len());
+/// let results = run.dispatch([values.len().div_ceil(64) as u32, 1, 1])?;
+/// let squared = results.read(squared)?;
+/// ```
+pub struct ComputeJob {
+    queue: Arc<Queue>,
+    pipeline: Arc<ComputePipeline>,
+    _descriptor_layout: Arc<DescriptorLayout>,
+    set: Arc<DescriptorSetAllocation>,
+    storage_buffers: u32,
+    push_constant_size: u32,
+    fence: Fence,
+    command_buffer: CommandPoolAllocation,
+    // declared last so the command buffer above is freed first.
+    command_pool: CommandPool,
+}
+impl ComputeJob {
+    #[inline]
+    pub fn builder() -> ComputeJobBuilder {
+        ComputeJobBuilder::new()
+    }
+    /// starts a run, bindings are assigned in the order they are added.
+    pub fn run<'d>(&mut self) -> ComputeRun<'_, 'd> {
+        ComputeRun { job: self, id: NEXT_RUN.fetch_add(1, Ordering::Relaxed), bindings: vec![], push_constants: vec![] }
+    }
+    #[inline]
+    pub fn pipeline(&self) -> &ComputePipeline {
+        &self.pipeline
+    }
+    #[inline]
+    pub fn queue(&self) -> Arc<Queue> {
+        self.queue.clone()
+    }
+}
+
+/// tells the runs of every job apart, so an [`Output`] is only read from the run that made it.
+static NEXT_RUN: AtomicU64 = AtomicU64::new(0);
+
+enum JobBinding<'d> {
+    Data(&'d [u8]),
+    Zeroed(usize),
+}
+
+/// The data bound to one dispatch of a [`ComputeJob`].
+pub struct ComputeRun<'j, 'd> {
+    job: &'j mut ComputeJob,
+    id: u64,
+    bindings: Vec<JobBinding<'d>>,
+    push_constants: Vec<u8>,
+}
+impl<'j, 'd> ComputeRun<'j, 'd> {
+    /// binds `data` to the next storage buffer, returning its binding.
+    pub fn input<T: Pod>(&mut self, data: &'d [T]) -> u32 {
+        self.bindings.push(JobBinding::Data(bytemuck::cast_slice(data)));
+        self.bindings.len() as u32 - 1
+    }
+    /// binds a zeroed buffer of `len` elements to the next storage buffer.
+    pub fn output<T: Pod>(&mut self, len: usize) -> Output<T> {
+        self.bindings.push(JobBinding::Zeroed(len * std::mem::size_of::<T>()));
+        Output { run: self.id, binding: self.bindings.len() as u32 - 1, len, _marker: PhantomData }
+    }
+    /// binds `data` to the next storage buffer and reads it back after the dispatch.
+    pub fn input_output<T: Pod>(&mut self, data: &'d [T]) -> Output<T> {
+        let binding = self.input(data);
+        Output { run: self.id, binding, len: data.len(), _marker: PhantomData }
+    }
+    pub fn push_constants<P: Pod>(&mut self, constants: &P) -> &mut Self {
+        self.push_constants = bytemuck::bytes_of(constants).to_vec();
+        self
+    }
+    /// dispatches `groups` workgroups and waits for them to finish.
+    pub fn dispatch(self, groups: [u32; 3]) -> Result<JobResults, NightfallError> {
+        let job = self.job;
+        if self.bindings.len() != job.storage_buffers as usize {
+            return Err(NightfallError::BindingCountMismatch(self.bindings.len(), job.storage_buffers));
+        }
+        if self.push_constants.len() != job.push_constant_size as usize {
+            return Err(NightfallError::PushConstantSizeMismatch(self.push_constants.len(), job.push_constant_size));
+        }
+        let device = job.queue.device();
+        let buffers = self.bindings.iter().map(|binding| {
+            let size = match binding {
+                JobBinding::Data(data) => data.len(),
+                JobBinding::Zeroed(size) => *size,
+            };
+            if size == 0 {
+                return Err(NightfallError::NoZeroSizedTypes);
+            }
+            let buffer = Buffer::new(device.clone(), BufferCreateInfo {
+                size,
+                usage: BufferUsageFlags::STORAGE_BUFFER,
+                properties: MemoryPropertyFlags::HOST_VISIBLE_COHERENT,
+                ..Default::default()
+            })?;
+            unsafe {
+                let ptr = buffer.raw_map::<u8>(size, 0)?;
+                match binding {
+                    JobBinding::Data(data) => std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, size),
+                    JobBinding::Zeroed(_) => std::ptr::write_bytes(ptr, 0, size),
+                }
+                buffer.raw_unmap();
+            }
+            Ok(buffer)
+        }).collect::<Result<Vec<_>, NightfallError>>()?;
+
+        // the writer keeps pointers into the infos, they're collected up front so they don't move.
+        let infos = buffers.iter().map(|buffer| DescriptorBufferInfo { buffer: buffer.handle(), offset: 0, range: vk::WHOLE_SIZE }).collect::<Vec<_>>();
+        infos.iter().enumerate()
+            .fold(DescriptorWriter::new(), |writer, (binding, info)| writer.add_storage_buffer(job.set.set(), 1, binding as u32, 0, info))
+            .write(device.clone());
+
+        job.command_buffer.reset(CommandBufferResetFlags::empty())?;
+        let recorder = job.command_buffer.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+        recorder.bind_compute_pipeline(&job.pipeline);
+        let layout = job.pipeline.layout().get_layout();
+        recorder.bind_descriptor_sets(vk::PipelineBindPoint::COMPUTE, layout, 0, std::slice::from_ref(&job.set), &[]);
+        if !self.push_constants.is_empty() {
+            unsafe { device.device.cmd_push_constants(recorder.get_command_buffer(), layout, vk::ShaderStageFlags::COMPUTE, 0, &self.push_constants) };
+        }
+        recorder.dispatch(groups[0], groups[1], groups[2]);
+        let barrier = MemoryBarrier { src_access_mask: AccessFlags::SHADER_WRITE, dst_access_mask: AccessFlags::HOST_READ };
+        recorder.pipeline_barrier(PipelineStageFlags::COMPUTE_SHADER, PipelineStageFlags::HOST, DependencyFlags::empty(), &[barrier], &[], &[]);
+        let command_buffer = recorder.end()?;
+
+        job.fence.reset();
+        let submit = vk::SubmitInfo {
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer.get_command_buffer(),
+            ..Default::default()
+        };
+        job.queue.submit_raw(&[submit], &job.fence)?;
+        job.fence.wait_max()?;
+        Ok(JobResults { run: self.id, buffers })
+    }
+}
+
+/// A typed handle to a buffer read back after a [`ComputeRun`], only valid
+/// for the [`JobResults`] of that run.
+#[derive(Clone, Copy, Debug)]
+pub struct Output<T> {
+    run: u64,
+    binding: u32,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+impl<T> Output<T> {
+    #[inline]
+    pub fn binding(&self) -> u32 {
+        self.binding
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The storage buffers of a finished [`ComputeRun`], in binding order.
+pub struct JobResults {
+    run: u64,
+    buffers: Vec<Buffer>,
+}
+impl JobResults {
+    /// fails instead of mapping when the output belongs to another run or
+    /// reaches past the end of its buffer.
+    pub fn read<T: Pod>(&self, output: Output<T>) -> Result<Vec<T>, NightfallError> {
+        if output.run != self.run {
+            return Err(NightfallError::ForeignOutput);
+        }
+        let Some(buffer) = self.buffers.get(output.binding as usize) else {
+            return Err(NightfallError::ForeignOutput);
+        };
+        let size = output.len.saturating_mul(std::mem::size_of::<T>());
+        if size > buffer.size() {
+            return Err(NightfallError::OutputOutOfBounds(size, buffer.size()));
+        }
+        let mut data = vec![T::zeroed(); output.len];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+        unsafe {
+            let ptr = buffer.raw_map::<u8>(bytes.len(), 0)?;
+            std::ptr::copy_nonoverlapping(ptr, bytes.as_mut_ptr(), bytes.len());
+            buffer.raw_unmap();
+        }
+        Ok(data)
+    }
+    #[inline]
+    pub fn buffer(&self, binding: u32) -> &Buffer {
+        &self.buffers[binding as usize]
+    }
+    pub fn into_buffers(self) -> Vec<Buffer> {
+        self.buffers
+    }
+}
@@ -120,6 +120,10 @@ impl DescriptorLayoutBuilder {
         self.bindings.push(binding);
         self
     }
+    #[inline]
+    pub fn bindings(&self) -> &[DescriptorSetLayoutBinding] {
+        &self.bindings
+    }
     pub fn set_flag(mut self, flags: DescriptorSetLayoutCreateFlags) -> Self {
         self.flags = vk::DescriptorSetLayoutCreateFlags::from_raw(flags.0);
         self
@@ -2,11 +2,13 @@ mod layout;
 mod writer;
 mod pool;
 mod set;
+mod resources;
 use ash::vk;
 pub use layout::*;
 pub use writer::*;
 pub use pool::*;
 pub use set::*;
+pub use resources::*;
 
 #[repr(C)]
 #[cfg_attr(feature = "debug", derive(Debug))]
@@ -27,4 +29,43 @@ impl From<&DescriptorBufferInfo> for &ash::vk::DescriptorBufferInfo {
     fn from(value: &DescriptorBufferInfo) -> Self {
         unsafe { std::mem::transmute::<&DescriptorBufferInfo, &vk::DescriptorBufferInfo>(value) }
     }
+}
+
+/// A [`DescriptorBufferInfo`] for data laid out by the std140 rules, the only
+/// kind the uniform buffer writes of [`DescriptorWriter`] accept. Made by
+/// [`NfPtrType::as_uniform_buffer_info`](crate::NfPtrType::as_uniform_buffer_info)
+/// or [`Buffer::uniform_buffer_info`](crate::buffers::Buffer::uniform_buffer_info)
+/// for a `T: Std140`, so std430 data can't be bound as a uniform buffer:
+/// ```compile_fail
+/// # use nightfall_core::{descriptors::UniformBufferInfo, glsl::{Std430, Vec2}, NfPtrType};
+/// #[repr(C)]
+/// #[derive(Clone, Copy, Std430)]
+/// struct Particle {
+///     position: Vec2,
+///     velocity: Vec2,
+/// }
+/// fn bind(particles: &NfPtrType<Particle>) -> UniformBufferInfo {
+///     particles.as_uniform_buffer_info()
+/// }
+/// ```
+#[repr(transparent)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone)]
+pub struct UniformBufferInfo(DescriptorBufferInfo);
+impl UniformBufferInfo {
+    /// # Safety
+    /// the range described by `info` must hold std140 data.
+    #[inline]
+    pub const unsafe fn from_raw(info: DescriptorBufferInfo) -> Self {
+        Self(info)
+    }
+    #[inline]
+    pub const fn info(&self) -> DescriptorBufferInfo {
+        self.0
+    }
+}
+impl From<&UniformBufferInfo> for &ash::vk::DescriptorBufferInfo {
+    fn from(value: &UniformBufferInfo) -> Self {
+        (&value.0).into()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::device::LogicalDevice;
+
+use super::{DescriptorLayout, DescriptorLayoutBuilder, DescriptorWriter};
+pub use nightfall_core_macros::ShaderResources;
+
+/// Describes the descriptors a shader expects in a single struct. Usually
+/// derived, with every field annotated by where it is bound:
+/// ```ignore
+/// #[derive(ShaderResources)]
+/// struct Material {
+///     #[descriptor(set = 0, binding = 0, uniform_buffer, stages(VERTEX, FRAGMENT))]
+///     camera: UniformBufferInfo,
+///     #[descriptor(set = 1, binding = 0, combined_image_sampler, stages(FRAGMENT))]
+///     albedo: vk::DescriptorImageInfo,
+/// }
+/// ```
+/// Uniform buffers are stored as [`super::UniformBufferInfo`], so only std140
+/// data can be bound to them, other buffer descriptors as
+/// [`super::DescriptorBufferInfo`] and image descriptors as
+/// [`vk::DescriptorImageInfo`]. A descriptor with `count = N` is stored as an
+/// array of `N` infos.
+pub trait ShaderResources {
+    /// every descriptor set number used by the struct, in ascending order.
+    const SETS: &'static [u32];
+    fn layout_builder(set: u32) -> DescriptorLayoutBuilder;
+    /// pushes a write for every descriptor, `sets[i]` being the descriptor set
+    /// allocated for `SETS[i]`.
+    fn fill_writer(&self, writer: &mut DescriptorWriter, sets: &[vk::DescriptorSet]);
+    /// builds one layout per entry in `SETS`, in the same order.
+    fn build_layouts(device: Arc<LogicalDevice>) -> Vec<Arc<DescriptorLayout>> {
+        Self::SETS.iter().map(|set| Self::layout_builder(*set).build(device.clone())).collect()
+    }
+    fn write(&self, device: Arc<LogicalDevice>, sets: &[vk::DescriptorSet]) {
+        let mut writer = DescriptorWriter::new();
+        self.fill_writer(&mut writer, sets);
+        writer.write(device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::{self, Handle};
+
+    use super::{DescriptorWriter, ShaderResources};
+    use crate::descriptors::{DescriptorBufferInfo, UniformBufferInfo};
+
+    #[derive(ShaderResources)]
+    struct Material {
+        #[descriptor(set = 0, binding = 0, uniform_buffer, stages(VERTEX, FRAGMENT))]
+        camera: UniformBufferInfo,
+        #[descriptor(set = 0, binding = 2, storage_buffer)]
+        lights: DescriptorBufferInfo,
+        #[descriptor(set = 2, binding = 1, combined_image_sampler, count = 3, stages(FRAGMENT))]
+        textures: [vk::DescriptorImageInfo; 3],
+        #[allow(dead_code)]
+        name: &'static str,
+    }
+
+    fn material() -> Material {
+        let buffer = |offset| DescriptorBufferInfo { buffer: vk::Buffer::null(), offset, range: 64 };
+        Material {
+            camera: unsafe { UniformBufferInfo::from_raw(buffer(0)) },
+            lights: buffer(256),
+            textures: [vk::DescriptorImageInfo::default(); 3],
+            name: "material",
+        }
+    }
+
+    fn bindings<R: ShaderResources>(set: u32) -> Vec<(u32, vk::DescriptorType, u32, vk::ShaderStageFlags)> {
+        R::layout_builder(set).bindings().iter()
+            .map(vk::DescriptorSetLayoutBinding::from)
+            .map(|b| (b.binding, b.descriptor_type, b.descriptor_count, b.stage_flags))
+            .collect()
+    }
+
+    #[test]
+    fn layouts_follow_the_attributes() {
+        assert_eq!(Material::SETS, [0, 2]);
+        assert_eq!(
+            bindings::<Material>(0),
+            [
+                (0, vk::DescriptorType::UNIFORM_BUFFER, 1, vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT),
+                (2, vk::DescriptorType::STORAGE_BUFFER, 1, vk::ShaderStageFlags::ALL),
+            ]
+        );
+        assert_eq!(bindings::<Material>(2), [(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 3, vk::ShaderStageFlags::FRAGMENT)]);
+    }
+    #[test]
+    fn writes_go_to_the_set_allocated_for_each_number() {
+        let material = material();
+        let sets = [vk::DescriptorSet::from_raw(10), vk::DescriptorSet::from_raw(12)];
+        let mut writer = DescriptorWriter::new();
+        material.fill_writer(&mut writer, &sets);
+        let writes = writer.writes().iter()
+            .map(|w| (w.dst_set, w.dst_binding, w.descriptor_type, w.descriptor_count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            writes,
+            [
+                (sets[0], 0, vk::DescriptorType::UNIFORM_BUFFER, 1),
+                (sets[0], 2, vk::DescriptorType::STORAGE_BUFFER, 1),
+                (sets[1], 1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 3),
+            ]
+        );
+        assert_eq!(unsafe { (*writer.writes()[1].p_buffer_info).offset }, 256);
+    }
+    #[test]
+    #[should_panic]
+    fn missing_set_panics() {
+        let _ = Material::layout_builder(1);
+    }
+}
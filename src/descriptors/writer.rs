@@ -4,7 +4,7 @@ use ash::vk;
 
 use crate::device::LogicalDevice;
 
-use super::DescriptorBufferInfo;
+use super::{DescriptorBufferInfo, UniformBufferInfo};
 
 
 #[derive(Clone)]
@@ -19,6 +19,11 @@ impl DescriptorWriter {
     pub fn reset(&mut self) {
         self.writers.clear();
     }
+    /// the writes pushed so far.
+    #[inline]
+    pub fn writes(&self) -> &[vk::WriteDescriptorSet] {
+        &self.writers
+    }
     pub fn add_storage_buffer(mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &DescriptorBufferInfo) -> Self {
         let info: &vk::DescriptorBufferInfo = info.into();
         let writer = vk::WriteDescriptorSet {
@@ -60,6 +65,19 @@ impl DescriptorWriter {
         self.writers.push(writer);
         self
     }
+    pub fn add_sampled_image(mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &vk::DescriptorImageInfo) -> Self {
+        let writer = vk::WriteDescriptorSet {
+            dst_set: set,
+            descriptor_count: count,
+            descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+            dst_binding: binding,
+            dst_array_element: array_element,
+            p_image_info: info,
+            ..Default::default()
+        };
+        self.writers.push(writer);
+        self
+    }
     pub fn add_storage_image(mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &vk::DescriptorImageInfo) -> Self {
         let writer = vk::WriteDescriptorSet {
             dst_set: set,
@@ -86,7 +104,7 @@ impl DescriptorWriter {
         self.writers.push(writer);
         self
     }
-    pub fn add_uniform_buffer(mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &DescriptorBufferInfo) -> Self {
+    pub fn add_uniform_buffer(mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &UniformBufferInfo) -> Self {
         let info: &vk::DescriptorBufferInfo = info.into();
         let writer = vk::WriteDescriptorSet {
             dst_set: set,
@@ -100,7 +118,7 @@ impl DescriptorWriter {
         self.writers.push(writer);
         self
     }
-    pub fn add_uniform_buffer_dynamic(mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &DescriptorBufferInfo) -> Self {
+    pub fn add_uniform_buffer_dynamic(mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &UniformBufferInfo) -> Self {
         let info: &vk::DescriptorBufferInfo = info.into();
         let writer = vk::WriteDescriptorSet {
             dst_set: set,
@@ -152,6 +170,18 @@ impl DescriptorWriter {
         };
         self.writers.push(writer);
     }
+    pub fn push_sampled_image(&mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &vk::DescriptorImageInfo) {
+        let writer = vk::WriteDescriptorSet {
+            dst_set: set,
+            descriptor_count: count,
+            descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+            dst_binding: binding,
+            dst_array_element: array_element,
+            p_image_info: info,
+            ..Default::default()
+        };
+        self.writers.push(writer);
+    }
     pub fn push_storage_image(&mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &vk::DescriptorImageInfo) {
         let writer = vk::WriteDescriptorSet {
             dst_set: set,
@@ -164,7 +194,7 @@ impl DescriptorWriter {
         };
         self.writers.push(writer);
     }
-    pub fn push_uniform_buffer(&mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &DescriptorBufferInfo) {
+    pub fn push_uniform_buffer(&mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &UniformBufferInfo) {
         let info: &vk::DescriptorBufferInfo = info.into();
         let writer = vk::WriteDescriptorSet {
             dst_set: set,
@@ -177,7 +207,8 @@ impl DescriptorWriter {
         };
         self.writers.push(writer);
     }
-    pub fn push_uniform_buffer_dynamic(&mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &vk::DescriptorBufferInfo) {
+    pub fn push_uniform_buffer_dynamic(&mut self, set: vk::DescriptorSet, count: u32, binding: u32, array_element: u32, info: &UniformBufferInfo) {
+        let info: &vk::DescriptorBufferInfo = info.into();
         let writer = vk::WriteDescriptorSet {
             dst_set: set,
             descriptor_count: count,
@@ -0,0 +1,77 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use ash::vk;
+
+/// A handle whose destruction was deferred until the GPU is done with it.
+pub(crate) enum DeferredHandle {
+    Buffer(vk::Buffer),
+    Image(vk::Image),
+    ImageView(vk::ImageView),
+    Pipeline(vk::Pipeline),
+    Memory(vk::DeviceMemory),
+}
+impl DeferredHandle {
+    pub(crate) unsafe fn destroy(self, device: &ash::Device) {
+        match self {
+            Self::Buffer(buffer) => device.destroy_buffer(buffer, None),
+            Self::Image(image) => device.destroy_image(image, None),
+            Self::ImageView(view) => device.destroy_image_view(view, None),
+            Self::Pipeline(pipeline) => device.destroy_pipeline(pipeline, None),
+            Self::Memory(memory) => device.free_memory(memory, None),
+        }
+    }
+}
+
+struct DeletionState {
+    point: u64,
+    pending: VecDeque<(u64, DeferredHandle)>,
+}
+
+/// Handles dropped while the queue is at a point are destroyed once the caller
+/// reports that point as completed by the GPU. Points only ever increase, so
+/// the pending handles stay ordered by the point they were dropped at.
+pub(crate) struct DeletionQueue {
+    state: Mutex<DeletionState>,
+}
+impl DeletionQueue {
+    pub(crate) fn new() -> Self {
+        Self { state: Mutex::new(DeletionState { point: 0, pending: VecDeque::new() }) }
+    }
+    pub(crate) fn push(&self, handle: DeferredHandle) {
+        let mut state = self.state.lock().unwrap();
+        let point = state.point;
+        state.pending.push_back((point, handle));
+    }
+    pub(crate) fn point(&self) -> u64 {
+        self.state.lock().unwrap().point
+    }
+    pub(crate) fn advance(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.point += 1;
+        state.point - 1
+    }
+    pub(crate) fn set_point(&self, point: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.point = state.point.max(point);
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().unwrap().pending.len()
+    }
+    /// destroys every handle dropped at or before `completed`, returning how many were destroyed.
+    pub(crate) unsafe fn collect(&self, device: &ash::Device, completed: u64) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let count = state.pending.iter().take_while(|(point, _)| *point <= completed).count();
+        for (_, handle) in state.pending.drain(..count) {
+            handle.destroy(device);
+        }
+        count
+    }
+    pub(crate) unsafe fn flush(&self, device: &ash::Device) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let count = state.pending.len();
+        for (_, handle) in state.pending.drain(..) {
+            handle.destroy(device);
+        }
+        count
+    }
+}
@@ -9,6 +9,8 @@
 
 use bytemuck::cast_slice;
 
+use crate::Version;
+
 /// Properties of an extension in the loader or a physical device.
 #[derive(Clone, Debug)]
 pub struct ExtensionProperties {
@@ -1509,6 +1511,554 @@ impl<'a> From<&'a DeviceExtensions> for Vec<std::ffi::CString> {
     }
 }
 
+macro_rules! device_extensions_set_ops {
+    ($(($field:ident, $name:literal)),* $(,)?) => {
+        impl DeviceExtensions {
+            /// the extensions enabled in `self` which are not enabled in `other`.
+            pub fn difference(&self, other: &Self) -> Self {
+                Self { $($field: self.$field && !other.$field),* }
+            }
+            pub fn union(&self, other: &Self) -> Self {
+                Self { $($field: self.$field || other.$field),* }
+            }
+            pub fn intersection(&self, other: &Self) -> Self {
+                Self { $($field: self.$field && other.$field),* }
+            }
+            /// whether every extension enabled in `other` is enabled in `self`.
+            pub fn contains(&self, other: &Self) -> bool {
+                other.difference(self).is_empty()
+            }
+            pub fn is_empty(&self) -> bool {
+                $(!self.$field)&&*
+            }
+            /// every enabled extension on its own.
+            pub fn each(&self) -> Vec<Self> {
+                let mut each = vec![];
+                $(if self.$field { each.push(Self { $field: true, ..Self::default() }); })*
+                each
+            }
+            /// the `VK_*` names of the enabled extensions.
+            pub fn names(&self) -> Vec<&'static str> {
+                let mut names = vec![];
+                $(if self.$field { names.push($name); })*
+                names
+            }
+        }
+    };
+}
+device_extensions_set_ops!(
+    (khr_16bit_storage, "VK_KHR_16bit_storage"),
+    (khr_8bit_storage, "VK_KHR_8bit_storage"),
+    (khr_acceleration_structure, "VK_KHR_acceleration_structure"),
+    (khr_bind_memory2, "VK_KHR_bind_memory2"),
+    (khr_buffer_device_address, "VK_KHR_buffer_device_address"),
+    (khr_copy_commands2, "VK_KHR_copy_commands2"),
+    (khr_create_renderpass2, "VK_KHR_create_renderpass2"),
+    (khr_dedicated_allocation, "VK_KHR_dedicated_allocation"),
+    (khr_deferred_host_operations, "VK_KHR_deferred_host_operations"),
+    (khr_depth_stencil_resolve, "VK_KHR_depth_stencil_resolve"),
+    (khr_descriptor_update_template, "VK_KHR_descriptor_update_template"),
+    (khr_device_group, "VK_KHR_device_group"),
+    (khr_display_swapchain, "VK_KHR_display_swapchain"),
+    (khr_draw_indirect_count, "VK_KHR_draw_indirect_count"),
+    (khr_driver_properties, "VK_KHR_driver_properties"),
+    (khr_dynamic_rendering, "VK_KHR_dynamic_rendering"),
+    (khr_external_fence, "VK_KHR_external_fence"),
+    (khr_external_fence_fd, "VK_KHR_external_fence_fd"),
+    (khr_external_fence_win32, "VK_KHR_external_fence_win32"),
+    (khr_external_memory, "VK_KHR_external_memory"),
+    (khr_external_memory_fd, "VK_KHR_external_memory_fd"),
+    (khr_external_memory_win32, "VK_KHR_external_memory_win32"),
+    (khr_external_semaphore, "VK_KHR_external_semaphore"),
+    (khr_external_semaphore_fd, "VK_KHR_external_semaphore_fd"),
+    (khr_external_semaphore_win32, "VK_KHR_external_semaphore_win32"),
+    (khr_format_feature_flags2, "VK_KHR_format_feature_flags2"),
+    (khr_fragment_shader_barycentric, "VK_KHR_fragment_shader_barycentric"),
+    (khr_fragment_shading_rate, "VK_KHR_fragment_shading_rate"),
+    (khr_get_memory_requirements2, "VK_KHR_get_memory_requirements2"),
+    (khr_global_priority, "VK_KHR_global_priority"),
+    (khr_image_format_list, "VK_KHR_image_format_list"),
+    (khr_imageless_framebuffer, "VK_KHR_imageless_framebuffer"),
+    (khr_incremental_present, "VK_KHR_incremental_present"),
+    (khr_maintenance1, "VK_KHR_maintenance1"),
+    (khr_maintenance2, "VK_KHR_maintenance2"),
+    (khr_maintenance3, "VK_KHR_maintenance3"),
+    (khr_maintenance4, "VK_KHR_maintenance4"),
+    (khr_map_memory2, "VK_KHR_map_memory2"),
+    (khr_multiview, "VK_KHR_multiview"),
+    (khr_performance_query, "VK_KHR_performance_query"),
+    (khr_pipeline_executable_properties, "VK_KHR_pipeline_executable_properties"),
+    (khr_pipeline_library, "VK_KHR_pipeline_library"),
+    (khr_portability_subset, "VK_KHR_portability_subset"),
+    (khr_present_id, "VK_KHR_present_id"),
+    (khr_present_wait, "VK_KHR_present_wait"),
+    (khr_push_descriptor, "VK_KHR_push_descriptor"),
+    (khr_ray_query, "VK_KHR_ray_query"),
+    (khr_ray_tracing_maintenance1, "VK_KHR_ray_tracing_maintenance1"),
+    (khr_ray_tracing_pipeline, "VK_KHR_ray_tracing_pipeline"),
+    (khr_ray_tracing_position_fetch, "VK_KHR_ray_tracing_position_fetch"),
+    (khr_relaxed_block_layout, "VK_KHR_relaxed_block_layout"),
+    (khr_sampler_mirror_clamp_to_edge, "VK_KHR_sampler_mirror_clamp_to_edge"),
+    (khr_sampler_ycbcr_conversion, "VK_KHR_sampler_ycbcr_conversion"),
+    (khr_separate_depth_stencil_layouts, "VK_KHR_separate_depth_stencil_layouts"),
+    (khr_shader_atomic_int64, "VK_KHR_shader_atomic_int64"),
+    (khr_shader_clock, "VK_KHR_shader_clock"),
+    (khr_shader_draw_parameters, "VK_KHR_shader_draw_parameters"),
+    (khr_shader_float16_int8, "VK_KHR_shader_float16_int8"),
+    (khr_shader_float_controls, "VK_KHR_shader_float_controls"),
+    (khr_shader_integer_dot_product, "VK_KHR_shader_integer_dot_product"),
+    (khr_shader_non_semantic_info, "VK_KHR_shader_non_semantic_info"),
+    (khr_shader_subgroup_extended_types, "VK_KHR_shader_subgroup_extended_types"),
+    (khr_shader_subgroup_uniform_control_flow, "VK_KHR_shader_subgroup_uniform_control_flow"),
+    (khr_shader_terminate_invocation, "VK_KHR_shader_terminate_invocation"),
+    (khr_shared_presentable_image, "VK_KHR_shared_presentable_image"),
+    (khr_spirv_1_4, "VK_KHR_spirv_1_4"),
+    (khr_storage_buffer_storage_class, "VK_KHR_storage_buffer_storage_class"),
+    (khr_swapchain, "VK_KHR_swapchain"),
+    (khr_swapchain_mutable_format, "VK_KHR_swapchain_mutable_format"),
+    (khr_synchronization2, "VK_KHR_synchronization2"),
+    (khr_timeline_semaphore, "VK_KHR_timeline_semaphore"),
+    (khr_uniform_buffer_standard_layout, "VK_KHR_uniform_buffer_standard_layout"),
+    (khr_variable_pointers, "VK_KHR_variable_pointers"),
+    (khr_video_decode_h264, "VK_KHR_video_decode_h264"),
+    (khr_video_decode_h265, "VK_KHR_video_decode_h265"),
+    (khr_video_decode_queue, "VK_KHR_video_decode_queue"),
+    (khr_video_encode_queue, "VK_KHR_video_encode_queue"),
+    (khr_video_queue, "VK_KHR_video_queue"),
+    (khr_vulkan_memory_model, "VK_KHR_vulkan_memory_model"),
+    (khr_win32_keyed_mutex, "VK_KHR_win32_keyed_mutex"),
+    (khr_workgroup_memory_explicit_layout, "VK_KHR_workgroup_memory_explicit_layout"),
+    (khr_zero_initialize_workgroup_memory, "VK_KHR_zero_initialize_workgroup_memory"),
+    (ext_4444_formats, "VK_EXT_4444_formats"),
+    (ext_astc_decode_mode, "VK_EXT_astc_decode_mode"),
+    (ext_attachment_feedback_loop_dynamic_state, "VK_EXT_attachment_feedback_loop_dynamic_state"),
+    (ext_attachment_feedback_loop_layout, "VK_EXT_attachment_feedback_loop_layout"),
+    (ext_blend_operation_advanced, "VK_EXT_blend_operation_advanced"),
+    (ext_border_color_swizzle, "VK_EXT_border_color_swizzle"),
+    (ext_buffer_device_address, "VK_EXT_buffer_device_address"),
+    (ext_calibrated_timestamps, "VK_EXT_calibrated_timestamps"),
+    (ext_color_write_enable, "VK_EXT_color_write_enable"),
+    (ext_conditional_rendering, "VK_EXT_conditional_rendering"),
+    (ext_conservative_rasterization, "VK_EXT_conservative_rasterization"),
+    (ext_custom_border_color, "VK_EXT_custom_border_color"),
+    (ext_debug_marker, "VK_EXT_debug_marker"),
+    (ext_depth_clamp_zero_one, "VK_EXT_depth_clamp_zero_one"),
+    (ext_depth_clip_control, "VK_EXT_depth_clip_control"),
+    (ext_depth_clip_enable, "VK_EXT_depth_clip_enable"),
+    (ext_depth_range_unrestricted, "VK_EXT_depth_range_unrestricted"),
+    (ext_descriptor_buffer, "VK_EXT_descriptor_buffer"),
+    (ext_descriptor_indexing, "VK_EXT_descriptor_indexing"),
+    (ext_device_address_binding_report, "VK_EXT_device_address_binding_report"),
+    (ext_device_fault, "VK_EXT_device_fault"),
+    (ext_device_memory_report, "VK_EXT_device_memory_report"),
+    (ext_discard_rectangles, "VK_EXT_discard_rectangles"),
+    (ext_display_control, "VK_EXT_display_control"),
+    (ext_dynamic_rendering_unused_attachments, "VK_EXT_dynamic_rendering_unused_attachments"),
+    (ext_extended_dynamic_state, "VK_EXT_extended_dynamic_state"),
+    (ext_extended_dynamic_state2, "VK_EXT_extended_dynamic_state2"),
+    (ext_extended_dynamic_state3, "VK_EXT_extended_dynamic_state3"),
+    (ext_external_memory_dma_buf, "VK_EXT_external_memory_dma_buf"),
+    (ext_external_memory_host, "VK_EXT_external_memory_host"),
+    (ext_filter_cubic, "VK_EXT_filter_cubic"),
+    (ext_fragment_density_map, "VK_EXT_fragment_density_map"),
+    (ext_fragment_density_map2, "VK_EXT_fragment_density_map2"),
+    (ext_fragment_shader_interlock, "VK_EXT_fragment_shader_interlock"),
+    (ext_full_screen_exclusive, "VK_EXT_full_screen_exclusive"),
+    (ext_global_priority, "VK_EXT_global_priority"),
+    (ext_global_priority_query, "VK_EXT_global_priority_query"),
+    (ext_graphics_pipeline_library, "VK_EXT_graphics_pipeline_library"),
+    (ext_hdr_metadata, "VK_EXT_hdr_metadata"),
+    (ext_host_query_reset, "VK_EXT_host_query_reset"),
+    (ext_image_2d_view_of_3d, "VK_EXT_image_2d_view_of_3d"),
+    (ext_image_compression_control, "VK_EXT_image_compression_control"),
+    (ext_image_compression_control_swapchain, "VK_EXT_image_compression_control_swapchain"),
+    (ext_image_drm_format_modifier, "VK_EXT_image_drm_format_modifier"),
+    (ext_image_robustness, "VK_EXT_image_robustness"),
+    (ext_image_sliced_view_of_3d, "VK_EXT_image_sliced_view_of_3d"),
+    (ext_image_view_min_lod, "VK_EXT_image_view_min_lod"),
+    (ext_index_type_uint8, "VK_EXT_index_type_uint8"),
+    (ext_inline_uniform_block, "VK_EXT_inline_uniform_block"),
+    (ext_legacy_dithering, "VK_EXT_legacy_dithering"),
+    (ext_line_rasterization, "VK_EXT_line_rasterization"),
+    (ext_load_store_op_none, "VK_EXT_load_store_op_none"),
+    (ext_memory_budget, "VK_EXT_memory_budget"),
+    (ext_memory_priority, "VK_EXT_memory_priority"),
+    (ext_mesh_shader, "VK_EXT_mesh_shader"),
+    (ext_metal_objects, "VK_EXT_metal_objects"),
+    (ext_multi_draw, "VK_EXT_multi_draw"),
+    (ext_multisampled_render_to_single_sampled, "VK_EXT_multisampled_render_to_single_sampled"),
+    (ext_mutable_descriptor_type, "VK_EXT_mutable_descriptor_type"),
+    (ext_non_seamless_cube_map, "VK_EXT_non_seamless_cube_map"),
+    (ext_opacity_micromap, "VK_EXT_opacity_micromap"),
+    (ext_pageable_device_local_memory, "VK_EXT_pageable_device_local_memory"),
+    (ext_pci_bus_info, "VK_EXT_pci_bus_info"),
+    (ext_physical_device_drm, "VK_EXT_physical_device_drm"),
+    (ext_pipeline_creation_cache_control, "VK_EXT_pipeline_creation_cache_control"),
+    (ext_pipeline_creation_feedback, "VK_EXT_pipeline_creation_feedback"),
+    (ext_pipeline_library_group_handles, "VK_EXT_pipeline_library_group_handles"),
+    (ext_pipeline_properties, "VK_EXT_pipeline_properties"),
+    (ext_pipeline_protected_access, "VK_EXT_pipeline_protected_access"),
+    (ext_pipeline_robustness, "VK_EXT_pipeline_robustness"),
+    (ext_post_depth_coverage, "VK_EXT_post_depth_coverage"),
+    (ext_primitive_topology_list_restart, "VK_EXT_primitive_topology_list_restart"),
+    (ext_primitives_generated_query, "VK_EXT_primitives_generated_query"),
+    (ext_private_data, "VK_EXT_private_data"),
+    (ext_provoking_vertex, "VK_EXT_provoking_vertex"),
+    (ext_queue_family_foreign, "VK_EXT_queue_family_foreign"),
+    (ext_rasterization_order_attachment_access, "VK_EXT_rasterization_order_attachment_access"),
+    (ext_rgba10x6_formats, "VK_EXT_rgba10x6_formats"),
+    (ext_robustness2, "VK_EXT_robustness2"),
+    (ext_sample_locations, "VK_EXT_sample_locations"),
+    (ext_sampler_filter_minmax, "VK_EXT_sampler_filter_minmax"),
+    (ext_scalar_block_layout, "VK_EXT_scalar_block_layout"),
+    (ext_separate_stencil_usage, "VK_EXT_separate_stencil_usage"),
+    (ext_shader_atomic_float, "VK_EXT_shader_atomic_float"),
+    (ext_shader_atomic_float2, "VK_EXT_shader_atomic_float2"),
+    (ext_shader_demote_to_helper_invocation, "VK_EXT_shader_demote_to_helper_invocation"),
+    (ext_shader_image_atomic_int64, "VK_EXT_shader_image_atomic_int64"),
+    (ext_shader_module_identifier, "VK_EXT_shader_module_identifier"),
+    (ext_shader_object, "VK_EXT_shader_object"),
+    (ext_shader_stencil_export, "VK_EXT_shader_stencil_export"),
+    (ext_shader_subgroup_ballot, "VK_EXT_shader_subgroup_ballot"),
+    (ext_shader_subgroup_vote, "VK_EXT_shader_subgroup_vote"),
+    (ext_shader_tile_image, "VK_EXT_shader_tile_image"),
+    (ext_shader_viewport_index_layer, "VK_EXT_shader_viewport_index_layer"),
+    (ext_subgroup_size_control, "VK_EXT_subgroup_size_control"),
+    (ext_subpass_merge_feedback, "VK_EXT_subpass_merge_feedback"),
+    (ext_swapchain_maintenance1, "VK_EXT_swapchain_maintenance1"),
+    (ext_texel_buffer_alignment, "VK_EXT_texel_buffer_alignment"),
+    (ext_texture_compression_astc_hdr, "VK_EXT_texture_compression_astc_hdr"),
+    (ext_tooling_info, "VK_EXT_tooling_info"),
+    (ext_transform_feedback, "VK_EXT_transform_feedback"),
+    (ext_validation_cache, "VK_EXT_validation_cache"),
+    (ext_vertex_attribute_divisor, "VK_EXT_vertex_attribute_divisor"),
+    (ext_vertex_input_dynamic_state, "VK_EXT_vertex_input_dynamic_state"),
+    (ext_video_encode_h264, "VK_EXT_video_encode_h264"),
+    (ext_video_encode_h265, "VK_EXT_video_encode_h265"),
+    (ext_ycbcr_2plane_444_formats, "VK_EXT_ycbcr_2plane_444_formats"),
+    (ext_ycbcr_image_arrays, "VK_EXT_ycbcr_image_arrays"),
+    (ext_nested_command_buffer, "VK_EXT_nested_command_buffer"),
+    (amd_buffer_marker, "VK_AMD_buffer_marker"),
+    (amd_device_coherent_memory, "VK_AMD_device_coherent_memory"),
+    (amd_display_native_hdr, "VK_AMD_display_native_hdr"),
+    (amd_draw_indirect_count, "VK_AMD_draw_indirect_count"),
+    (amd_gcn_shader, "VK_AMD_gcn_shader"),
+    (amd_gpu_shader_half_float, "VK_AMD_gpu_shader_half_float"),
+    (amd_gpu_shader_int16, "VK_AMD_gpu_shader_int16"),
+    (amd_memory_overallocation_behavior, "VK_AMD_memory_overallocation_behavior"),
+    (amd_mixed_attachment_samples, "VK_AMD_mixed_attachment_samples"),
+    (amd_pipeline_compiler_control, "VK_AMD_pipeline_compiler_control"),
+    (amd_rasterization_order, "VK_AMD_rasterization_order"),
+    (amd_shader_ballot, "VK_AMD_shader_ballot"),
+    (amd_shader_core_properties, "VK_AMD_shader_core_properties"),
+    (amd_shader_core_properties2, "VK_AMD_shader_core_properties2"),
+    (amd_shader_early_and_late_fragment_tests, "VK_AMD_shader_early_and_late_fragment_tests"),
+    (amd_shader_explicit_vertex_parameter, "VK_AMD_shader_explicit_vertex_parameter"),
+    (amd_shader_fragment_mask, "VK_AMD_shader_fragment_mask"),
+    (amd_shader_image_load_store_lod, "VK_AMD_shader_image_load_store_lod"),
+    (amd_shader_info, "VK_AMD_shader_info"),
+    (amd_shader_trinary_minmax, "VK_AMD_shader_trinary_minmax"),
+    (amd_texture_gather_bias_lod, "VK_AMD_texture_gather_bias_lod"),
+    (android_external_memory_android_hardware_buffer, "VK_ANDROID_external_memory_android_hardware_buffer"),
+    (arm_rasterization_order_attachment_access, "VK_ARM_rasterization_order_attachment_access"),
+    (arm_shader_core_builtins, "VK_ARM_shader_core_builtins"),
+    (arm_shader_core_properties, "VK_ARM_shader_core_properties"),
+    (fuchsia_buffer_collection, "VK_FUCHSIA_buffer_collection"),
+    (fuchsia_external_memory, "VK_FUCHSIA_external_memory"),
+    (fuchsia_external_semaphore, "VK_FUCHSIA_external_semaphore"),
+    (ggp_frame_token, "VK_GGP_frame_token"),
+    (google_decorate_string, "VK_GOOGLE_decorate_string"),
+    (google_display_timing, "VK_GOOGLE_display_timing"),
+    (google_hlsl_functionality1, "VK_GOOGLE_hlsl_functionality1"),
+    (google_user_type, "VK_GOOGLE_user_type"),
+    (huawei_cluster_culling_shader, "VK_HUAWEI_cluster_culling_shader"),
+    (huawei_invocation_mask, "VK_HUAWEI_invocation_mask"),
+    (huawei_subpass_shading, "VK_HUAWEI_subpass_shading"),
+    (img_filter_cubic, "VK_IMG_filter_cubic"),
+    (img_format_pvrtc, "VK_IMG_format_pvrtc"),
+    (intel_performance_query, "VK_INTEL_performance_query"),
+    (intel_shader_integer_functions2, "VK_INTEL_shader_integer_functions2"),
+    (nvx_binary_import, "VK_NVX_binary_import"),
+    (nvx_image_view_handle, "VK_NVX_image_view_handle"),
+    (nvx_multiview_per_view_attributes, "VK_NVX_multiview_per_view_attributes"),
+    (nv_acquire_winrt_display, "VK_NV_acquire_winrt_display"),
+    (nv_clip_space_w_scaling, "VK_NV_clip_space_w_scaling"),
+    (nv_compute_shader_derivatives, "VK_NV_compute_shader_derivatives"),
+    (nv_cooperative_matrix, "VK_NV_cooperative_matrix"),
+    (nv_copy_memory_indirect, "VK_NV_copy_memory_indirect"),
+    (nv_corner_sampled_image, "VK_NV_corner_sampled_image"),
+    (nv_coverage_reduction_mode, "VK_NV_coverage_reduction_mode"),
+    (nv_dedicated_allocation, "VK_NV_dedicated_allocation"),
+    (nv_dedicated_allocation_image_aliasing, "VK_NV_dedicated_allocation_image_aliasing"),
+    (nv_device_diagnostic_checkpoints, "VK_NV_device_diagnostic_checkpoints"),
+    (nv_device_diagnostics_config, "VK_NV_device_diagnostics_config"),
+    (nv_device_generated_commands, "VK_NV_device_generated_commands"),
+    (nv_displacement_micromap, "VK_NV_displacement_micromap"),
+    (nv_external_memory, "VK_NV_external_memory"),
+    (nv_external_memory_rdma, "VK_NV_external_memory_rdma"),
+    (nv_external_memory_win32, "VK_NV_external_memory_win32"),
+    (nv_fill_rectangle, "VK_NV_fill_rectangle"),
+    (nv_fragment_coverage_to_color, "VK_NV_fragment_coverage_to_color"),
+    (nv_fragment_shader_barycentric, "VK_NV_fragment_shader_barycentric"),
+    (nv_fragment_shading_rate_enums, "VK_NV_fragment_shading_rate_enums"),
+    (nv_framebuffer_mixed_samples, "VK_NV_framebuffer_mixed_samples"),
+    (nv_geometry_shader_passthrough, "VK_NV_geometry_shader_passthrough"),
+    (nv_glsl_shader, "VK_NV_glsl_shader"),
+    (nv_inherited_viewport_scissor, "VK_NV_inherited_viewport_scissor"),
+    (nv_linear_color_attachment, "VK_NV_linear_color_attachment"),
+    (nv_low_latency, "VK_NV_low_latency"),
+    (nv_memory_decompression, "VK_NV_memory_decompression"),
+    (nv_mesh_shader, "VK_NV_mesh_shader"),
+    (nv_optical_flow, "VK_NV_optical_flow"),
+    (nv_present_barrier, "VK_NV_present_barrier"),
+    (nv_ray_tracing, "VK_NV_ray_tracing"),
+    (nv_ray_tracing_invocation_reorder, "VK_NV_ray_tracing_invocation_reorder"),
+    (nv_ray_tracing_motion_blur, "VK_NV_ray_tracing_motion_blur"),
+    (nv_representative_fragment_test, "VK_NV_representative_fragment_test"),
+    (nv_sample_mask_override_coverage, "VK_NV_sample_mask_override_coverage"),
+    (nv_scissor_exclusive, "VK_NV_scissor_exclusive"),
+    (nv_shader_image_footprint, "VK_NV_shader_image_footprint"),
+    (nv_shader_sm_builtins, "VK_NV_shader_sm_builtins"),
+    (nv_shader_subgroup_partitioned, "VK_NV_shader_subgroup_partitioned"),
+    (nv_shading_rate_image, "VK_NV_shading_rate_image"),
+    (nv_viewport_array2, "VK_NV_viewport_array2"),
+    (nv_viewport_swizzle, "VK_NV_viewport_swizzle"),
+    (nv_win32_keyed_mutex, "VK_NV_win32_keyed_mutex"),
+    (qcom_fragment_density_map_offset, "VK_QCOM_fragment_density_map_offset"),
+    (qcom_image_processing, "VK_QCOM_image_processing"),
+    (qcom_multiview_per_view_render_areas, "VK_QCOM_multiview_per_view_render_areas"),
+    (qcom_multiview_per_view_viewports, "VK_QCOM_multiview_per_view_viewports"),
+    (qcom_render_pass_shader_resolve, "VK_QCOM_render_pass_shader_resolve"),
+    (qcom_render_pass_store_ops, "VK_QCOM_render_pass_store_ops"),
+    (qcom_render_pass_transform, "VK_QCOM_render_pass_transform"),
+    (qcom_rotated_copy_commands, "VK_QCOM_rotated_copy_commands"),
+    (qcom_tile_properties, "VK_QCOM_tile_properties"),
+    (sec_amigo_profiling, "VK_SEC_amigo_profiling"),
+    (valve_descriptor_set_host_mapping, "VK_VALVE_descriptor_set_host_mapping"),
+    (valve_mutable_descriptor_type, "VK_VALVE_mutable_descriptor_type"),
+);
+impl DeviceExtensions {
+    /// the extensions promoted to the core API of `api_version`.
+    pub fn core_in(api_version: Version) -> Self {
+        let mut extensions = Self::default();
+        if api_version >= Version::new(1, 1, 0) {
+            extensions.khr_16bit_storage = true;
+            extensions.khr_bind_memory2 = true;
+            extensions.khr_dedicated_allocation = true;
+            extensions.khr_descriptor_update_template = true;
+            extensions.khr_device_group = true;
+            extensions.khr_external_fence = true;
+            extensions.khr_external_memory = true;
+            extensions.khr_external_semaphore = true;
+            extensions.khr_get_memory_requirements2 = true;
+            extensions.khr_maintenance1 = true;
+            extensions.khr_maintenance2 = true;
+            extensions.khr_maintenance3 = true;
+            extensions.khr_multiview = true;
+            extensions.khr_relaxed_block_layout = true;
+            extensions.khr_sampler_ycbcr_conversion = true;
+            extensions.khr_shader_draw_parameters = true;
+            extensions.khr_storage_buffer_storage_class = true;
+            extensions.khr_variable_pointers = true;
+        }
+        if api_version >= Version::new(1, 2, 0) {
+            extensions.khr_8bit_storage = true;
+            extensions.khr_buffer_device_address = true;
+            extensions.khr_create_renderpass2 = true;
+            extensions.khr_depth_stencil_resolve = true;
+            extensions.khr_draw_indirect_count = true;
+            extensions.khr_driver_properties = true;
+            extensions.khr_image_format_list = true;
+            extensions.khr_imageless_framebuffer = true;
+            extensions.khr_sampler_mirror_clamp_to_edge = true;
+            extensions.khr_separate_depth_stencil_layouts = true;
+            extensions.khr_shader_atomic_int64 = true;
+            extensions.khr_shader_float16_int8 = true;
+            extensions.khr_shader_float_controls = true;
+            extensions.khr_shader_subgroup_extended_types = true;
+            extensions.khr_spirv_1_4 = true;
+            extensions.khr_timeline_semaphore = true;
+            extensions.khr_uniform_buffer_standard_layout = true;
+            extensions.khr_vulkan_memory_model = true;
+            extensions.ext_descriptor_indexing = true;
+            extensions.ext_host_query_reset = true;
+            extensions.ext_sampler_filter_minmax = true;
+            extensions.ext_scalar_block_layout = true;
+            extensions.ext_separate_stencil_usage = true;
+            extensions.ext_shader_viewport_index_layer = true;
+        }
+        if api_version >= Version::new(1, 3, 0) {
+            extensions.khr_copy_commands2 = true;
+            extensions.khr_dynamic_rendering = true;
+            extensions.khr_format_feature_flags2 = true;
+            extensions.khr_maintenance4 = true;
+            extensions.khr_shader_integer_dot_product = true;
+            extensions.khr_shader_non_semantic_info = true;
+            extensions.khr_shader_terminate_invocation = true;
+            extensions.khr_synchronization2 = true;
+            extensions.khr_zero_initialize_workgroup_memory = true;
+            extensions.ext_4444_formats = true;
+            extensions.ext_extended_dynamic_state = true;
+            extensions.ext_extended_dynamic_state2 = true;
+            extensions.ext_image_robustness = true;
+            extensions.ext_inline_uniform_block = true;
+            extensions.ext_pipeline_creation_cache_control = true;
+            extensions.ext_pipeline_creation_feedback = true;
+            extensions.ext_private_data = true;
+            extensions.ext_shader_demote_to_helper_invocation = true;
+            extensions.ext_subgroup_size_control = true;
+            extensions.ext_texel_buffer_alignment = true;
+            extensions.ext_texture_compression_astc_hdr = true;
+            extensions.ext_tooling_info = true;
+            extensions.ext_ycbcr_2plane_444_formats = true;
+        }
+        extensions
+    }
+    /// `self` along with every device extension the enabled extensions depend on,
+    /// leaving out dependencies that are core in `api_version`. Instance extensions
+    /// required by device extensions are not included.
+    pub fn with_dependencies(&self, api_version: Version) -> Self {
+        let mut extensions = *self;
+        loop {
+            let previous = extensions;
+            if extensions.khr_16bit_storage && api_version < Version::new(1, 1, 0) { extensions.khr_storage_buffer_storage_class = true; }
+            if extensions.khr_8bit_storage && api_version < Version::new(1, 1, 0) { extensions.khr_storage_buffer_storage_class = true; }
+            if extensions.khr_acceleration_structure && api_version < Version::new(1, 2, 0) { extensions.ext_descriptor_indexing = true; }
+            if extensions.khr_acceleration_structure && api_version < Version::new(1, 2, 0) { extensions.khr_buffer_device_address = true; }
+            if extensions.khr_acceleration_structure { extensions.khr_deferred_host_operations = true; }
+            if extensions.khr_buffer_device_address && api_version < Version::new(1, 1, 0) { extensions.khr_device_group = true; }
+            if extensions.khr_create_renderpass2 && api_version < Version::new(1, 1, 0) { extensions.khr_multiview = true; }
+            if extensions.khr_create_renderpass2 && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance2 = true; }
+            if extensions.khr_dedicated_allocation && api_version < Version::new(1, 1, 0) { extensions.khr_get_memory_requirements2 = true; }
+            if extensions.khr_depth_stencil_resolve && api_version < Version::new(1, 2, 0) { extensions.khr_create_renderpass2 = true; }
+            if extensions.khr_display_swapchain { extensions.khr_swapchain = true; }
+            if extensions.khr_dynamic_rendering && api_version < Version::new(1, 2, 0) { extensions.khr_depth_stencil_resolve = true; }
+            if extensions.khr_external_fence_fd && api_version < Version::new(1, 1, 0) { extensions.khr_external_fence = true; }
+            if extensions.khr_external_fence_win32 && api_version < Version::new(1, 1, 0) { extensions.khr_external_fence = true; }
+            if extensions.khr_external_memory_fd && api_version < Version::new(1, 1, 0) { extensions.khr_external_memory = true; }
+            if extensions.khr_external_memory_win32 && api_version < Version::new(1, 1, 0) { extensions.khr_external_memory = true; }
+            if extensions.khr_external_semaphore_fd && api_version < Version::new(1, 1, 0) { extensions.khr_external_semaphore = true; }
+            if extensions.khr_external_semaphore_win32 && api_version < Version::new(1, 1, 0) { extensions.khr_external_semaphore = true; }
+            if extensions.khr_fragment_shading_rate && api_version < Version::new(1, 2, 0) { extensions.khr_create_renderpass2 = true; }
+            if extensions.khr_imageless_framebuffer && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance2 = true; }
+            if extensions.khr_imageless_framebuffer && api_version < Version::new(1, 2, 0) { extensions.khr_image_format_list = true; }
+            if extensions.khr_incremental_present { extensions.khr_swapchain = true; }
+            if extensions.khr_present_id { extensions.khr_swapchain = true; }
+            if extensions.khr_present_wait { extensions.khr_swapchain = true; }
+            if extensions.khr_present_wait { extensions.khr_present_id = true; }
+            if extensions.khr_ray_query && api_version < Version::new(1, 2, 0) { extensions.khr_spirv_1_4 = true; }
+            if extensions.khr_ray_query { extensions.khr_acceleration_structure = true; }
+            if extensions.khr_ray_tracing_maintenance1 { extensions.khr_acceleration_structure = true; }
+            if extensions.khr_ray_tracing_pipeline && api_version < Version::new(1, 2, 0) { extensions.khr_spirv_1_4 = true; }
+            if extensions.khr_ray_tracing_pipeline { extensions.khr_acceleration_structure = true; }
+            if extensions.khr_ray_tracing_position_fetch { extensions.khr_acceleration_structure = true; }
+            if extensions.khr_sampler_ycbcr_conversion && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance1 = true; }
+            if extensions.khr_sampler_ycbcr_conversion && api_version < Version::new(1, 1, 0) { extensions.khr_bind_memory2 = true; }
+            if extensions.khr_sampler_ycbcr_conversion && api_version < Version::new(1, 1, 0) { extensions.khr_get_memory_requirements2 = true; }
+            if extensions.khr_separate_depth_stencil_layouts && api_version < Version::new(1, 2, 0) { extensions.khr_create_renderpass2 = true; }
+            if extensions.khr_shared_presentable_image { extensions.khr_swapchain = true; }
+            if extensions.khr_spirv_1_4 && api_version < Version::new(1, 2, 0) { extensions.khr_shader_float_controls = true; }
+            if extensions.khr_swapchain_mutable_format { extensions.khr_swapchain = true; }
+            if extensions.khr_swapchain_mutable_format && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance2 = true; }
+            if extensions.khr_swapchain_mutable_format && api_version < Version::new(1, 2, 0) { extensions.khr_image_format_list = true; }
+            if extensions.khr_variable_pointers && api_version < Version::new(1, 1, 0) { extensions.khr_storage_buffer_storage_class = true; }
+            if extensions.khr_video_decode_h264 { extensions.khr_video_decode_queue = true; }
+            if extensions.khr_video_decode_h265 { extensions.khr_video_decode_queue = true; }
+            if extensions.khr_video_decode_queue { extensions.khr_video_queue = true; }
+            if extensions.khr_video_decode_queue && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.khr_video_encode_queue { extensions.khr_video_queue = true; }
+            if extensions.khr_video_encode_queue && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.khr_video_queue && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.khr_win32_keyed_mutex { extensions.khr_external_memory_win32 = true; }
+            if extensions.ext_attachment_feedback_loop_dynamic_state { extensions.ext_attachment_feedback_loop_layout = true; }
+            if extensions.ext_border_color_swizzle { extensions.ext_custom_border_color = true; }
+            if extensions.ext_descriptor_buffer && api_version < Version::new(1, 2, 0) { extensions.khr_buffer_device_address = true; }
+            if extensions.ext_descriptor_buffer && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.ext_descriptor_buffer && api_version < Version::new(1, 2, 0) { extensions.ext_descriptor_indexing = true; }
+            if extensions.ext_descriptor_indexing && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance3 = true; }
+            if extensions.ext_display_control { extensions.khr_swapchain = true; }
+            if extensions.ext_dynamic_rendering_unused_attachments && api_version < Version::new(1, 3, 0) { extensions.khr_dynamic_rendering = true; }
+            if extensions.ext_external_memory_dma_buf { extensions.khr_external_memory_fd = true; }
+            if extensions.ext_external_memory_host && api_version < Version::new(1, 1, 0) { extensions.khr_external_memory = true; }
+            if extensions.ext_fragment_density_map2 { extensions.ext_fragment_density_map = true; }
+            if extensions.ext_full_screen_exclusive { extensions.khr_swapchain = true; }
+            if extensions.ext_global_priority_query { extensions.khr_global_priority = true; }
+            if extensions.ext_graphics_pipeline_library { extensions.khr_pipeline_library = true; }
+            if extensions.ext_hdr_metadata { extensions.khr_swapchain = true; }
+            if extensions.ext_image_2d_view_of_3d && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance1 = true; }
+            if extensions.ext_image_compression_control_swapchain { extensions.ext_image_compression_control = true; }
+            if extensions.ext_image_drm_format_modifier && api_version < Version::new(1, 1, 0) { extensions.khr_bind_memory2 = true; }
+            if extensions.ext_image_drm_format_modifier && api_version < Version::new(1, 1, 0) { extensions.khr_sampler_ycbcr_conversion = true; }
+            if extensions.ext_image_drm_format_modifier && api_version < Version::new(1, 2, 0) { extensions.khr_image_format_list = true; }
+            if extensions.ext_image_sliced_view_of_3d && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance1 = true; }
+            if extensions.ext_inline_uniform_block && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance1 = true; }
+            if extensions.ext_mesh_shader && api_version < Version::new(1, 2, 0) { extensions.khr_spirv_1_4 = true; }
+            if extensions.ext_multisampled_render_to_single_sampled && api_version < Version::new(1, 2, 0) { extensions.khr_create_renderpass2 = true; }
+            if extensions.ext_multisampled_render_to_single_sampled && api_version < Version::new(1, 2, 0) { extensions.khr_depth_stencil_resolve = true; }
+            if extensions.ext_mutable_descriptor_type && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance3 = true; }
+            if extensions.ext_opacity_micromap { extensions.khr_acceleration_structure = true; }
+            if extensions.ext_opacity_micromap && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.ext_pageable_device_local_memory { extensions.ext_memory_priority = true; }
+            if extensions.ext_pipeline_library_group_handles { extensions.khr_ray_tracing_pipeline = true; }
+            if extensions.ext_pipeline_library_group_handles { extensions.khr_pipeline_library = true; }
+            if extensions.ext_primitives_generated_query { extensions.ext_transform_feedback = true; }
+            if extensions.ext_queue_family_foreign && api_version < Version::new(1, 1, 0) { extensions.khr_external_memory = true; }
+            if extensions.ext_rgba10x6_formats && api_version < Version::new(1, 1, 0) { extensions.khr_sampler_ycbcr_conversion = true; }
+            if extensions.ext_shader_atomic_float2 { extensions.ext_shader_atomic_float = true; }
+            if extensions.ext_shader_module_identifier && api_version < Version::new(1, 3, 0) { extensions.ext_pipeline_creation_cache_control = true; }
+            if extensions.ext_shader_object && api_version < Version::new(1, 3, 0) { extensions.khr_dynamic_rendering = true; }
+            if extensions.ext_swapchain_maintenance1 { extensions.khr_swapchain = true; }
+            if extensions.ext_video_encode_h264 { extensions.khr_video_encode_queue = true; }
+            if extensions.ext_video_encode_h265 { extensions.khr_video_encode_queue = true; }
+            if extensions.ext_ycbcr_2plane_444_formats && api_version < Version::new(1, 1, 0) { extensions.khr_sampler_ycbcr_conversion = true; }
+            if extensions.ext_ycbcr_image_arrays && api_version < Version::new(1, 1, 0) { extensions.khr_sampler_ycbcr_conversion = true; }
+            if extensions.ext_nested_command_buffer && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance3 = true; }
+            if extensions.amd_display_native_hdr { extensions.khr_swapchain = true; }
+            if extensions.amd_shader_core_properties2 { extensions.amd_shader_core_properties = true; }
+            if extensions.android_external_memory_android_hardware_buffer && api_version < Version::new(1, 1, 0) { extensions.khr_sampler_ycbcr_conversion = true; }
+            if extensions.android_external_memory_android_hardware_buffer && api_version < Version::new(1, 1, 0) { extensions.khr_external_memory = true; }
+            if extensions.android_external_memory_android_hardware_buffer { extensions.ext_queue_family_foreign = true; }
+            if extensions.android_external_memory_android_hardware_buffer && api_version < Version::new(1, 1, 0) { extensions.khr_dedicated_allocation = true; }
+            if extensions.fuchsia_buffer_collection { extensions.fuchsia_external_memory = true; }
+            if extensions.fuchsia_buffer_collection && api_version < Version::new(1, 1, 0) { extensions.khr_sampler_ycbcr_conversion = true; }
+            if extensions.fuchsia_external_memory && api_version < Version::new(1, 1, 0) { extensions.khr_external_memory = true; }
+            if extensions.fuchsia_external_semaphore && api_version < Version::new(1, 1, 0) { extensions.khr_external_semaphore = true; }
+            if extensions.ggp_frame_token { extensions.khr_swapchain = true; }
+            if extensions.google_display_timing { extensions.khr_swapchain = true; }
+            if extensions.huawei_invocation_mask { extensions.khr_ray_tracing_pipeline = true; }
+            if extensions.huawei_invocation_mask && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.huawei_subpass_shading && api_version < Version::new(1, 2, 0) { extensions.khr_create_renderpass2 = true; }
+            if extensions.huawei_subpass_shading && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.nvx_multiview_per_view_attributes && api_version < Version::new(1, 1, 0) { extensions.khr_multiview = true; }
+            if extensions.nv_copy_memory_indirect && api_version < Version::new(1, 2, 0) { extensions.khr_buffer_device_address = true; }
+            if extensions.nv_coverage_reduction_mode { extensions.nv_framebuffer_mixed_samples = true; }
+            if extensions.nv_dedicated_allocation_image_aliasing && api_version < Version::new(1, 1, 0) { extensions.khr_dedicated_allocation = true; }
+            if extensions.nv_device_generated_commands && api_version < Version::new(1, 2, 0) { extensions.khr_buffer_device_address = true; }
+            if extensions.nv_displacement_micromap { extensions.ext_opacity_micromap = true; }
+            if extensions.nv_external_memory_rdma && api_version < Version::new(1, 1, 0) { extensions.khr_external_memory = true; }
+            if extensions.nv_external_memory_win32 { extensions.nv_external_memory = true; }
+            if extensions.nv_fragment_shading_rate_enums { extensions.khr_fragment_shading_rate = true; }
+            if extensions.nv_memory_decompression && api_version < Version::new(1, 2, 0) { extensions.khr_buffer_device_address = true; }
+            if extensions.nv_optical_flow && api_version < Version::new(1, 3, 0) { extensions.khr_format_feature_flags2 = true; }
+            if extensions.nv_optical_flow && api_version < Version::new(1, 3, 0) { extensions.khr_synchronization2 = true; }
+            if extensions.nv_present_barrier { extensions.khr_swapchain = true; }
+            if extensions.nv_ray_tracing && api_version < Version::new(1, 1, 0) { extensions.khr_get_memory_requirements2 = true; }
+            if extensions.nv_ray_tracing_invocation_reorder { extensions.khr_ray_tracing_pipeline = true; }
+            if extensions.nv_ray_tracing_motion_blur { extensions.khr_ray_tracing_pipeline = true; }
+            if extensions.nv_win32_keyed_mutex { extensions.nv_external_memory_win32 = true; }
+            if extensions.qcom_fragment_density_map_offset { extensions.ext_fragment_density_map = true; }
+            if extensions.qcom_image_processing && api_version < Version::new(1, 3, 0) { extensions.khr_format_feature_flags2 = true; }
+            if extensions.qcom_render_pass_transform { extensions.khr_swapchain = true; }
+            if extensions.qcom_rotated_copy_commands { extensions.khr_swapchain = true; }
+            if extensions.qcom_rotated_copy_commands && api_version < Version::new(1, 3, 0) { extensions.khr_copy_commands2 = true; }
+            if extensions.valve_mutable_descriptor_type && api_version < Version::new(1, 1, 0) { extensions.khr_maintenance3 = true; }
+            if extensions == previous {
+                return extensions;
+            }
+        }
+    }
+}
+
 impl<'a> FromIterator<&'a str> for DeviceExtensions {
     fn from_iter<I>(iter: I) -> Self
     where
@@ -2389,4 +2939,52 @@ impl<'a> FromIterator<&'a str> for DeviceExtensions {
         }
         extensions
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::Version;
+
+    use super::DeviceExtensions;
+
+    fn v(minor: u32) -> Version {
+        Version::new(1, minor, 0)
+    }
+
+    #[test]
+    fn core_extensions_accumulate_with_the_version() {
+        assert!(DeviceExtensions::core_in(v(0)).is_empty());
+        let vk11 = DeviceExtensions::core_in(v(1));
+        assert!(vk11.khr_multiview && vk11.khr_maintenance3);
+        assert!(!vk11.khr_8bit_storage && !vk11.khr_dynamic_rendering);
+        let vk13 = DeviceExtensions::core_in(v(3));
+        assert!(vk13.contains(&DeviceExtensions::core_in(v(2))) && vk13.contains(&vk11));
+        assert!(vk13.khr_dynamic_rendering && vk13.ext_descriptor_indexing);
+        assert!(!vk13.khr_swapchain && !vk13.khr_present_id);
+    }
+
+    #[test]
+    fn dependencies_are_followed_transitively() {
+        let requested = DeviceExtensions { khr_dynamic_rendering: true, ..Default::default() };
+        let on_10 = requested.with_dependencies(v(0));
+        assert_eq!(
+            on_10.names(),
+            ["VK_KHR_create_renderpass2", "VK_KHR_depth_stencil_resolve", "VK_KHR_dynamic_rendering", "VK_KHR_maintenance2", "VK_KHR_multiview"]
+        );
+        let on_11 = requested.with_dependencies(v(1));
+        assert_eq!(on_11.names(), ["VK_KHR_create_renderpass2", "VK_KHR_depth_stencil_resolve", "VK_KHR_dynamic_rendering"]);
+        assert_eq!(requested.with_dependencies(v(2)), requested);
+        assert_eq!(on_10.with_dependencies(v(0)), on_10);
+    }
+
+    #[test]
+    fn unconditional_dependencies_apply_on_every_version() {
+        let requested = DeviceExtensions { khr_present_wait: true, khr_ray_query: true, ..Default::default() };
+        for minor in 0..=3 {
+            let extensions = requested.with_dependencies(v(minor));
+            assert!(extensions.khr_swapchain && extensions.khr_present_id);
+            assert!(extensions.khr_acceleration_structure && extensions.khr_deferred_host_operations);
+            assert_eq!(extensions.khr_spirv_1_4, minor < 2);
+            assert_eq!(extensions.khr_buffer_device_address, minor < 2);
+        }
+    }
+}
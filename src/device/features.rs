@@ -1,8 +1,10 @@
-use std::sync::Arc;
+use std::{ffi::c_void, sync::Arc};
 
 use ash::vk;
 
-use crate::instance::Instance;
+use crate::{instance::Instance, Version};
+
+use super::DeviceExtensions;
 
 #[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
 pub struct DeviceFeatures {
@@ -139,8 +141,174 @@ pub struct DeviceFeatures {
     pub sparse_residency_aliased: bool,
     pub variable_multisample_rate: bool,
     pub inherited_queries: bool,
+    // VK_KHR_present_id and VK_KHR_present_wait
+    pub present_id: bool,
+    pub present_wait: bool,
 }
 
+macro_rules! device_features_set_ops {
+    ($($field:ident),* $(,)?) => {
+        impl DeviceFeatures {
+            /// the features enabled in `self` which are not enabled in `other`.
+            pub fn difference(&self, other: &Self) -> Self {
+                Self { $($field: self.$field && !other.$field),* }
+            }
+            pub fn union(&self, other: &Self) -> Self {
+                Self { $($field: self.$field || other.$field),* }
+            }
+            pub fn intersection(&self, other: &Self) -> Self {
+                Self { $($field: self.$field && other.$field),* }
+            }
+            /// whether every feature enabled in `other` is enabled in `self`.
+            pub fn contains(&self, other: &Self) -> bool {
+                other.difference(self).is_empty()
+            }
+            pub fn is_empty(&self) -> bool {
+                $(!self.$field)&&*
+            }
+            /// the names of the enabled features, as they are named in this struct.
+            pub fn names(&self) -> Vec<&'static str> {
+                let mut names = vec![];
+                $(if self.$field { names.push(stringify!($field)); })*
+                names
+            }
+        }
+    };
+}
+device_features_set_ops!(
+    robust_image_access,
+    inline_uniform_block,
+    descriptor_binding_inline_uniform_block_update_after_bind,
+    pipeline_creation_cache_control,
+    private_data,
+    shader_demote_to_helper_invocation,
+    shader_terminate_invocation,
+    subgroup_size_control,
+    compute_full_subgroups,
+    synchronization2,
+    texture_compression_astc_hdr,
+    shader_zero_initialize_workgroup_memory,
+    dynamic_rendering,
+    shader_integer_dot_product,
+    maintenance4,
+    sampler_mirror_clamp_to_edge,
+    draw_indirect_count,
+    storage_buffer8_bit_access,
+    uniform_and_storage_buffer8_bit_access,
+    storage_push_constant8,
+    shader_buffer_int64_atomics,
+    shader_shared_int64_atomics,
+    shader_float16,
+    shader_int8,
+    descriptor_indexing,
+    shader_input_attachment_array_dynamic_indexing,
+    shader_uniform_texel_buffer_array_dynamic_indexing,
+    shader_storage_texel_buffer_array_dynamic_indexing,
+    shader_uniform_buffer_array_non_uniform_indexing,
+    shader_sampled_image_array_non_uniform_indexing,
+    shader_storage_buffer_array_non_uniform_indexing,
+    shader_storage_image_array_non_uniform_indexing,
+    shader_input_attachment_array_non_uniform_indexing,
+    shader_uniform_texel_buffer_array_non_uniform_indexing,
+    shader_storage_texel_buffer_array_non_uniform_indexing,
+    descriptor_binding_uniform_buffer_update_after_bind,
+    descriptor_binding_sampled_image_update_after_bind,
+    descriptor_binding_storage_image_update_after_bind,
+    descriptor_binding_storage_buffer_update_after_bind,
+    descriptor_binding_uniform_texel_buffer_update_after_bind,
+    descriptor_binding_storage_texel_buffer_update_after_bind,
+    descriptor_binding_update_unused_while_pending,
+    descriptor_binding_partially_bound,
+    descriptor_binding_variable_descriptor_count,
+    runtime_descriptor_array,
+    sampler_filter_minmax,
+    scalar_block_layout,
+    imageless_framebuffer,
+    uniform_buffer_standard_layout,
+    shader_subgroup_extended_types,
+    separate_depth_stencil_layouts,
+    host_query_reset,
+    timeline_semaphore,
+    buffer_device_address,
+    buffer_device_address_capture_replay,
+    buffer_device_address_multi_device,
+    vulkan_memory_model,
+    vulkan_memory_model_device_scope,
+    vulkan_memory_model_availability_visibility_chains,
+    shader_output_viewport_index,
+    shader_output_layer,
+    subgroup_broadcast_dynamic_id,
+    storage_buffer16_bit_access,
+    uniform_and_storage_buffer16_bit_access,
+    storage_push_constant16,
+    storage_input_output16,
+    multiview,
+    multiview_geometry_shader,
+    multiview_tessellation_shader,
+    variable_pointers_storage_buffer,
+    variable_pointers,
+    protected_memory,
+    sampler_ycbcr_conversion,
+    shader_draw_parameters,
+    robust_buffer_access,
+    full_draw_index_uint32,
+    image_cube_array,
+    independent_blend,
+    geometry_shader,
+    tessellation_shader,
+    sample_rate_shading,
+    dual_src_blend,
+    logic_op,
+    multi_draw_indirect,
+    draw_indirect_first_instance,
+    depth_clamp,
+    depth_bias_clamp,
+    fill_mode_non_solid,
+    depth_bounds,
+    wide_lines,
+    large_points,
+    alpha_to_one,
+    multi_viewport,
+    sampler_anisotropy,
+    texture_compression_etc2,
+    texture_compression_astc_ldr,
+    texture_compression_bc,
+    occlusion_query_precise,
+    pipeline_statistics_query,
+    vertex_pipeline_stores_and_atomics,
+    fragment_stores_and_atomics,
+    shader_tessellation_and_geometry_point_size,
+    shader_image_gather_extended,
+    shader_storage_image_extended_formats,
+    shader_storage_image_multisample,
+    shader_storage_image_read_without_format,
+    shader_storage_image_write_without_format,
+    shader_uniform_buffer_array_dynamic_indexing,
+    shader_sampled_image_array_dynamic_indexing,
+    shader_storage_buffer_array_dynamic_indexing,
+    shader_storage_image_array_dynamic_indexing,
+    shader_clip_distance,
+    shader_cull_distance,
+    shader_float64,
+    shader_int64,
+    shader_int16,
+    shader_resource_residency,
+    shader_resource_min_lod,
+    sparse_binding,
+    sparse_residency_buffer,
+    sparse_residency_image2_d,
+    sparse_residency_image3_d,
+    sparse_residency2_samples,
+    sparse_residency4_samples,
+    sparse_residency8_samples,
+    sparse_residency16_samples,
+    sparse_residency_aliased,
+    variable_multisample_rate,
+    inherited_queries,
+    present_id,
+    present_wait,
+);
+
 impl DeviceFeatures {
     pub fn get_vulkan13features(&self) -> vk::PhysicalDeviceVulkan13Features {
         vk::PhysicalDeviceVulkan13Features {
@@ -429,63 +597,47 @@ impl DeviceFeatures {
         self.variable_multisample_rate = features.variable_multisample_rate != 0;
         self.inherited_queries = features.inherited_queries != 0;
     }
-    pub fn get_all_features(instance: &Arc<Instance>, physical_device: vk::PhysicalDevice) -> Self {
+    /// queries every feature of a device supporting `version`, including the
+    /// features of `extensions` which aren't part of the core structs there,
+    /// such as `dynamic_rendering` on a Vulkan 1.2 device with `VK_KHR_dynamic_rendering`.
+    pub fn get_all_features(instance: &Arc<Instance>, physical_device: vk::PhysicalDevice, version: Version, extensions: &DeviceExtensions) -> Self {
         let mut this = Self::default();
-        match instance.api_version().minor {
-            0 => {
-                let features = unsafe { instance.instance.get_physical_device_features(physical_device) };
-                this.set_features(&features);
-            }
-            1 => {
-                let mut vk11 = vk::PhysicalDeviceVulkan11Features::default();
-                let mut features = vk::PhysicalDeviceFeatures2 {
-                    p_next: &mut vk11 as *mut _ as _,
-                    ..Default::default()
-                };
-                unsafe { instance.instance.get_physical_device_features2(physical_device, &mut features) }
-                this.set_features(&features.features);
-                this.set_features_11(&vk11);
-            }
-            2 => {
-                let mut vk11 = vk::PhysicalDeviceVulkan11Features::default();
-                let mut vk12 = vk::PhysicalDeviceVulkan12Features {
-                    p_next: &mut vk11 as *mut _ as _,
-                    ..Default::default()
-                };
-                let mut features = vk::PhysicalDeviceFeatures2 {
-                    p_next: &mut vk12 as *mut _ as _,
-                    ..Default::default()
-                };
-                unsafe { instance.instance.get_physical_device_features2(physical_device, &mut features) }
-                this.set_features(&features.features);
-                this.set_features_11(&vk11);
-                this.set_features_12(&vk12);
-                
-            }
-            3 => {
-                let mut vk11 = vk::PhysicalDeviceVulkan11Features::default();
-                let mut vk12 = vk::PhysicalDeviceVulkan12Features {
-                    p_next: &mut vk11 as *mut _ as _,
-                    ..Default::default()
-                };
-                let mut vk13 = vk::PhysicalDeviceVulkan13Features {
-                    p_next: &mut vk12 as *mut _ as _,
-                    ..Default::default()
-                };
-                let mut features = vk::PhysicalDeviceFeatures2 {
-                    p_next: &mut vk13 as *mut _ as _,
-                    ..Default::default()
-                };
-                unsafe { instance.instance.get_physical_device_features2(physical_device, &mut features) }
-                this.set_features(&features.features);
-                this.set_features_11(&vk11);
-                this.set_features_12(&vk12);
-                this.set_features_13(&vk13);
-            }
-            _ => {}
+        if !version.supports_version_1_1() {
+            let features = unsafe { instance.instance.get_physical_device_features(physical_device) };
+            this.set_features(&features);
+            this.set_implied_features(extensions, version);
+            return this;
         }
+        let mut vk11 = vk::PhysicalDeviceVulkan11Features::default();
+        let mut vk12 = vk::PhysicalDeviceVulkan12Features::default();
+        let mut vk13 = vk::PhysicalDeviceVulkan13Features::default();
+        let mut extension_features = ExtensionFeatures::default();
+        let mut features = vk::PhysicalDeviceFeatures2::default();
+        extension_features.link(&mut features.p_next, extensions, version);
+        if version.supports_version_1_2() {
+            link(&mut features.p_next, &mut vk11);
+            link(&mut features.p_next, &mut vk12);
+        }
+        if version.supports_version_1_3() {
+            link(&mut features.p_next, &mut vk13);
+        }
+        unsafe { instance.instance.get_physical_device_features2(physical_device, &mut features) }
+        this.set_features(&features.features);
+        if version.supports_version_1_2() {
+            this.set_features_11(&vk11);
+            this.set_features_12(&vk12);
+        }
+        if version.supports_version_1_3() {
+            this.set_features_13(&vk13);
+        }
+        extension_features.read(&mut this, extensions, version);
+        this.set_implied_features(extensions, version);
         this
     }
+    /// the extensions providing the enabled features which aren't core in `version`.
+    pub fn required_extensions(&self, version: Version) -> DeviceExtensions {
+        ExtensionFeatures::required_extensions(self, version).union(&self.implied_extensions(version))
+    }
     pub fn validate_features11(mut self, instance: Arc<ash::Instance>, physical_device: vk::PhysicalDevice) -> Self {
         let mut vk11 = vk::PhysicalDeviceVulkan11Features::default();
         let mut features = vk::PhysicalDeviceFeatures2 {
@@ -644,4 +796,218 @@ impl DeviceFeatures {
         self.shader_integer_dot_product ||
         self.maintenance4
     }
-}
\ No newline at end of file
+}
+/// whether the core feature structs of `version` cover features promoted in `minor`.
+fn is_core(version: Version, minor: Option<u32>) -> bool {
+    minor.is_some_and(|minor| version >= Version::new(1, minor, 0))
+}
+
+/// puts `structure` at the front of the chain starting at `head`.
+fn link<T: vk::ExtendsPhysicalDeviceFeatures2>(head: &mut *mut c_void, structure: &mut T) {
+    let header = structure as *mut T as *mut vk::BaseOutStructure;
+    unsafe { (*header).p_next = *head as _ };
+    *head = header as _;
+}
+
+macro_rules! require_extension {
+    ($extensions:ident, $enabled:expr, $extension:ident) => {
+        if $enabled {
+            $extensions.$extension = true;
+        }
+    };
+    ($extensions:ident, $enabled:expr,) => {};
+}
+macro_rules! extension_features {
+    ($($member:ident: $ty:ty, $($extension:ident)?, $core:expr => [$($field:ident),* $(,)?];)*) => {
+        /// The feature structs used before the Vulkan 1.x feature structs cover
+        /// them, either from an extension or, for Vulkan 1.1, its own structs.
+        #[derive(Default)]
+        pub(crate) struct ExtensionFeatures {
+            $($member: $ty,)*
+        }
+        impl ExtensionFeatures {
+            /// chains the structs available through `extensions` which aren't core in `version`.
+            pub(crate) fn link(&mut self, head: &mut *mut c_void, extensions: &DeviceExtensions, version: Version) {
+                $(if !is_core(version, $core) $(&& extensions.$extension)? {
+                    link(head, &mut self.$member);
+                })*
+            }
+            /// copies the features of the structs chained by [`Self::link`].
+            fn read(&self, features: &mut DeviceFeatures, extensions: &DeviceExtensions, version: Version) {
+                $(if !is_core(version, $core) $(&& extensions.$extension)? {
+                    $(features.$field = self.$member.$field != 0;)*
+                })*
+            }
+            /// the structs with `features` enabled.
+            pub(crate) fn from_features(features: &DeviceFeatures) -> Self {
+                let mut this = Self::default();
+                $($(this.$member.$field = features.$field as vk::Bool32;)*)*
+                this
+            }
+            /// chains the structs enabling any of `features` which aren't core in `version`.
+            pub(crate) fn link_enabled(&mut self, head: &mut *mut c_void, features: &DeviceFeatures, version: Version) {
+                $(if !is_core(version, $core) && ($(features.$field)||*) {
+                    link(head, &mut self.$member);
+                })*
+            }
+            /// the extensions providing the enabled features which aren't core in `version`.
+            fn required_extensions(features: &DeviceFeatures, version: Version) -> DeviceExtensions {
+                let mut extensions = DeviceExtensions::default();
+                $(require_extension!(extensions, !is_core(version, $core) && ($(features.$field)||*), $($extension)?);)*
+                extensions
+            }
+        }
+    };
+}
+extension_features! {
+    storage16: vk::PhysicalDevice16BitStorageFeatures, , Some(2) => [storage_buffer16_bit_access, uniform_and_storage_buffer16_bit_access, storage_push_constant16, storage_input_output16];
+    multiview: vk::PhysicalDeviceMultiviewFeatures, , Some(2) => [multiview, multiview_geometry_shader, multiview_tessellation_shader];
+    variable_pointers: vk::PhysicalDeviceVariablePointersFeatures, , Some(2) => [variable_pointers_storage_buffer, variable_pointers];
+    protected_memory: vk::PhysicalDeviceProtectedMemoryFeatures, , Some(2) => [protected_memory];
+    sampler_ycbcr_conversion: vk::PhysicalDeviceSamplerYcbcrConversionFeatures, , Some(2) => [sampler_ycbcr_conversion];
+    shader_draw_parameters: vk::PhysicalDeviceShaderDrawParametersFeatures, , Some(2) => [shader_draw_parameters];
+    storage8: vk::PhysicalDevice8BitStorageFeatures, khr_8bit_storage, Some(2) => [storage_buffer8_bit_access, uniform_and_storage_buffer8_bit_access, storage_push_constant8];
+    atomic_int64: vk::PhysicalDeviceShaderAtomicInt64Features, khr_shader_atomic_int64, Some(2) => [shader_buffer_int64_atomics, shader_shared_int64_atomics];
+    float16_int8: vk::PhysicalDeviceShaderFloat16Int8Features, khr_shader_float16_int8, Some(2) => [shader_float16, shader_int8];
+    descriptor_indexing: vk::PhysicalDeviceDescriptorIndexingFeatures, ext_descriptor_indexing, Some(2) => [
+        shader_input_attachment_array_dynamic_indexing,
+        shader_uniform_texel_buffer_array_dynamic_indexing,
+        shader_storage_texel_buffer_array_dynamic_indexing,
+        shader_uniform_buffer_array_non_uniform_indexing,
+        shader_sampled_image_array_non_uniform_indexing,
+        shader_storage_buffer_array_non_uniform_indexing,
+        shader_storage_image_array_non_uniform_indexing,
+        shader_input_attachment_array_non_uniform_indexing,
+        shader_uniform_texel_buffer_array_non_uniform_indexing,
+        shader_storage_texel_buffer_array_non_uniform_indexing,
+        descriptor_binding_uniform_buffer_update_after_bind,
+        descriptor_binding_sampled_image_update_after_bind,
+        descriptor_binding_storage_image_update_after_bind,
+        descriptor_binding_storage_buffer_update_after_bind,
+        descriptor_binding_uniform_texel_buffer_update_after_bind,
+        descriptor_binding_storage_texel_buffer_update_after_bind,
+        descriptor_binding_update_unused_while_pending,
+        descriptor_binding_partially_bound,
+        descriptor_binding_variable_descriptor_count,
+        runtime_descriptor_array,
+    ];
+    scalar_block_layout: vk::PhysicalDeviceScalarBlockLayoutFeatures, ext_scalar_block_layout, Some(2) => [scalar_block_layout];
+    imageless_framebuffer: vk::PhysicalDeviceImagelessFramebufferFeatures, khr_imageless_framebuffer, Some(2) => [imageless_framebuffer];
+    uniform_buffer_standard_layout: vk::PhysicalDeviceUniformBufferStandardLayoutFeatures, khr_uniform_buffer_standard_layout, Some(2) => [uniform_buffer_standard_layout];
+    subgroup_extended_types: vk::PhysicalDeviceShaderSubgroupExtendedTypesFeatures, khr_shader_subgroup_extended_types, Some(2) => [shader_subgroup_extended_types];
+    separate_depth_stencil_layouts: vk::PhysicalDeviceSeparateDepthStencilLayoutsFeatures, khr_separate_depth_stencil_layouts, Some(2) => [separate_depth_stencil_layouts];
+    host_query_reset: vk::PhysicalDeviceHostQueryResetFeatures, ext_host_query_reset, Some(2) => [host_query_reset];
+    timeline_semaphore: vk::PhysicalDeviceTimelineSemaphoreFeatures, khr_timeline_semaphore, Some(2) => [timeline_semaphore];
+    buffer_device_address: vk::PhysicalDeviceBufferDeviceAddressFeatures, khr_buffer_device_address, Some(2) => [buffer_device_address, buffer_device_address_capture_replay, buffer_device_address_multi_device];
+    vulkan_memory_model: vk::PhysicalDeviceVulkanMemoryModelFeatures, khr_vulkan_memory_model, Some(2) => [vulkan_memory_model, vulkan_memory_model_device_scope, vulkan_memory_model_availability_visibility_chains];
+    image_robustness: vk::PhysicalDeviceImageRobustnessFeatures, ext_image_robustness, Some(3) => [robust_image_access];
+    inline_uniform_block: vk::PhysicalDeviceInlineUniformBlockFeatures, ext_inline_uniform_block, Some(3) => [inline_uniform_block, descriptor_binding_inline_uniform_block_update_after_bind];
+    pipeline_creation_cache_control: vk::PhysicalDevicePipelineCreationCacheControlFeatures, ext_pipeline_creation_cache_control, Some(3) => [pipeline_creation_cache_control];
+    private_data: vk::PhysicalDevicePrivateDataFeatures, ext_private_data, Some(3) => [private_data];
+    demote_to_helper_invocation: vk::PhysicalDeviceShaderDemoteToHelperInvocationFeatures, ext_shader_demote_to_helper_invocation, Some(3) => [shader_demote_to_helper_invocation];
+    terminate_invocation: vk::PhysicalDeviceShaderTerminateInvocationFeatures, khr_shader_terminate_invocation, Some(3) => [shader_terminate_invocation];
+    subgroup_size_control: vk::PhysicalDeviceSubgroupSizeControlFeatures, ext_subgroup_size_control, Some(3) => [subgroup_size_control, compute_full_subgroups];
+    synchronization2: vk::PhysicalDeviceSynchronization2Features, khr_synchronization2, Some(3) => [synchronization2];
+    astc_hdr: vk::PhysicalDeviceTextureCompressionASTCHDRFeatures, ext_texture_compression_astc_hdr, Some(3) => [texture_compression_astc_hdr];
+    zero_initialize_workgroup_memory: vk::PhysicalDeviceZeroInitializeWorkgroupMemoryFeatures, khr_zero_initialize_workgroup_memory, Some(3) => [shader_zero_initialize_workgroup_memory];
+    dynamic_rendering: vk::PhysicalDeviceDynamicRenderingFeatures, khr_dynamic_rendering, Some(3) => [dynamic_rendering];
+    integer_dot_product: vk::PhysicalDeviceShaderIntegerDotProductFeatures, khr_shader_integer_dot_product, Some(3) => [shader_integer_dot_product];
+    maintenance4: vk::PhysicalDeviceMaintenance4Features, khr_maintenance4, Some(3) => [maintenance4];
+    present_id: vk::PhysicalDevicePresentIdFeaturesKHR, khr_present_id, None => [present_id];
+    present_wait: vk::PhysicalDevicePresentWaitFeaturesKHR, khr_present_wait, None => [present_wait];
+}
+
+macro_rules! implied_features {
+    ($($field:ident: $extension:ident, $core:expr;)*) => {
+        impl DeviceFeatures {
+            /// features without a struct outside the core ones, supported
+            /// whenever their extension is.
+            fn set_implied_features(&mut self, extensions: &DeviceExtensions, version: Version) {
+                $(if !is_core(version, $core) && extensions.$extension {
+                    self.$field = true;
+                })*
+            }
+            fn implied_extensions(&self, version: Version) -> DeviceExtensions {
+                let mut extensions = DeviceExtensions::default();
+                $(if !is_core(version, $core) && self.$field {
+                    extensions.$extension = true;
+                })*
+                extensions
+            }
+        }
+    };
+}
+implied_features! {
+    sampler_mirror_clamp_to_edge: khr_sampler_mirror_clamp_to_edge, Some(2);
+    draw_indirect_count: khr_draw_indirect_count, Some(2);
+    descriptor_indexing: ext_descriptor_indexing, Some(2);
+    sampler_filter_minmax: ext_sampler_filter_minmax, Some(2);
+    shader_output_viewport_index: ext_shader_viewport_index_layer, Some(2);
+    shader_output_layer: ext_shader_viewport_index_layer, Some(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use crate::Version;
+
+    use super::{DeviceExtensions, DeviceFeatures, ExtensionFeatures};
+
+    fn v(minor: u32) -> Version {
+        Version::new(1, minor, 0)
+    }
+
+    /// the types of the structs chained for `features` on `version`.
+    fn chained(features: &DeviceFeatures, version: Version) -> Vec<vk::StructureType> {
+        let mut structs = ExtensionFeatures::from_features(features);
+        let mut head = std::ptr::null_mut();
+        structs.link_enabled(&mut head, features, version);
+        let mut types = vec![];
+        let mut next = head as *const vk::BaseOutStructure;
+        while let Some(structure) = unsafe { next.as_ref() } {
+            types.push(structure.s_type);
+            next = structure.p_next;
+        }
+        types
+    }
+
+    #[test]
+    fn promoted_features_need_their_extension_on_older_versions() {
+        let features = DeviceFeatures { dynamic_rendering: true, timeline_semaphore: true, ..Default::default() };
+        assert_eq!(
+            features.required_extensions(v(1)),
+            DeviceExtensions { khr_dynamic_rendering: true, khr_timeline_semaphore: true, ..Default::default() }
+        );
+        assert_eq!(features.required_extensions(v(2)), DeviceExtensions { khr_dynamic_rendering: true, ..Default::default() });
+        assert!(features.required_extensions(v(3)).is_empty());
+    }
+
+    #[test]
+    fn extension_only_features_always_need_their_extension() {
+        let features = DeviceFeatures { present_wait: true, draw_indirect_count: true, multiview: true, ..Default::default() };
+        assert_eq!(
+            features.required_extensions(v(1)),
+            DeviceExtensions { khr_present_wait: true, khr_draw_indirect_count: true, ..Default::default() }
+        );
+        assert_eq!(features.required_extensions(v(3)), DeviceExtensions { khr_present_wait: true, ..Default::default() });
+    }
+
+    #[test]
+    fn only_structs_outside_the_core_ones_are_chained() {
+        let features = DeviceFeatures { multiview: true, dynamic_rendering: true, present_id: true, ..Default::default() };
+        let mut on_11 = chained(&features, v(1));
+        on_11.sort_by_key(|ty| ty.as_raw());
+        assert_eq!(
+            on_11,
+            [
+                vk::StructureType::PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES,
+                vk::StructureType::PHYSICAL_DEVICE_MULTIVIEW_FEATURES,
+                vk::StructureType::PHYSICAL_DEVICE_PRESENT_ID_FEATURES_KHR,
+            ]
+        );
+        assert_eq!(chained(&features, v(3)), [vk::StructureType::PHYSICAL_DEVICE_PRESENT_ID_FEATURES_KHR]);
+        let structs = ExtensionFeatures::from_features(&features);
+        assert_eq!((structs.dynamic_rendering.dynamic_rendering, structs.maintenance4.maintenance4), (vk::TRUE, vk::FALSE));
+    }
+}
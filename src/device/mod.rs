@@ -1,35 +1,121 @@
+mod deletion;
 mod extensions;
 mod features;
 mod functions;
 mod physical_device;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+mod selector;
+use std::{collections::{BTreeMap, HashMap}, sync::{Arc, Mutex, OnceLock, PoisonError}, time::Duration};
 
 use ash::vk::{self};
+pub(crate) use deletion::*;
 pub use extensions::*;
 pub use features::*;
 pub use functions::*;
 pub use physical_device::*;
+pub use selector::*;
 
-use crate::{error::VulkanError, image::{FormatFeatureFlags, ImageTiling}, instance::Instance, memory::{DeviceMemory, DevicePointer}, pipeline::shader::ShaderStageFlags, queue::{DeviceQueueCreateFlags, Queue, QueueBuilder}, swapchain::Format, sync::Semaphore, PNext, Version};
+use crate::{buffers::BufferOffset, error::VulkanError, image::{FormatFeatureFlags, ImageTiling}, instance::Instance, memory::{DeviceMemory, DevicePointer}, pipeline::shader::ShaderStageFlags, queue::{DeviceQueueCreateFlags, DeviceQueues, Queue, QueueBuilder, QueueRequest, QueueRequests, QueueSelection}, swapchain::Format, sync::{Reactor, Semaphore}, PNext, Version};
 
 #[derive(Clone, Default, Debug)]
 pub struct LogicalDeviceBuilder {
     pub enabled_extensions: DeviceExtensions,
     pub enabled_features: DeviceFeatures,
+    pub preferred_extensions: DeviceExtensions,
+    pub preferred_features: DeviceFeatures,
     pub queue_builders: HashMap<u32, QueueBuilder>,
+    pub queue_requests: QueueRequests,
+    pub defer_destruction: bool,
 }
 impl LogicalDeviceBuilder {
     pub fn new() -> Self {
         Self {
             enabled_extensions: DeviceExtensions::default(),
             enabled_features: DeviceFeatures::default(),
+            preferred_extensions: DeviceExtensions::default(),
+            preferred_features: DeviceFeatures::default(),
             queue_builders: HashMap::new(),
+            queue_requests: QueueRequests::default(),
+            defer_destruction: false,
         }
     }
+    /// extensions the device must support, along with the extensions they depend on.
+    pub fn require_extensions(mut self, extensions: DeviceExtensions) -> Self {
+        self.enabled_extensions = self.enabled_extensions.union(&extensions);
+        self
+    }
+    /// extensions enabled only when the device supports them and their dependencies.
+    pub fn prefer_extensions(mut self, extensions: DeviceExtensions) -> Self {
+        self.preferred_extensions = self.preferred_extensions.union(&extensions);
+        self
+    }
+    /// features the device must support, building fails with [`VulkanError::FeatureNotPresent`] otherwise.
+    /// Features which aren't core in the device's version enable the extensions providing them.
+    pub fn require_features(mut self, features: DeviceFeatures) -> Self {
+        self.enabled_features = self.enabled_features.union(&features);
+        self
+    }
+    /// features enabled only when the device supports them.
+    pub fn prefer_features(mut self, features: DeviceFeatures) -> Self {
+        self.preferred_features = self.preferred_features.union(&features);
+        self
+    }
+    /// checks the requested features and extensions against the device, adding the
+    /// supported preferences, the extensions providing the enabled features and the
+    /// extensions required ones depend on. Extensions promoted to the device's API
+    /// version count as supported and are only enabled if the device still lists them.
+    fn resolve(&mut self, physical_device: &PhysicalDevice) -> Result<(), VulkanError> {
+        let version = physical_device.api_version();
+        let supported_features = physical_device.get_supported_features();
+        let missing = self.enabled_features.difference(supported_features);
+        if !missing.is_empty() {
+            return Err(VulkanError::FeatureNotPresent(missing.names()));
+        }
+        self.enabled_features = self.enabled_features.union(&self.preferred_features.intersection(supported_features));
+
+        let listed = physical_device.get_supported_extensions();
+        let available = listed.union(&DeviceExtensions::core_in(version));
+        let mut extensions = self.enabled_extensions
+            .union(&self.enabled_features.required_extensions(version))
+            .with_dependencies(version);
+        let missing = extensions.difference(&available);
+        if !missing.is_empty() {
+            return Err(VulkanError::ExtensionNotPresent(missing.names()));
+        }
+        for preferred in self.preferred_extensions.each() {
+            let preferred = preferred.with_dependencies(version);
+            if available.contains(&preferred) {
+                extensions = extensions.union(&preferred);
+            }
+        }
+        self.enabled_extensions = extensions.intersection(listed);
+        Ok(())
+    }
     pub fn enable_swapchain_extensions(mut self) -> Self {
         self.enabled_extensions.khr_swapchain = true;
         self
     }
+    pub fn enable_hdr_metadata(mut self) -> Self {
+        self.enabled_extensions.khr_swapchain = true;
+        self.enabled_extensions.ext_hdr_metadata = true;
+        self
+    }
+    /// Buffers, images, image views, pipelines and device memory dropped after
+    /// this are queued for destruction instead of destroyed immediately, see
+    /// [`LogicalDevice::collect_deletions`].
+    pub fn defer_destruction(mut self) -> Self {
+        self.defer_destruction = true;
+        self
+    }
+    /// enables `VK_KHR_present_id` and `VK_KHR_present_wait` along with their
+    /// features, for waiting on presents to reach the display.
+    pub fn enable_present_wait(mut self) -> Self {
+        self.enabled_extensions.khr_swapchain = true;
+        self.enabled_extensions.khr_present_id = true;
+        self.enabled_extensions.khr_present_wait = true;
+        self.enabled_features.present_id = true;
+        self.enabled_features.present_wait = true;
+        self
+    }
     pub fn enable_anisotropic_sampling(mut self) -> Self {
         self.enabled_features.sampler_anisotropy = true;
         self
@@ -125,12 +211,52 @@ impl LogicalDeviceBuilder {
         
         self
     }
-    pub fn build(self, physical_device: Arc<PhysicalDevice>) -> Result<(Arc<LogicalDevice>, impl ExactSizeIterator<Item = Arc<Queue>>), VulkanError> {
+    /// requests `count` queues supporting graphics, see [`Self::build_with_queues`].
+    pub fn graphics_queues(mut self, count: u32, priority: f32) -> Self {
+        self.queue_requests.graphics = Some(QueueRequest::new(count, priority));
+        self
+    }
+    /// only considers graphics families able to present to `surface`.
+    pub fn present_to(mut self, surface: vk::SurfaceKHR) -> Self {
+        self.queue_requests.present = Some(surface);
+        self
+    }
+    /// requests `count` compute queues, preferring a family without graphics for async compute.
+    pub fn compute_queues(mut self, count: u32, priority: f32) -> Self {
+        self.queue_requests.compute = Some(QueueRequest::new(count, priority));
+        self
+    }
+    /// requests `count` transfer queues, preferring a dedicated transfer family.
+    pub fn transfer_queues(mut self, count: u32, priority: f32) -> Self {
+        self.queue_requests.transfer = Some(QueueRequest::new(count, priority));
+        self
+    }
+    /// builds the device with the queues requested through [`Self::graphics_queues`],
+    /// [`Self::compute_queues`] and [`Self::transfer_queues`], picking distinct queue families
+    /// where possible. Where it had to settle for sharing is listed in the selection's fallbacks.
+    pub fn build_with_queues(mut self, physical_device: Arc<PhysicalDevice>) -> Result<(Arc<LogicalDevice>, DeviceQueues), VulkanError> {
+        let selection = QueueSelection::select(&physical_device, &self.queue_requests)?;
+        for (family, priorities) in selection.priorities.iter() {
+            self.queue_builders.insert(*family, QueueBuilder {
+                flags: DeviceQueueCreateFlags::empty(),
+                queue_family_index: *family,
+                queue_count: priorities.len() as u32,
+                idx: 0,
+                p_queue_priorities: priorities.as_ptr(),
+            });
+        }
+        let (device, _) = self.build(physical_device)?;
+        let queues = DeviceQueues::new(&device, selection);
+        Ok((device, queues))
+    }
+    pub fn build(mut self, physical_device: Arc<PhysicalDevice>) -> Result<(Arc<LogicalDevice>, impl ExactSizeIterator<Item = Arc<Queue>>), VulkanError> {
+        self.resolve(&physical_device)?;
         let mut p_next = PNext::new();
         let enabled_extensions: Vec<std::ffi::CString> = (&self.enabled_extensions).into();
         let enabled_extension_names = enabled_extensions.iter().map(|value|{ value.as_ptr() }).collect::<Vec<_>>();
         let vk10 = self.enabled_features.get_features();
-        let mut vk11 = if self.enabled_features.features11_active() && physical_device.version.supports_version_1_1() {
+        // the Vulkan 1.1 struct is only part of Vulkan 1.2, older devices use the extension structs.
+        let mut vk11 = if self.enabled_features.features11_active() && physical_device.version.supports_version_1_2() {
             Some(self.enabled_features.get_vulkan11features())
         } else {
             None
@@ -154,6 +280,9 @@ impl LogicalDeviceBuilder {
         if let Some(vk13) = &mut vk13 {
             p_next.push_back(vk13)
         }
+        let mut extension_features = ExtensionFeatures::from_features(&self.enabled_features);
+        let mut extension_chain = p_next.use_p_next();
+        extension_features.link_enabled(&mut extension_chain, &self.enabled_features, physical_device.version);
         let queue_create_info = self.queue_builders.iter().map(|(queue_idx, val)|{
             vk::DeviceQueueCreateInfo {
                 queue_family_index: val.queue_family_index,
@@ -167,7 +296,7 @@ impl LogicalDeviceBuilder {
             enabled_extension_count: enabled_extension_names.len() as u32,
             pp_enabled_extension_names: enabled_extension_names.as_ptr(),
             p_enabled_features: &vk10,
-            p_next: p_next.use_p_next(),
+            p_next: extension_chain,
             queue_create_info_count: queue_create_info.len() as u32,
             p_queue_create_infos: queue_create_info.as_ptr(),
             ..Default::default()
@@ -190,6 +319,9 @@ impl LogicalDeviceBuilder {
             device: handle,
             enabled_extensions: self.enabled_extensions,
             enabled_features: self.enabled_features,
+            deletions: self.defer_destruction.then(DeletionQueue::new),
+            reactor: OnceLock::new(),
+            queue_locks: Mutex::default(),
             fns
         });
         let queues = {
@@ -209,6 +341,10 @@ pub struct LogicalDevice {
     pub(crate) enabled_extensions: DeviceExtensions,
     pub(crate) enabled_features: DeviceFeatures,
     pub(crate) fns: DeviceFunctions,
+    pub(crate) deletions: Option<DeletionQueue>,
+    pub(crate) reactor: OnceLock<Reactor>,
+    /// one lock per `VkQueue`, shared by every [`Queue`] wrapping it.
+    queue_locks: Mutex<BTreeMap<vk::Queue, Arc<Mutex<()>>>>,
 }
 
 impl LogicalDevice {
@@ -227,6 +363,14 @@ impl LogicalDevice {
     pub fn physical_device(&self) -> Arc<PhysicalDevice> {
         self.physical_device.clone()
     }
+    /// the extensions the device was created with, including dependencies and supported preferences.
+    pub fn enabled_extensions(&self) -> &DeviceExtensions {
+        &self.enabled_extensions
+    }
+    /// the features the device was created with, including supported preferences.
+    pub fn enabled_features(&self) -> &DeviceFeatures {
+        &self.enabled_features
+    }
     pub fn instance(&self) -> Arc<Instance> {
         self.instance.clone()
     }
@@ -278,7 +422,87 @@ impl LogicalDevice {
         unsafe { self.device.bind_image_memory(image, memory.handle, 0).unwrap() };
         Ok((image, memory))
     }
+    /// records `vkCmdDrawIndirectCount`, through `VK_KHR_draw_indirect_count`
+    /// on devices older than Vulkan 1.2.
+    pub(crate) unsafe fn cmd_draw_indirect_count(&self, command_buffer: vk::CommandBuffer, indirect: BufferOffset, count: BufferOffset, max_draw_count: u32, stride: u32) {
+        if self.physical_device.version.supports_version_1_2() {
+            self.device.cmd_draw_indirect_count(command_buffer, indirect.handle, indirect.offset, count.handle, count.offset, max_draw_count, stride)
+        } else {
+            (self.fns.khr_draw_indirect_count.cmd_draw_indirect_count_khr)(command_buffer, indirect.handle, indirect.offset, count.handle, count.offset, max_draw_count, stride)
+        }
+    }
+    /// records `vkCmdDrawIndexedIndirectCount`, through `VK_KHR_draw_indirect_count`
+    /// on devices older than Vulkan 1.2.
+    pub(crate) unsafe fn cmd_draw_indexed_indirect_count(&self, command_buffer: vk::CommandBuffer, indirect: BufferOffset, count: BufferOffset, max_draw_count: u32, stride: u32) {
+        if self.physical_device.version.supports_version_1_2() {
+            self.device.cmd_draw_indexed_indirect_count(command_buffer, indirect.handle, indirect.offset, count.handle, count.offset, max_draw_count, stride)
+        } else {
+            (self.fns.khr_draw_indirect_count.cmd_draw_indexed_indirect_count_khr)(command_buffer, indirect.handle, indirect.offset, count.handle, count.offset, max_draw_count, stride)
+        }
+    }
+    /// the thread waking futures waiting on this device's fences and semaphores.
+    pub(crate) fn reactor(&self) -> &Reactor {
+        self.reactor.get_or_init(|| Reactor::start(self.device.clone()))
+    }
+    /// destroys `handle` now, or once the GPU is past the current deletion point
+    /// when the device defers destruction.
+    pub(crate) fn destroy_deferred(&self, handle: DeferredHandle) {
+        match &self.deletions {
+            Some(deletions) => deletions.push(handle),
+            None => unsafe { handle.destroy(&self.device) },
+        }
+    }
+    #[inline]
+    pub fn defers_destruction(&self) -> bool {
+        self.deletions.is_some()
+    }
+    /// the point objects dropped now are queued at.
+    pub fn deletion_point(&self) -> u64 {
+        self.deletions.as_ref().map_or(0, |deletions| deletions.point())
+    }
+    /// Moves to the next deletion point, returning the one just closed. Call
+    /// this after submitting a frame and pass the returned value to
+    /// [`collect_deletions`](Self::collect_deletions) once that frame's fence signals.
+    pub fn advance_deletion_point(&self) -> u64 {
+        self.deletions.as_ref().map_or(0, |deletions| deletions.advance())
+    }
+    /// Sets the deletion point directly, such as to the value a timeline semaphore
+    /// will be signaled with by the next submission. Points never move backwards.
+    pub fn set_deletion_point(&self, point: u64) {
+        if let Some(deletions) = &self.deletions {
+            deletions.set_point(point);
+        }
+    }
+    /// the number of objects waiting to be destroyed.
+    pub fn pending_deletions(&self) -> usize {
+        self.deletions.as_ref().map_or(0, |deletions| deletions.len())
+    }
+    /// Destroys every queued object dropped at or before the `completed` point.
+    /// The caller guarantees the GPU has finished all work submitted up to it.
+    pub fn collect_deletions(&self, completed: u64) -> usize {
+        self.deletions.as_ref().map_or(0, |deletions| unsafe { deletions.collect(&self.device, completed) })
+    }
+    /// waits for the device to go idle and destroys everything still queued, for shutdown.
+    pub fn flush_deletions(&self) -> Result<usize, VulkanError> {
+        match &self.deletions {
+            Some(deletions) => {
+                self.wait()?;
+                Ok(unsafe { deletions.flush(&self.device) })
+            }
+            None => Ok(0),
+        }
+    }
+    /// the lock serializing access to `queue`, shared by every [`Queue`] wrapping the handle.
+    pub(crate) fn queue_lock(&self, queue: vk::Queue) -> Arc<Mutex<()>> {
+        self.queue_locks.lock().unwrap_or_else(PoisonError::into_inner).entry(queue).or_default().clone()
+    }
+    /// waits for the device to go idle. `vkDeviceWaitIdle` needs every queue to
+    /// be externally synchronized, so this holds all queue locks while waiting
+    /// and must not be called while holding a [`Queue::lock`] guard.
     pub fn wait(&self) -> Result<(), VulkanError> {
+        let locks = self.queue_locks.lock().unwrap_or_else(PoisonError::into_inner).values().cloned().collect::<Vec<_>>();
+        // always locked in handle order so concurrent waits can't deadlock.
+        let _guards = locks.iter().map(|lock| lock.lock().unwrap_or_else(PoisonError::into_inner)).collect::<Vec<_>>();
         unsafe { self.device.device_wait_idle().map_err(VulkanError::from) }
     }
     pub fn find_supported_format<'a>(&self, candidates: &'a [Format], tiling: ImageTiling, features: FormatFeatureFlags) -> Option<Format> {
@@ -299,6 +523,10 @@ impl LogicalDevice {
 
 impl Drop for LogicalDevice {
     fn drop(&mut self) {
+        drop(self.reactor.take());
+        if self.deletions.is_some() {
+            let _ = self.flush_deletions();
+        }
         unsafe { self.device.destroy_device(None) };
     }
 }
\ No newline at end of file
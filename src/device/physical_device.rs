@@ -1,4 +1,4 @@
-use std::{mem::MaybeUninit, sync::Arc};
+use std::{ffi::CStr, fmt, mem::MaybeUninit, sync::Arc};
 
 
 use ash::vk;
@@ -7,6 +7,29 @@ use crate::{entry::ENTRY, instance::Instance, queue::QueueFamilyProperties, swap
 
 use super::{DeviceExtensions, DeviceFeatures, ExtensionProperties};
 
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkPhysicalDeviceType.html>"]
+pub struct PhysicalDeviceType(pub(crate) i32);
+impl PhysicalDeviceType {
+    pub const OTHER: Self = Self(0);
+    pub const INTEGRATED_GPU: Self = Self(1);
+    pub const DISCRETE_GPU: Self = Self(2);
+    pub const VIRTUAL_GPU: Self = Self(3);
+    pub const CPU: Self = Self(4);
+}
+impl fmt::Display for PhysicalDeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Self::INTEGRATED_GPU => "integrated GPU",
+            Self::DISCRETE_GPU => "discrete GPU",
+            Self::VIRTUAL_GPU => "virtual GPU",
+            Self::CPU => "CPU",
+            _ => "other",
+        })
+    }
+}
+
 pub struct PhysicalDevice {
     pub(crate) handle: ash::vk::PhysicalDevice,
     pub(crate) instance: Arc<Instance>,
@@ -44,6 +67,15 @@ impl PhysicalDevice {
         )}
         subgroup_propeties
     }
+    pub fn name(&self) -> String {
+        unsafe { CStr::from_ptr(self.properties.device_name.as_ptr()) }.to_string_lossy().into_owned()
+    }
+    pub fn device_type(&self) -> PhysicalDeviceType {
+        PhysicalDeviceType(self.properties.device_type.as_raw())
+    }
+    pub fn api_version(&self) -> Version {
+        self.version
+    }
     pub fn get_supported_extensions(&self) -> &DeviceExtensions {
         &self.supported_extensions
     }
@@ -63,12 +95,12 @@ impl PhysicalDevice {
             .map(Into::into)
             .collect();
             
-        let supported_features = DeviceFeatures::get_all_features(&instance.clone(), handle);
         let supported_extensions = extension_properties
             .iter()
             .map(|property| property.extension_name.as_str())
             .collect();
         let version = Self::get_api_version(instance.clone(), handle);
+        let supported_features = DeviceFeatures::get_all_features(&instance, handle, version, &supported_extensions);
         let queue_family_properties = instance.instance.get_physical_device_queue_family_properties(handle)
             .iter()
             .map(|value|{
@@ -0,0 +1,292 @@
+use std::{fmt, sync::Arc};
+
+use ash::vk;
+
+use crate::{error::VulkanError, instance::Instance, queue::QueueFlags, Version};
+
+use super::{DeviceExtensions, DeviceFeatures, PhysicalDevice, PhysicalDeviceType};
+
+/// Why a physical device can not be used.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Rejection {
+    ApiVersion { supported: Version, required: Version },
+    MissingExtensions(Vec<&'static str>),
+    MissingFeatures(Vec<&'static str>),
+    /// queue capabilities no queue family of the device has.
+    MissingQueues(QueueFlags),
+    NoPresentSupport,
+}
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ApiVersion { supported, required } => write!(f, "supports Vulkan {} but {} is required", supported, required),
+            Self::MissingExtensions(names) => write!(f, "missing extensions: {}", names.join(", ")),
+            Self::MissingFeatures(names) => write!(f, "missing features: {}", names.join(", ")),
+            Self::MissingQueues(flags) => write!(f, "no queue family supports {:?}", flags),
+            Self::NoPresentSupport => write!(f, "can not present to the surface"),
+        }
+    }
+}
+/// Ranks devices which meet every requirement, compared field by field.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct DeviceScore {
+    /// higher for device types earlier in the preference list.
+    pub device_type: u32,
+    /// how many of the preferred extensions and features are supported.
+    pub preferred: u32,
+    /// size of the largest device local heap in bytes.
+    pub device_local_memory: u64,
+}
+pub struct DeviceReport {
+    pub device: Arc<PhysicalDevice>,
+    /// `Ok` with the score of usable devices, `Err` with every reason the device was rejected.
+    pub outcome: Result<DeviceScore, Vec<Rejection>>,
+}
+/// Every device the instance reported, with the outcome of checking it against a
+/// [`PhysicalDeviceSelector`]. Formatting it gives a report suited for bug reports.
+pub struct DeviceSelection {
+    pub reports: Vec<DeviceReport>,
+    best: Option<usize>,
+}
+impl DeviceSelection {
+    pub fn best(&self) -> Option<&Arc<PhysicalDevice>> {
+        self.best.map(|idx| &self.reports[idx].device)
+    }
+    pub fn rejected(&self) -> impl Iterator<Item = (&Arc<PhysicalDevice>, &[Rejection])> {
+        self.reports.iter().filter_map(|report| match &report.outcome {
+            Err(rejections) => Some((&report.device, rejections.as_slice())),
+            Ok(_) => None,
+        })
+    }
+}
+impl fmt::Display for DeviceSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reports.is_empty() {
+            return writeln!(f, "no physical devices were found");
+        }
+        for (idx, report) in self.reports.iter().enumerate() {
+            let device = &report.device;
+            write_report(f, &device.name(), device.device_type(), device.api_version(), &report.outcome, Some(idx) == self.best)?;
+        }
+        Ok(())
+    }
+}
+/// writes the lines [`DeviceSelection`] formats for one device.
+fn write_report(
+    out: &mut impl fmt::Write,
+    name: &str,
+    device_type: PhysicalDeviceType,
+    api_version: Version,
+    outcome: &Result<DeviceScore, Vec<Rejection>>,
+    selected: bool,
+) -> fmt::Result {
+    write!(out, "{} ({}, Vulkan {}): ", name, device_type, api_version)?;
+    match outcome {
+        Ok(_) if selected => writeln!(out, "selected"),
+        Ok(score) => writeln!(out, "usable but ranked lower than the selected device ({:?})", score),
+        Err(rejections) => {
+            writeln!(out, "rejected")?;
+            for rejection in rejections {
+                writeln!(out, "    - {}", rejection)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Picks the physical device best suited to a set of requirements and preferences.
+#[derive(Clone, Debug, Default)]
+pub struct PhysicalDeviceSelector {
+    pub required_extensions: DeviceExtensions,
+    pub preferred_extensions: DeviceExtensions,
+    pub required_features: DeviceFeatures,
+    pub preferred_features: DeviceFeatures,
+    pub min_api_version: Option<Version>,
+    pub required_queues: QueueFlags,
+    pub surface: Option<vk::SurfaceKHR>,
+    /// most preferred first, defaults to discrete, integrated, virtual then cpu.
+    pub device_types: Vec<PhysicalDeviceType>,
+}
+impl PhysicalDeviceSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn require_extensions(mut self, extensions: DeviceExtensions) -> Self {
+        self.required_extensions = self.required_extensions.union(&extensions);
+        self
+    }
+    pub fn prefer_extensions(mut self, extensions: DeviceExtensions) -> Self {
+        self.preferred_extensions = self.preferred_extensions.union(&extensions);
+        self
+    }
+    pub fn require_features(mut self, features: DeviceFeatures) -> Self {
+        self.required_features = self.required_features.union(&features);
+        self
+    }
+    pub fn prefer_features(mut self, features: DeviceFeatures) -> Self {
+        self.preferred_features = self.preferred_features.union(&features);
+        self
+    }
+    pub fn min_api_version(mut self, version: Version) -> Self {
+        self.min_api_version = Some(version);
+        self
+    }
+    pub fn require_queues(mut self, flags: QueueFlags) -> Self {
+        self.required_queues |= flags;
+        self
+    }
+    /// requires a queue family able to present to `surface`.
+    pub fn require_present(mut self, surface: vk::SurfaceKHR) -> Self {
+        self.surface = Some(surface);
+        self
+    }
+    pub fn prefer_device_types(mut self, device_types: &[PhysicalDeviceType]) -> Self {
+        self.device_types = device_types.to_vec();
+        self
+    }
+    pub fn select(&self, instance: Arc<Instance>) -> Result<DeviceSelection, VulkanError> {
+        let reports = instance.enumerate_physical_devices()?
+            .map(|device| Ok(DeviceReport { outcome: self.check(&device)?, device }))
+            .collect::<Result<Vec<_>, VulkanError>>()?;
+        let best = reports.iter().enumerate()
+            .filter_map(|(idx, report)| report.outcome.as_ref().ok().map(|score| (idx, score)))
+            .max_by_key(|(_, score)| **score)
+            .map(|(idx, _)| idx);
+        Ok(DeviceSelection { reports, best })
+    }
+    fn check(&self, device: &Arc<PhysicalDevice>) -> Result<Result<DeviceScore, Vec<Rejection>>, VulkanError> {
+        let mut rejections = vec![];
+        if let Some(required) = self.min_api_version {
+            if device.api_version() < required {
+                rejections.push(Rejection::ApiVersion { supported: device.api_version(), required });
+            }
+        }
+        let missing_extensions = self.required_extensions.difference(device.get_supported_extensions());
+        if !missing_extensions.is_empty() {
+            rejections.push(Rejection::MissingExtensions(missing_extensions.names()));
+        }
+        let missing_features = self.required_features.difference(device.get_supported_features());
+        if !missing_features.is_empty() {
+            rejections.push(Rejection::MissingFeatures(missing_features.names()));
+        }
+        let families = device.enumerate_queue_family_properties();
+        let supported_queues = families.iter().fold(QueueFlags::empty(), |flags, family| flags | family.queue_flags);
+        let missing_queues = self.required_queues & !supported_queues;
+        if !missing_queues.is_empty() {
+            rejections.push(Rejection::MissingQueues(missing_queues));
+        }
+        if let Some(surface) = self.surface {
+            let mut present = false;
+            for family in 0..families.len() as u32 {
+                present |= device.instance.get_physical_device_surface_support(device.clone(), family, surface)?;
+            }
+            if !present {
+                rejections.push(Rejection::NoPresentSupport);
+            }
+        }
+        if !rejections.is_empty() {
+            return Ok(Err(rejections));
+        }
+        Ok(Ok(self.score(device)))
+    }
+    /// higher for types earlier in the preference list, 0 for types not in it.
+    fn type_rank(&self, device_type: PhysicalDeviceType) -> u32 {
+        const DEFAULT_TYPES: [PhysicalDeviceType; 4] = [
+            PhysicalDeviceType::DISCRETE_GPU,
+            PhysicalDeviceType::INTEGRATED_GPU,
+            PhysicalDeviceType::VIRTUAL_GPU,
+            PhysicalDeviceType::CPU,
+        ];
+        let device_types = if self.device_types.is_empty() { &DEFAULT_TYPES[..] } else { &self.device_types[..] };
+        device_types.iter()
+            .position(|ty| *ty == device_type)
+            .map_or(0, |position| (device_types.len() - position) as u32)
+    }
+    fn score(&self, device: &PhysicalDevice) -> DeviceScore {
+        let device_type = self.type_rank(device.device_type());
+        let preferred = self.preferred_extensions.intersection(device.get_supported_extensions()).names().len()
+            + self.preferred_features.intersection(device.get_supported_features()).names().len();
+        let memory = device.get_physical_device_memory_properties();
+        let device_local_memory = memory.memory_heaps[..memory.memory_heap_count as usize].iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .max()
+            .unwrap_or(0);
+        DeviceScore { device_type, preferred: preferred as u32, device_local_memory }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_compare_type_then_preferences_then_memory() {
+        let score = |device_type, preferred, device_local_memory| DeviceScore { device_type, preferred, device_local_memory };
+        assert!(score(4, 0, 0) > score(3, 10, 1 << 40));
+        assert!(score(3, 2, 0) > score(3, 1, 1 << 40));
+        assert!(score(3, 2, 1 << 33) > score(3, 2, 1 << 32));
+        let scores = [score(3, 1, 8), score(4, 0, 2), score(4, 0, 4)];
+        assert_eq!(scores.iter().max(), Some(&score(4, 0, 4)));
+    }
+    #[test]
+    fn device_types_rank_by_preference() {
+        let selector = PhysicalDeviceSelector::new();
+        let ranks = [
+            PhysicalDeviceType::DISCRETE_GPU,
+            PhysicalDeviceType::INTEGRATED_GPU,
+            PhysicalDeviceType::VIRTUAL_GPU,
+            PhysicalDeviceType::CPU,
+            PhysicalDeviceType::OTHER,
+        ].map(|ty| selector.type_rank(ty));
+        assert_eq!(ranks, [4, 3, 2, 1, 0]);
+        let selector = selector.prefer_device_types(&[PhysicalDeviceType::CPU, PhysicalDeviceType::INTEGRATED_GPU]);
+        assert_eq!(selector.type_rank(PhysicalDeviceType::CPU), 2);
+        assert_eq!(selector.type_rank(PhysicalDeviceType::INTEGRATED_GPU), 1);
+        assert_eq!(selector.type_rank(PhysicalDeviceType::DISCRETE_GPU), 0);
+    }
+    #[test]
+    fn rejections_read_as_reasons() {
+        let reasons = [
+            Rejection::ApiVersion { supported: Version::new(1, 1, 0), required: Version::new(1, 3, 0) },
+            Rejection::MissingExtensions(vec!["VK_KHR_swapchain", "VK_KHR_present_id"]),
+            Rejection::MissingFeatures(vec!["geometry_shader"]),
+            Rejection::MissingQueues(QueueFlags::COMPUTE),
+            Rejection::NoPresentSupport,
+        ].map(|rejection| rejection.to_string());
+        assert_eq!(
+            reasons,
+            [
+                "supports Vulkan 1.1.0 but 1.3.0 is required",
+                "missing extensions: VK_KHR_swapchain, VK_KHR_present_id",
+                "missing features: geometry_shader",
+                "no queue family supports QueueFlags(2)",
+                "can not present to the surface",
+            ]
+        );
+    }
+    #[test]
+    fn report_lists_every_device_and_why() {
+        let score = DeviceScore { device_type: 3, preferred: 1, device_local_memory: 1024 };
+        let mut report = String::new();
+        write_report(&mut report, "Discrete", PhysicalDeviceType::DISCRETE_GPU, Version::new(1, 3, 0), &Ok(score), true).unwrap();
+        write_report(&mut report, "Integrated", PhysicalDeviceType::INTEGRATED_GPU, Version::new(1, 2, 0), &Ok(score), false).unwrap();
+        let rejections = Err(vec![Rejection::NoPresentSupport, Rejection::MissingFeatures(vec!["shader_int64"])]);
+        write_report(&mut report, "Software", PhysicalDeviceType::CPU, Version::new(1, 0, 0), &rejections, false).unwrap();
+        assert_eq!(
+            report,
+            "Discrete (discrete GPU, Vulkan 1.3.0): selected\n\
+             Integrated (integrated GPU, Vulkan 1.2.0): usable but ranked lower than the selected device \
+             (DeviceScore { device_type: 3, preferred: 1, device_local_memory: 1024 })\n\
+             Software (CPU, Vulkan 1.0.0): rejected\n    \
+             - can not present to the surface\n    \
+             - missing features: shader_int64\n"
+        );
+    }
+    #[test]
+    fn empty_selection_says_so() {
+        let selection = DeviceSelection { reports: vec![], best: None };
+        assert_eq!(selection.to_string(), "no physical devices were found\n");
+        assert!(selection.best().is_none());
+    }
+}
@@ -1,6 +1,8 @@
 use ash::vk;
 use thiserror::Error;
 
+use crate::queue::QueueFlags;
+
 #[derive(Error, Debug, PartialEq, PartialOrd)]
 pub enum VulkanError {
     #[error("Out of host memory")]
@@ -13,10 +15,10 @@ pub enum VulkanError {
     InitializationFailed,
     #[error("Layer not present")]
     LayerNotPresent,
-    #[error("Extensions not present")]
-    ExtensionNotPresent,
-    #[error("Features not present")]
-    FeatureNotPresent,
+    #[error("Extensions not present: {0:?}")]
+    ExtensionNotPresent(Vec<&'static str>),
+    #[error("Features not present: {0:?}")]
+    FeatureNotPresent(Vec<&'static str>),
     #[error("Too many objects")]
     TooManyObjects,
     #[error("Lost device")]
@@ -47,6 +49,10 @@ pub enum VulkanError {
     NotAccessibleInHostMemory,
     #[error("The prefix header of the safe pipeline cache is invalid, build the pipeline without the cache")]
     InvalidPipelineCachePrefixHeader,
+    #[error("No queue family supports {0:?}")]
+    NoSuitableQueueFamily(QueueFlags),
+    #[error("No graphics queue family can present to the surface")]
+    NoPresentQueueFamily,
 }
 impl From<ash::vk::Result> for VulkanError {
     fn from(value: vk::Result) -> Self {
@@ -64,13 +70,13 @@ impl From<ash::vk::Result> for VulkanError {
                 VulkanError::LayerNotPresent
             }
             vk::Result::ERROR_EXTENSION_NOT_PRESENT => {
-                VulkanError::ExtensionNotPresent
+                VulkanError::ExtensionNotPresent(vec![])
             }
             vk::Result::ERROR_INCOMPATIBLE_DRIVER => {
                 VulkanError::IncompatibleDriver
             }
             vk::Result::ERROR_FEATURE_NOT_PRESENT => {
-                VulkanError::FeatureNotPresent
+                VulkanError::FeatureNotPresent(vec![])
             }
             vk::Result::ERROR_TOO_MANY_OBJECTS => {
                 VulkanError::TooManyObjects
@@ -136,8 +142,36 @@ pub enum NightfallError {
     NotEnoughDescriptorSets(usize, usize),
     #[error("No Input was provided for this action")]
     NoInputWasGiven,
+    #[error("Push constants need {0} bytes but the device only supports {1}")]
+    PushConstantsTooLarge(u32, u32),
+    #[error("Vertex attribute at location {0} uses {1:?} which the device can not read from vertex buffers")]
+    UnsupportedVertexFormat(u32, vk::Format),
+    #[error("{0} vertex bindings were used but the device supports {1}")]
+    TooManyVertexBindings(u32, u32),
+    #[error("{0} vertex attribute locations were used but the device supports {1}")]
+    TooManyVertexAttributes(u32, u32),
     #[error("{0}")]
     PointerError(PointerError),
+    #[error("{0} storage buffers were bound but the compute job declares {1}")]
+    BindingCountMismatch(usize, u32),
+    #[error("{0} bytes of push constants were given but the compute job declares {1}")]
+    PushConstantSizeMismatch(usize, u32),
+    #[error("{0:?} can neither be blitted with linear filtering nor written by the compute downsampler, mipmaps can not be generated for it")]
+    MipmapFormatUnsupported(vk::Format),
+    #[error("Failed to decode texture: {0}")]
+    TextureDecodeFailed(String),
+    #[error("Textures stored as {0} can not be loaded")]
+    UnsupportedTextureFormat(String),
+    #[error("A buffer of {0} elements was given where {1} are needed")]
+    BufferTooSmall(usize, usize),
+    #[error("{0} elements are more than a primitive can process at once")]
+    TooManyElements(usize),
+    #[error("{0} bytes of pixel data were given but the image needs {1}")]
+    PixelDataSizeMismatch(usize, usize),
+    #[error("The output was read from the results of a different compute run")]
+    ForeignOutput,
+    #[error("Reading {0} bytes from a buffer of {1} bytes")]
+    OutputOutOfBounds(usize, usize),
 }
 impl From<ash::vk::Result> for NightfallError {
     fn from(value: ash::vk::Result) -> Self {
@@ -0,0 +1,195 @@
+//! Layout rules for sharing Rust structs with GLSL blocks.
+//! 
+//! Structs are checked with `#[derive(Std140)]` or `#[derive(Std430)]`, which
+//! fail to compile when a field is not where GLSL expects it. To have the
+//! padding inserted instead, annotate the struct with
+//! `#[glsl_layout(std140)]` or `#[glsl_layout(std430)]`.
+//! ```ignore
+//! #[glsl_layout(std140)]
+//! #[derive(Clone, Copy, Default)]
+//! struct Light {
+//!     position: Vec3,
+//!     intensity: f32,
+//!     color: Vec3,
+//! }
+//! ```
+mod types;
+pub use types::*;
+pub use nightfall_core_macros::{Std140, Std430, glsl_layout};
+
+/// A type with a known std140 layout, the layout of uniform buffers.
+/// 
+/// # Safety
+/// `ALIGN` and `SIZE` must follow the std140 rules for the GLSL type the
+/// implementor stands in for, and `SIZE` must be `size_of::<Self>()`.
+pub unsafe trait Std140: Copy {
+    const ALIGN: usize;
+    const SIZE: usize;
+}
+
+/// A type with a known std430 layout, the default layout of push constant
+/// blocks and storage buffers.
+/// 
+/// # Safety
+/// `ALIGN` and `SIZE` must follow the std430 rules for the GLSL type the
+/// implementor stands in for, and `SIZE` must be `size_of::<Self>()`.
+pub unsafe trait Std430: Copy {
+    const ALIGN: usize;
+    const SIZE: usize;
+}
+
+#[inline]
+pub const fn align_up(offset: usize, align: usize) -> usize {
+    offset.next_multiple_of(align)
+}
+
+/// Explicit padding bytes, inserted by `#[glsl_layout]` and skipped by the
+/// layout derives when the field is marked `#[padding]`.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Padding<const N: usize>([u8; N]);
+impl<const N: usize> Default for Padding<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+macro_rules! glsl_scalar {
+    ($($ty:ty),*) => {
+        $(
+            unsafe impl Std140 for $ty {
+                const ALIGN: usize = std::mem::size_of::<$ty>();
+                const SIZE: usize = std::mem::size_of::<$ty>();
+            }
+            unsafe impl Std430 for $ty {
+                const ALIGN: usize = std::mem::size_of::<$ty>();
+                const SIZE: usize = std::mem::size_of::<$ty>();
+            }
+        )*
+    };
+}
+glsl_scalar!(f32, i32, u32, f64, i64, u64);
+
+/// the size of `[T; N]`, which only matches GLSL when the array stride
+/// needs no padding between the elements.
+const fn packed_array_size<T>(stride: usize, n: usize) -> usize {
+    assert!(stride == std::mem::size_of::<T>(), "the array stride of the element type needs padding, wrap the element in a struct padded to the stride");
+    stride * n
+}
+
+// std140 rounds the alignment and stride of array elements up to a vec4.
+unsafe impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGN: usize = align_up(T::ALIGN, 16);
+    const SIZE: usize = packed_array_size::<T>(align_up(T::SIZE, align_up(T::ALIGN, 16)), N);
+}
+unsafe impl<T: Std430, const N: usize> Std430 for [T; N] {
+    const ALIGN: usize = T::ALIGN;
+    const SIZE: usize = packed_array_size::<T>(align_up(T::SIZE, T::ALIGN), N);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::offset_of;
+
+    use super::*;
+
+    #[glsl_layout(std140)]
+    #[derive(Clone, Copy, Default)]
+    struct Light140 {
+        position: Vec3,
+        intensity: f32,
+        color: Vec3,
+    }
+    #[glsl_layout(std140)]
+    #[derive(Clone, Copy, Default)]
+    struct Scene140 {
+        ambient: f32,
+        lights: [Light140; 2],
+        count: u32,
+    }
+    #[glsl_layout(std430)]
+    #[derive(Clone, Copy, Default)]
+    struct Light430 {
+        position: Vec3,
+        intensity: f32,
+        color: Vec3,
+    }
+    #[glsl_layout(std430)]
+    #[derive(Clone, Copy, Default)]
+    struct Scene430 {
+        ambient: f32,
+        lights: [Light430; 2],
+        count: u32,
+    }
+    #[glsl_layout(std140)]
+    #[derive(Clone, Copy, Default)]
+    struct Pair140 {
+        a: f32,
+        b: f32,
+    }
+    #[glsl_layout(std140)]
+    #[derive(Clone, Copy, Default)]
+    struct Nested140 {
+        value: f32,
+        pair: Pair140,
+        tail: f32,
+    }
+    #[glsl_layout(std430)]
+    #[derive(Clone, Copy, Default)]
+    struct Pair430 {
+        a: f32,
+        b: f32,
+    }
+    #[glsl_layout(std430)]
+    #[derive(Clone, Copy, Default)]
+    struct Nested430 {
+        value: f32,
+        pair: Pair430,
+        tail: f32,
+    }
+    #[repr(C)]
+    #[derive(Clone, Copy, Std430)]
+    struct Particle {
+        position: Vec3,
+        mass: f32,
+        velocity: Vec3,
+        #[padding]
+        _pad: Padding<4>,
+    }
+
+    #[test]
+    fn vec3_packs_with_a_scalar() {
+        assert_eq!((<Light140 as Std140>::ALIGN, <Light140 as Std140>::SIZE), (16, 32));
+        assert_eq!((<Light430 as Std430>::ALIGN, <Light430 as Std430>::SIZE), (16, 32));
+        assert_eq!(offset_of!(Light140, intensity), 12);
+        assert_eq!(offset_of!(Light140, color), 16);
+    }
+    #[test]
+    fn arrays_of_structs() {
+        assert_eq!(<[Light140; 2] as Std140>::SIZE, 64);
+        assert_eq!(<[Light140; 2] as Std140>::SIZE, std::mem::size_of::<[Light140; 2]>());
+        assert_eq!(<[f32; 4] as Std430>::SIZE, 16);
+        assert_eq!(<[Vec4; 3] as Std140>::SIZE, 48);
+        assert_eq!((<Scene140 as Std140>::ALIGN, <Scene140 as Std140>::SIZE), (16, 96));
+        assert_eq!((offset_of!(Scene140, lights), offset_of!(Scene140, count)), (16, 80));
+        assert_eq!((<Scene430 as Std430>::ALIGN, <Scene430 as Std430>::SIZE), (16, 96));
+        assert_eq!((offset_of!(Scene430, lights), offset_of!(Scene430, count)), (16, 80));
+    }
+    #[test]
+    fn std140_rounds_nested_structs_to_vec4() {
+        assert_eq!((<Pair140 as Std140>::ALIGN, <Pair140 as Std140>::SIZE), (16, 16));
+        assert_eq!((<Nested140 as Std140>::ALIGN, <Nested140 as Std140>::SIZE), (16, 48));
+        assert_eq!((offset_of!(Nested140, pair), offset_of!(Nested140, tail)), (16, 32));
+        assert_eq!(std::mem::size_of::<Nested140>(), 48);
+    }
+    #[test]
+    fn std430_packs_nested_structs() {
+        assert_eq!((<Pair430 as Std430>::ALIGN, <Pair430 as Std430>::SIZE), (4, 8));
+        assert_eq!((<Nested430 as Std430>::ALIGN, <Nested430 as Std430>::SIZE), (4, 16));
+        assert_eq!((offset_of!(Nested430, pair), offset_of!(Nested430, tail)), (4, 12));
+    }
+    #[test]
+    fn checked_struct_with_explicit_padding() {
+        assert_eq!((<Particle as Std430>::ALIGN, <Particle as Std430>::SIZE), (16, 32));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,97 @@
+use super::{Std140, Std430};
+
+macro_rules! glsl_vector {
+    ($name:ident, $scalar:ty, $align:expr, $($field:ident),*) => {
+        #[repr(C)]
+        #[derive(Clone, Copy, Default, PartialEq, Debug)]
+        pub struct $name {
+            $(pub $field: $scalar,)*
+        }
+        impl $name {
+            #[inline]
+            pub const fn new($($field: $scalar),*) -> Self {
+                Self { $($field),* }
+            }
+        }
+        impl From<[$scalar; [$(stringify!($field)),*].len()]> for $name {
+            #[inline]
+            fn from([$($field),*]: [$scalar; [$(stringify!($field)),*].len()]) -> Self {
+                Self { $($field),* }
+            }
+        }
+        unsafe impl Std140 for $name {
+            const ALIGN: usize = $align;
+            const SIZE: usize = std::mem::size_of::<Self>();
+        }
+        unsafe impl Std430 for $name {
+            const ALIGN: usize = $align;
+            const SIZE: usize = std::mem::size_of::<Self>();
+        }
+    };
+}
+glsl_vector!(Vec2, f32, 8, x, y);
+glsl_vector!(Vec3, f32, 16, x, y, z);
+glsl_vector!(Vec4, f32, 16, x, y, z, w);
+glsl_vector!(IVec2, i32, 8, x, y);
+glsl_vector!(IVec3, i32, 16, x, y, z);
+glsl_vector!(IVec4, i32, 16, x, y, z, w);
+glsl_vector!(UVec2, u32, 8, x, y);
+glsl_vector!(UVec3, u32, 16, x, y, z);
+glsl_vector!(UVec4, u32, 16, x, y, z, w);
+
+/// A column major `mat2`. Only usable with std430, std140 pads every column
+/// to a `vec4`; use `[Vec4; 2]` there instead.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Mat2 {
+    pub cols: [Vec2; 2],
+}
+unsafe impl Std430 for Mat2 {
+    const ALIGN: usize = 8;
+    const SIZE: usize = 16;
+}
+impl From<[[f32; 2]; 2]> for Mat2 {
+    fn from(cols: [[f32; 2]; 2]) -> Self {
+        Self { cols: cols.map(Vec2::from) }
+    }
+}
+
+/// A column major `mat3`, every column is padded to a `vec4` in both layouts.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Mat3 {
+    pub cols: [Vec4; 3],
+}
+unsafe impl Std140 for Mat3 {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 48;
+}
+unsafe impl Std430 for Mat3 {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 48;
+}
+impl From<[[f32; 3]; 3]> for Mat3 {
+    fn from(cols: [[f32; 3]; 3]) -> Self {
+        Self { cols: cols.map(|[x, y, z]| Vec4::new(x, y, z, 0.0)) }
+    }
+}
+
+/// A column major `mat4`.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Mat4 {
+    pub cols: [Vec4; 4],
+}
+unsafe impl Std140 for Mat4 {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 64;
+}
+unsafe impl Std430 for Mat4 {
+    const ALIGN: usize = 16;
+    const SIZE: usize = 64;
+}
+impl From<[[f32; 4]; 4]> for Mat4 {
+    fn from(cols: [[f32; 4]; 4]) -> Self {
+        Self { cols: cols.map(Vec4::from) }
+    }
+}
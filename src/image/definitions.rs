@@ -198,6 +198,26 @@ impl ImageType {
         self as i32
     }
 }
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(i32)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkImageViewType.html>"]
+pub enum ImageViewType {
+    Type1D = 0,
+    #[default]
+    Type2D = 1,
+    Type3D = 2,
+    Cube = 3,
+    Type1DArray = 4,
+    Type2DArray = 5,
+    CubeArray = 6,
+}
+impl ImageViewType {
+    #[inline]
+    pub const fn as_raw(self) -> i32 {
+        self as i32
+    }
+}
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkImageCreateFlagBits.html>"]
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use ash::vk::{self, ComponentMapping};
+
+use crate::{
+    commands::CommandPool,
+    descriptors::{DescriptorLayout, DescriptorPool, DescriptorType, DescriptorWriter},
+    device::LogicalDevice,
+    error::{NightfallError, VulkanError},
+    pipeline::{compute::ComputePipeline, layout::PipelineLayout, shader::{Shader, ShaderCreateInfo, ShaderStageFlags, Spirv}},
+    queue::{Queue, QueueFlags},
+    swapchain::Format,
+};
+
+use super::{ImageUsageFlags, RawImage};
+
+// built from shaders/downsample.comp, the integer variants with UINT or SINT defined,
+// by scripts/compile_shaders.sh.
+static DOWNSAMPLE: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/downsample.spv"));
+static DOWNSAMPLE_UINT: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/downsample_uint.spv"));
+static DOWNSAMPLE_SINT: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/downsample_sint.spv"));
+
+/// The workgroup size of the downsampler along x and y.
+const GROUP_SIZE: u32 = 8;
+
+/// How [`RawImage::generate_mipmaps`] fills in the levels of an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MipmapGeneration {
+    /// blits each level into the next with linear filtering.
+    Blit,
+    /// averages each 2x2 block of a level into the next with a compute
+    /// shader, for formats that can't be blitted with linear filtering.
+    Compute,
+}
+impl MipmapGeneration {
+    /// How mipmaps of `format` are generated on `device`, `None` when they can't be.
+    /// The compute fallback needs the format to support sampling and storage
+    /// with optimal tiling and the device to be created with
+    /// `shader_storage_image_write_without_format`.
+    pub fn for_format(device: &LogicalDevice, format: vk::Format) -> Option<Self> {
+        let features = device.physical_device.get_format_properties(Format::from_raw(format.as_raw())).optimal_tiling_features;
+        if features.contains(vk::FormatFeatureFlags::BLIT_SRC | vk::FormatFeatureFlags::BLIT_DST | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+            Some(Self::Blit)
+        } else if features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::STORAGE_IMAGE)
+            && device.enabled_features().shader_storage_image_write_without_format
+            && downsampler(format).is_some() {
+            Some(Self::Compute)
+        } else {
+            None
+        }
+    }
+    /// the usage the image needs for its mipmaps to be generated this way.
+    pub fn required_usage(self) -> ImageUsageFlags {
+        match self {
+            Self::Blit => ImageUsageFlags::TRANSFER_SRC | ImageUsageFlags::TRANSFER_DST,
+            Self::Compute => ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED | ImageUsageFlags::STORAGE,
+        }
+    }
+}
+
+/// the downsampler for the numeric type of `format`.
+fn downsampler(format: vk::Format) -> Option<&'static Spirv<[u8]>> {
+    match format {
+        vk::Format::R8_UINT | vk::Format::R8G8_UINT | vk::Format::R8G8B8_UINT | vk::Format::B8G8R8_UINT |
+        vk::Format::R8G8B8A8_UINT | vk::Format::B8G8R8A8_UINT | vk::Format::A8B8G8R8_UINT_PACK32 |
+        vk::Format::A2R10G10B10_UINT_PACK32 | vk::Format::A2B10G10R10_UINT_PACK32 |
+        vk::Format::R16_UINT | vk::Format::R16G16_UINT | vk::Format::R16G16B16_UINT | vk::Format::R16G16B16A16_UINT |
+        vk::Format::R32_UINT | vk::Format::R32G32_UINT | vk::Format::R32G32B32_UINT | vk::Format::R32G32B32A32_UINT => Some(DOWNSAMPLE_UINT),
+        vk::Format::R8_SINT | vk::Format::R8G8_SINT | vk::Format::R8G8B8_SINT | vk::Format::B8G8R8_SINT |
+        vk::Format::R8G8B8A8_SINT | vk::Format::B8G8R8A8_SINT | vk::Format::A8B8G8R8_SINT_PACK32 |
+        vk::Format::A2R10G10B10_SINT_PACK32 | vk::Format::A2B10G10R10_SINT_PACK32 |
+        vk::Format::R16_SINT | vk::Format::R16G16_SINT | vk::Format::R16G16B16_SINT | vk::Format::R16G16B16A16_SINT |
+        vk::Format::R32_SINT | vk::Format::R32G32_SINT | vk::Format::R32G32B32_SINT | vk::Format::R32G32B32A32_SINT => Some(DOWNSAMPLE_SINT),
+        // 64 bit texels can't be read or written as 32 bit vectors.
+        vk::Format::R64_UINT | vk::Format::R64G64_UINT | vk::Format::R64G64B64_UINT | vk::Format::R64G64B64A64_UINT |
+        vk::Format::R64_SINT | vk::Format::R64G64_SINT | vk::Format::R64G64B64_SINT | vk::Format::R64G64B64A64_SINT |
+        vk::Format::R64_SFLOAT | vk::Format::R64G64_SFLOAT | vk::Format::R64G64B64_SFLOAT | vk::Format::R64G64B64A64_SFLOAT => None,
+        _ => Some(DOWNSAMPLE),
+    }
+}
+
+impl RawImage {
+    /// The compute path of [`generate_mipmaps`](Self::generate_mipmaps), level
+    /// 0 is read as a sampled image and the others are written as storage images.
+    pub(super) fn downsample_mipmaps(&self, queue: &Arc<Queue>, pool: Arc<CommandPool>, final_layout: vk::ImageLayout, dst_stage_mask: vk::PipelineStageFlags) -> Result<(), NightfallError> {
+        if !queue.queue_flags().contains(QueueFlags::COMPUTE) {
+            return Err(VulkanError::NoSuitableQueueFamily(QueueFlags::COMPUTE).into());
+        }
+        let spirv = downsampler(self.format).ok_or(NightfallError::MipmapFormatUnsupported(self.format))?;
+        let device = queue.device();
+        let descriptor_layout = DescriptorLayout::builder()
+            .add_binding(0, DescriptorType::SAMPLED_IMAGE, 1, ShaderStageFlags::COMPUTE)
+            .add_binding(1, DescriptorType::STORAGE_IMAGE, 1, ShaderStageFlags::COMPUTE)
+            .build(device.clone());
+        let layout = PipelineLayout::builder().add_descriptor_layout(descriptor_layout.layout()).try_build(device.clone())?;
+        let shader = Shader::new(device.clone(), ShaderCreateInfo { entry: "main\0", stage: ShaderStageFlags::COMPUTE, data: &spirv.0 })?;
+        let pipeline = ComputePipeline::new(device.clone(), layout.clone(), shader)?;
+
+        let levels = self.mip_levels - 1;
+        let descriptor_pool = DescriptorPool::builder()
+            .add_pool_size(DescriptorType::SAMPLED_IMAGE, levels)
+            .add_pool_size(DescriptorType::STORAGE_IMAGE, levels)
+            .set_max_sets(levels)
+            .build(device.clone());
+        let sets = descriptor_pool.allocate(&vec![descriptor_layout; levels as usize])?.collect::<Vec<_>>();
+        let range = |base_mip_level: u32, level_count: u32| vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level,
+            level_count,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let views = (0..self.mip_levels).map(|level| self.create_view(range(level, 1), ComponentMapping::default())).collect::<Vec<_>>();
+        // the writer keeps pointers into the infos, they're collected up front so they don't move.
+        let infos = views.windows(2).map(|views| [
+            vk::DescriptorImageInfo { image_view: views[0].handle(), image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, ..Default::default() },
+            vk::DescriptorImageInfo { image_view: views[1].handle(), image_layout: vk::ImageLayout::GENERAL, ..Default::default() },
+        ]).collect::<Vec<_>>();
+        sets.iter().zip(&infos)
+            .fold(DescriptorWriter::new(), |writer, (set, [src, dst])| writer.add_sampled_image(set.set(), 1, 0, 0, src).add_storage_image(set.set(), 1, 1, 0, dst))
+            .write(device.clone());
+
+        let barrier = |range: vk::ImageSubresourceRange, old_layout: vk::ImageLayout, new_layout: vk::ImageLayout, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::ImageMemoryBarrier {
+            old_layout,
+            new_layout,
+            src_access_mask,
+            dst_access_mask,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: self.image,
+            subresource_range: range,
+            ..Default::default()
+        };
+        let cmd = queue.single_time_commands(pool.clone())?;
+        let command_buffer = cmd.get_command_buffer();
+        unsafe {
+            device.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[
+                    barrier(range(0, 1), vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+                    barrier(range(1, levels), vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::GENERAL, vk::AccessFlags::empty(), vk::AccessFlags::SHADER_WRITE),
+                ],
+            )
+        };
+        pipeline.bind(command_buffer);
+        for (level, set) in (1..self.mip_levels).zip(&sets) {
+            let (width, height) = ((self.width >> level).max(1), (self.height >> level).max(1));
+            unsafe {
+                device.device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, layout.get_layout(), 0, &[set.set()], &[]);
+                device.device.cmd_dispatch(command_buffer, width.div_ceil(GROUP_SIZE), height.div_ceil(GROUP_SIZE), 1);
+                device.device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(range(level, 1), vk::ImageLayout::GENERAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ)],
+                )
+            };
+        }
+        unsafe {
+            device.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                dst_stage_mask,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(range(0, self.mip_levels), vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, final_layout, vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ)],
+            )
+        };
+        queue.end_single_time_commands(pool, &cmd);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_averaged_in_their_numeric_type() {
+        assert!(std::ptr::eq(downsampler(vk::Format::R8G8B8A8_SRGB).unwrap(), DOWNSAMPLE));
+        assert!(std::ptr::eq(downsampler(vk::Format::R32G32B32A32_SFLOAT).unwrap(), DOWNSAMPLE));
+        assert!(std::ptr::eq(downsampler(vk::Format::R16_UINT).unwrap(), DOWNSAMPLE_UINT));
+        assert!(std::ptr::eq(downsampler(vk::Format::A2B10G10R10_SINT_PACK32).unwrap(), DOWNSAMPLE_SINT));
+        assert!(downsampler(vk::Format::R64_UINT).is_none());
+    }
+
+    #[test]
+    fn shaders_are_aligned_spirv() {
+        for spirv in [DOWNSAMPLE, DOWNSAMPLE_UINT, DOWNSAMPLE_SINT] {
+            assert_eq!(spirv.0.as_ptr() as usize % 4, 0);
+            assert_eq!(spirv.0[..4], 0x0723_0203u32.to_le_bytes());
+        }
+    }
+}
@@ -0,0 +1,583 @@
+use std::{path::Path, sync::Arc};
+
+use ash::vk::{self, Extent3D};
+use ddsfile::{Caps2, D3DFormat, Dds, DxgiFormat, MiscFlag};
+use image::DynamicImage;
+
+use crate::{
+    buffers::{Buffer, BufferCreateInfo, BufferUsageFlags, MemoryPropertyFlags},
+    commands::{BufferImageCopy, CommandPool},
+    error::NightfallError,
+    queue::Queue,
+    swapchain::Format,
+};
+
+use super::{ImageAspectFlags, ImageCreateFlags, ImageLayout, ImageSubresourceLayers, ImageUsageFlags, ImageViewType, MipmapGeneration, PipelineStageFlags, RawImage, TextureImage};
+
+const KTX2_MAGIC: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/// How the color channels of a file are encoded, picks between the `_SRGB`
+/// and `_UNORM` variant of a format when the file doesn't say itself.
+/// KTX2 files and DX10 DDS files carry their exact format and ignore this.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ColorSpace {
+    /// color textures authored for display, sampled back as linear values.
+    #[default]
+    Srgb,
+    /// normal maps, roughness and other data that must not be converted.
+    Linear,
+}
+impl ColorSpace {
+    const fn pick(self, srgb: Format, linear: Format) -> Format {
+        match self {
+            ColorSpace::Srgb => srgb,
+            ColorSpace::Linear => linear,
+        }
+    }
+}
+
+/// A decoded texture file ready to be copied into an image, the pixel data
+/// of every mip level and array layer along with the regions describing
+/// where each of them lives in [`data`](Self::data).
+pub struct TextureData {
+    format: Format,
+    extent: [u32; 3],
+    mip_levels: u32,
+    array_layers: u32,
+    cubemap: bool,
+    data: Vec<u8>,
+    regions: Vec<BufferImageCopy>,
+}
+impl TextureData {
+    /// reads and decodes the file at `path`, see [`decode`](Self::decode).
+    pub fn open(path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Self, NightfallError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| NightfallError::TextureDecodeFailed(format!("{}: {e}", path.display())))?;
+        Self::decode(&bytes, color_space)
+    }
+    /// Decodes a PNG, JPEG, Radiance HDR, KTX2 or DDS file, told apart by
+    /// their contents. PNG and JPEG become `R8G8B8A8` in `color_space`, except
+    /// 16 bit PNGs loaded as [`ColorSpace::Linear`] which keep their precision
+    /// as `R16G16B16A16_UNORM`. HDR becomes `R32G32B32A32_SFLOAT`. KTX2 and DDS
+    /// are uploaded as stored, block compressed data included, keeping their
+    /// mip levels, array layers and cubemap faces.
+    pub fn decode(bytes: &[u8], color_space: ColorSpace) -> Result<Self, NightfallError> {
+        if bytes.starts_with(&KTX2_MAGIC) {
+            Self::decode_ktx2(bytes)
+        } else if bytes.starts_with(&DDS_MAGIC) {
+            Self::decode_dds(bytes, color_space)
+        } else {
+            Self::decode_image(bytes, color_space)
+        }
+    }
+    fn decode_image(bytes: &[u8], color_space: ColorSpace) -> Result<Self, NightfallError> {
+        let image = image::load_from_memory(bytes).map_err(|e| NightfallError::TextureDecodeFailed(e.to_string()))?;
+        let (width, height) = (image.width(), image.height());
+        let (format, data) = match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                (Format::R32G32B32A32_SFLOAT, bytemuck::cast_slice(&image.to_rgba32f().into_raw()).to_vec())
+            }
+            DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) | DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) if color_space == ColorSpace::Linear => {
+                (Format::R16G16B16A16_UNORM, bytemuck::cast_slice(&image.to_rgba16().into_raw()).to_vec())
+            }
+            _ => (color_space.pick(Format::R8G8B8A8_SRGB, Format::R8G8B8A8_UNORM), image.to_rgba8().into_raw()),
+        };
+        Ok(Self {
+            format,
+            extent: [width, height, 1],
+            mip_levels: 1,
+            array_layers: 1,
+            cubemap: false,
+            data,
+            regions: vec![Self::region(0, 0, 0, 1, [width, height, 1])],
+        })
+    }
+    fn decode_ktx2(bytes: &[u8]) -> Result<Self, NightfallError> {
+        let reader = ktx2::Reader::new(bytes).map_err(|e| NightfallError::TextureDecodeFailed(e.to_string()))?;
+        let header = reader.header();
+        if let Some(scheme) = header.supercompression_scheme {
+            return Err(NightfallError::UnsupportedTextureFormat(format!("KTX2 supercompressed with {scheme:?}")));
+        }
+        let Some(format) = header.format else {
+            return Err(NightfallError::UnsupportedTextureFormat("KTX2 without a Vulkan format".to_owned()));
+        };
+        let extent = [header.pixel_width, header.pixel_height.max(1), header.pixel_depth.max(1)];
+        let cubemap = header.face_count == 6;
+        let array_layers = header.layer_count.max(1) * header.face_count;
+        // Vulkan has no arrays of 3D images.
+        if extent[2] > 1 && array_layers > 1 {
+            return Err(NightfallError::UnsupportedTextureFormat("KTX2 arrays of 3D textures".to_owned()));
+        }
+        // levels are stored aligned to their texel block size, so the offsets
+        // in the file stay valid buffer offsets when it is copied as a whole.
+        let levels = reader.levels().map(|level| (level.data.as_ptr() as usize - bytes.as_ptr() as usize, level.data.len())).collect::<Vec<_>>();
+        let start = levels.iter().map(|&(offset, _)| offset).min().unwrap_or(0);
+        let end = levels.iter().map(|&(offset, len)| offset + len).max().unwrap_or(0);
+        let regions = levels.iter().enumerate().map(|(level, &(offset, _))| {
+            Self::region((offset - start) as u64, level as u32, 0, array_layers, Self::mip_extent(extent, level as u32))
+        }).collect();
+        Ok(Self {
+            format: Format::from_raw(format.value() as i32),
+            extent,
+            mip_levels: levels.len() as u32,
+            array_layers,
+            cubemap,
+            data: bytes[start..end].to_vec(),
+            regions,
+        })
+    }
+    fn decode_dds(bytes: &[u8], color_space: ColorSpace) -> Result<Self, NightfallError> {
+        let dds = Dds::read(bytes).map_err(|e| NightfallError::TextureDecodeFailed(e.to_string()))?;
+        let (format, block_size, block_extent) = Self::dds_format(&dds, color_space)?;
+        let extent = [dds.get_width(), dds.get_height(), dds.get_depth()];
+        let mip_levels = dds.get_num_mipmap_levels().max(1);
+        let (cubemap, array_layers) = match &dds.header10 {
+            Some(header10) => {
+                let cubemap = header10.misc_flag.contains(MiscFlag::TEXTURECUBE);
+                (cubemap, header10.array_size.max(1) * if cubemap { 6 } else { 1 })
+            }
+            None if dds.header.caps2.contains(Caps2::CUBEMAP) => (true, 6),
+            None => (false, 1),
+        };
+        if extent[2] > 1 && array_layers > 1 {
+            return Err(NightfallError::UnsupportedTextureFormat("DDS arrays of 3D textures".to_owned()));
+        }
+        // DDS stores every mip level of one layer before the next layer,
+        // each subresource is repacked at an offset the copy can start from.
+        let alignment = block_size.max(4) as usize;
+        let mut data = Vec::with_capacity(dds.data.len());
+        let mut regions = Vec::with_capacity((array_layers * mip_levels) as usize);
+        let mut read = 0;
+        for layer in 0..array_layers {
+            for level in 0..mip_levels {
+                let [width, height, depth] = Self::mip_extent(extent, level);
+                let blocks = width.div_ceil(block_extent) * height.div_ceil(block_extent) * depth;
+                let size = (blocks * block_size) as usize;
+                let Some(subresource) = dds.data.get(read..read + size) else {
+                    return Err(NightfallError::TextureDecodeFailed("DDS file is cut short".to_owned()));
+                };
+                data.resize(data.len().next_multiple_of(alignment), 0);
+                regions.push(Self::region(data.len() as u64, level, layer, 1, [width, height, depth]));
+                data.extend_from_slice(subresource);
+                read += size;
+            }
+        }
+        Ok(Self { format, extent, mip_levels, array_layers, cubemap, data, regions })
+    }
+    /// the format of a DDS file along with the size in bytes of a texel block
+    /// and its width and height in texels.
+    fn dds_format(dds: &Dds, color_space: ColorSpace) -> Result<(Format, u32, u32), NightfallError> {
+        if let Some(format) = dds.get_dxgi_format() {
+            return Ok(match format {
+                DxgiFormat::R8G8B8A8_Typeless => (color_space.pick(Format::R8G8B8A8_SRGB, Format::R8G8B8A8_UNORM), 4, 1),
+                DxgiFormat::R8G8B8A8_UNorm => (Format::R8G8B8A8_UNORM, 4, 1),
+                DxgiFormat::R8G8B8A8_UNorm_sRGB => (Format::R8G8B8A8_SRGB, 4, 1),
+                DxgiFormat::B8G8R8A8_Typeless => (color_space.pick(Format::B8G8R8A8_SRGB, Format::B8G8R8A8_UNORM), 4, 1),
+                DxgiFormat::B8G8R8A8_UNorm => (Format::B8G8R8A8_UNORM, 4, 1),
+                DxgiFormat::B8G8R8A8_UNorm_sRGB => (Format::B8G8R8A8_SRGB, 4, 1),
+                DxgiFormat::R10G10B10A2_UNorm => (Format::A2B10G10R10_UNORM_PACK32, 4, 1),
+                DxgiFormat::R11G11B10_Float => (Format::B10G11R11_UFLOAT_PACK32, 4, 1),
+                DxgiFormat::R9G9B9E5_SharedExp => (Format::E5B9G9R9_UFLOAT_PACK32, 4, 1),
+                DxgiFormat::R8_UNorm => (Format::R8_UNORM, 1, 1),
+                DxgiFormat::R8G8_UNorm => (Format::R8G8_UNORM, 2, 1),
+                DxgiFormat::R16_Float => (Format::R16_SFLOAT, 2, 1),
+                DxgiFormat::R32_Float => (Format::R32_SFLOAT, 4, 1),
+                DxgiFormat::R16G16B16A16_Float => (Format::R16G16B16A16_SFLOAT, 8, 1),
+                DxgiFormat::R32G32B32A32_Float => (Format::R32G32B32A32_SFLOAT, 16, 1),
+                DxgiFormat::BC1_Typeless => (color_space.pick(Format::BC1_RGBA_SRGB_BLOCK, Format::BC1_RGBA_UNORM_BLOCK), 8, 4),
+                DxgiFormat::BC1_UNorm => (Format::BC1_RGBA_UNORM_BLOCK, 8, 4),
+                DxgiFormat::BC1_UNorm_sRGB => (Format::BC1_RGBA_SRGB_BLOCK, 8, 4),
+                DxgiFormat::BC2_Typeless => (color_space.pick(Format::BC2_SRGB_BLOCK, Format::BC2_UNORM_BLOCK), 16, 4),
+                DxgiFormat::BC2_UNorm => (Format::BC2_UNORM_BLOCK, 16, 4),
+                DxgiFormat::BC2_UNorm_sRGB => (Format::BC2_SRGB_BLOCK, 16, 4),
+                DxgiFormat::BC3_Typeless => (color_space.pick(Format::BC3_SRGB_BLOCK, Format::BC3_UNORM_BLOCK), 16, 4),
+                DxgiFormat::BC3_UNorm => (Format::BC3_UNORM_BLOCK, 16, 4),
+                DxgiFormat::BC3_UNorm_sRGB => (Format::BC3_SRGB_BLOCK, 16, 4),
+                DxgiFormat::BC4_Typeless | DxgiFormat::BC4_UNorm => (Format::BC4_UNORM_BLOCK, 8, 4),
+                DxgiFormat::BC4_SNorm => (Format::BC4_SNORM_BLOCK, 8, 4),
+                DxgiFormat::BC5_Typeless | DxgiFormat::BC5_UNorm => (Format::BC5_UNORM_BLOCK, 16, 4),
+                DxgiFormat::BC5_SNorm => (Format::BC5_SNORM_BLOCK, 16, 4),
+                DxgiFormat::BC6H_Typeless | DxgiFormat::BC6H_UF16 => (Format::BC6H_UFLOAT_BLOCK, 16, 4),
+                DxgiFormat::BC6H_SF16 => (Format::BC6H_SFLOAT_BLOCK, 16, 4),
+                DxgiFormat::BC7_Typeless => (color_space.pick(Format::BC7_SRGB_BLOCK, Format::BC7_UNORM_BLOCK), 16, 4),
+                DxgiFormat::BC7_UNorm => (Format::BC7_UNORM_BLOCK, 16, 4),
+                DxgiFormat::BC7_UNorm_sRGB => (Format::BC7_SRGB_BLOCK, 16, 4),
+                format => return Err(NightfallError::UnsupportedTextureFormat(format!("DDS {format:?}"))),
+            });
+        }
+        match dds.get_d3d_format() {
+            Some(D3DFormat::A8B8G8R8) => Ok((color_space.pick(Format::R8G8B8A8_SRGB, Format::R8G8B8A8_UNORM), 4, 1)),
+            Some(D3DFormat::A8R8G8B8) => Ok((color_space.pick(Format::B8G8R8A8_SRGB, Format::B8G8R8A8_UNORM), 4, 1)),
+            Some(D3DFormat::L8) => Ok((Format::R8_UNORM, 1, 1)),
+            Some(D3DFormat::A16B16G16R16) => Ok((Format::R16G16B16A16_UNORM, 8, 1)),
+            Some(D3DFormat::R16F) => Ok((Format::R16_SFLOAT, 2, 1)),
+            Some(D3DFormat::R32F) => Ok((Format::R32_SFLOAT, 4, 1)),
+            Some(D3DFormat::A16B16G16R16F) => Ok((Format::R16G16B16A16_SFLOAT, 8, 1)),
+            Some(D3DFormat::A32B32G32R32F) => Ok((Format::R32G32B32A32_SFLOAT, 16, 1)),
+            Some(D3DFormat::DXT1) => Ok((color_space.pick(Format::BC1_RGBA_SRGB_BLOCK, Format::BC1_RGBA_UNORM_BLOCK), 8, 4)),
+            Some(D3DFormat::DXT2 | D3DFormat::DXT3) => Ok((color_space.pick(Format::BC2_SRGB_BLOCK, Format::BC2_UNORM_BLOCK), 16, 4)),
+            Some(D3DFormat::DXT4 | D3DFormat::DXT5) => Ok((color_space.pick(Format::BC3_SRGB_BLOCK, Format::BC3_UNORM_BLOCK), 16, 4)),
+            Some(format) => Err(NightfallError::UnsupportedTextureFormat(format!("DDS {format:?}"))),
+            None => Err(NightfallError::UnsupportedTextureFormat("DDS with an unrecognised pixel format".to_owned())),
+        }
+    }
+    fn mip_extent(extent: [u32; 3], level: u32) -> [u32; 3] {
+        extent.map(|x| (x >> level).max(1))
+    }
+    fn region(buffer_offset: u64, mip_level: u32, base_array_layer: u32, layer_count: u32, image_extent: [u32; 3]) -> BufferImageCopy {
+        BufferImageCopy {
+            buffer_offset,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: ImageSubresourceLayers { aspect_mask: ImageAspectFlags::COLOR, mip_level, base_array_layer, layer_count },
+            image_offset: [0, 0, 0],
+            image_extent,
+        }
+    }
+    #[inline]
+    pub fn format(&self) -> Format {
+        self.format
+    }
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.extent[0]
+    }
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.extent[1]
+    }
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.extent[2]
+    }
+    #[inline]
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+    /// the number of layers of the image, six per cube for cubemaps.
+    #[inline]
+    pub fn array_layers(&self) -> u32 {
+        self.array_layers
+    }
+    #[inline]
+    pub fn is_cubemap(&self) -> bool {
+        self.cubemap
+    }
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    #[inline]
+    pub fn regions(&self) -> &[BufferImageCopy] {
+        &self.regions
+    }
+}
+
+/// Loads image files into sampled [`TextureImage`]s, decoding them with
+/// [`TextureData`] and uploading them through a staging buffer.
+pub struct TextureLoader {
+    color_space: ColorSpace,
+    usage: ImageUsageFlags,
+    dst_stage_mask: PipelineStageFlags,
+    generate_mipmaps: bool,
+}
+impl Default for TextureLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl TextureLoader {
+    pub fn new() -> Self {
+        Self {
+            color_space: ColorSpace::Srgb,
+            usage: ImageUsageFlags::SAMPLED,
+            dst_stage_mask: PipelineStageFlags::FRAGMENT_SHADER,
+            generate_mipmaps: false,
+        }
+    }
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+    /// usage on top of the `TRANSFER_DST` the upload needs, `SAMPLED` by default.
+    pub fn usage(mut self, usage: ImageUsageFlags) -> Self {
+        self.usage = usage;
+        self
+    }
+    /// the stages that first sample the texture, `FRAGMENT_SHADER` by default.
+    pub fn dst_stage_mask(mut self, dst_stage_mask: PipelineStageFlags) -> Self {
+        self.dst_stage_mask = dst_stage_mask;
+        self
+    }
+    /// builds a full mip chain for single layer 2D files that come without
+    /// one, see [`RawImage::generate_mipmaps`]. Files that carry their own mip
+    /// levels always use those.
+    pub fn generate_mipmaps(mut self, generate_mipmaps: bool) -> Self {
+        self.generate_mipmaps = generate_mipmaps;
+        self
+    }
+    pub fn load_file(&self, queue: Arc<Queue>, pool: Arc<CommandPool>, path: impl AsRef<Path>) -> Result<TextureImage, NightfallError> {
+        self.upload(queue, pool, &TextureData::open(path, self.color_space)?)
+    }
+    pub fn load_memory(&self, queue: Arc<Queue>, pool: Arc<CommandPool>, bytes: &[u8]) -> Result<TextureImage, NightfallError> {
+        self.upload(queue, pool, &TextureData::decode(bytes, self.color_space)?)
+    }
+    /// Creates an image matching `texture` and copies every subresource into
+    /// it, leaving it in `SHADER_READ_ONLY_OPTIMAL`. Cubemaps get a cube view,
+    /// arrays an array view and files with depth a 3D view.
+    pub fn upload(&self, queue: Arc<Queue>, pool: Arc<CommandPool>, texture: &TextureData) -> Result<TextureImage, NightfallError> {
+        let device = queue.device();
+        let stage = Buffer::new(device.clone(), BufferCreateInfo {
+                size: texture.data.len(),
+                usage: BufferUsageFlags::TRANSFER_SRC,
+                properties: MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                ..Default::default()
+            },
+        )?;
+        unsafe {
+            let guard = stage.raw_map::<u8>(texture.data.len(), 0)?;
+            std::ptr::copy_nonoverlapping(texture.data.as_ptr(), guard, texture.data.len());
+            stage.raw_unmap();
+        }
+        let [width, height, depth] = texture.extent;
+        let generate = self.generate_mipmaps && texture.mip_levels == 1 && texture.array_layers == 1 && depth == 1;
+        let view_type = match (texture.cubemap, texture.array_layers, depth) {
+            (true, 6, _) => ImageViewType::Cube,
+            (true, _, _) => ImageViewType::CubeArray,
+            (false, 1, 1) => ImageViewType::Type2D,
+            (false, 1, _) => ImageViewType::Type3D,
+            (false, _, _) => ImageViewType::Type2DArray,
+        };
+        let format = vk::Format::from_raw(texture.format.as_raw());
+        let mut usage = ImageUsageFlags::TRANSFER_DST | self.usage;
+        if generate {
+            usage |= MipmapGeneration::for_format(&device, format).ok_or(NightfallError::MipmapFormatUnsupported(format))?.required_usage();
+        }
+        let create_info = vk::ImageCreateInfo {
+            flags: if texture.cubemap { vk::ImageCreateFlags::from_raw(ImageCreateFlags::CUBE_COMPATIBLE.0) } else { vk::ImageCreateFlags::empty() },
+            image_type: if depth > 1 { vk::ImageType::TYPE_3D } else { vk::ImageType::TYPE_2D },
+            extent: Extent3D { width, height, depth },
+            mip_levels: if generate { RawImage::max_mip_levels(width, height, 1) } else { texture.mip_levels },
+            array_layers: texture.array_layers,
+            format,
+            tiling: vk::ImageTiling::OPTIMAL,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            usage: vk::ImageUsageFlags::from_raw(usage.0),
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            samples: vk::SampleCountFlags::TYPE_1,
+            ..Default::default()
+        };
+        let image = RawImage::from_raw_info(device.clone(), Some(queue.clone()), &create_info)?;
+        let barrier = |old_layout: vk::ImageLayout, new_layout: vk::ImageLayout, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::ImageMemoryBarrier {
+            old_layout,
+            new_layout,
+            src_access_mask,
+            dst_access_mask,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: image.handle(),
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: create_info.mip_levels,
+                base_array_layer: 0,
+                layer_count: texture.array_layers,
+            },
+            ..Default::default()
+        };
+        let cmd = queue.single_time_commands(pool.clone())?;
+        unsafe {
+            device.device.cmd_pipeline_barrier(
+                cmd.get_command_buffer(),
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE)],
+            )
+        };
+        cmd.buffer_to_image(stage.buffer(), image.handle(), ImageLayout::TRANSFER_DST_OPTIMAL, &texture.regions);
+        if !generate {
+            unsafe {
+                device.device.cmd_pipeline_barrier(
+                    cmd.get_command_buffer(),
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::from_raw(self.dst_stage_mask.0),
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ)],
+                )
+            };
+        }
+        queue.end_single_time_commands(pool.clone(), &cmd);
+        if generate {
+            image.generate_mipmaps(pool, ImageLayout::SHADER_READ_ONLY_OPTIMAL, self.dst_stage_mask)?;
+        }
+        Ok(TextureImage::with_view_type(queue, image, view_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ddsfile::{AlphaMode, D3D10ResourceDimension, NewD3dParams, NewDxgiParams};
+
+    use super::*;
+
+    /// bytes that differ between neighbouring subresources.
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+    fn dds_bytes(mut dds: Dds, len: usize) -> Vec<u8> {
+        dds.data = pattern(len);
+        let mut bytes = vec![];
+        dds.write(&mut bytes).unwrap();
+        bytes
+    }
+    /// a KTX2 file with the levels stored smallest first, as the spec lays them out.
+    fn ktx2_bytes(format: Format, extent: [u32; 3], layer_count: u32, levels: &[Vec<u8>]) -> Vec<u8> {
+        let level_index = ktx2::Header::LENGTH + levels.len() * ktx2::LevelIndex::LENGTH;
+        // the descriptor only holds its own size, the decoder doesn't read it.
+        let dfd = level_index as u32;
+        let mut offset = level_index + 4;
+        let mut offsets = vec![0; levels.len()];
+        for (level, data) in levels.iter().enumerate().rev() {
+            offsets[level] = offset;
+            offset += data.len();
+        }
+        let header = ktx2::Header {
+            format: ktx2::Format::new(format.as_raw() as u32),
+            type_size: 1,
+            pixel_width: extent[0],
+            pixel_height: extent[1],
+            pixel_depth: extent[2],
+            layer_count,
+            face_count: 1,
+            level_count: levels.len() as u32,
+            supercompression_scheme: None,
+            index: ktx2::Index { dfd_byte_offset: dfd, dfd_byte_length: 4, kvd_byte_offset: 0, kvd_byte_length: 0, sgd_byte_offset: 0, sgd_byte_length: 0 },
+        };
+        let mut bytes = header.as_bytes().to_vec();
+        for (data, &offset) in levels.iter().zip(&offsets) {
+            let index = ktx2::LevelIndex { byte_offset: offset as u64, byte_length: data.len() as u64, uncompressed_byte_length: data.len() as u64 };
+            bytes.extend_from_slice(&index.as_bytes());
+        }
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        for data in levels.iter().rev() {
+            bytes.extend_from_slice(data);
+        }
+        bytes
+    }
+    /// the offset, level, first layer, layer count and extent of a region.
+    fn region(region: &BufferImageCopy) -> (u64, u32, u32, u32, [u32; 3]) {
+        let subresource = region.image_subresource;
+        (region.buffer_offset, subresource.mip_level, subresource.base_array_layer, subresource.layer_count, region.image_extent)
+    }
+
+    #[test]
+    fn decodes_dxgi_dds_arrays() {
+        let dds = Dds::new_dxgi(NewDxgiParams {
+            height: 4,
+            width: 4,
+            depth: None,
+            format: DxgiFormat::R8G8B8A8_UNorm_sRGB,
+            mipmap_levels: Some(3),
+            array_layers: Some(2),
+            caps2: None,
+            is_cubemap: false,
+            resource_dimension: D3D10ResourceDimension::Texture2D,
+            alpha_mode: AlphaMode::Unknown,
+        }).unwrap();
+        // every layer holds levels of 64, 16 and 4 bytes.
+        let bytes = dds_bytes(dds, 2 * 84);
+        let texture = TextureData::decode(&bytes, ColorSpace::Linear).unwrap();
+        assert!(texture.format() == Format::R8G8B8A8_SRGB);
+        assert_eq!([texture.width(), texture.height(), texture.depth()], [4, 4, 1]);
+        assert_eq!((texture.mip_levels(), texture.array_layers(), texture.is_cubemap()), (3, 2, false));
+        assert_eq!(texture.data(), pattern(2 * 84));
+        let regions = texture.regions().iter().map(region).collect::<Vec<_>>();
+        assert_eq!(regions, [
+            (0, 0, 0, 1, [4, 4, 1]),
+            (64, 1, 0, 1, [2, 2, 1]),
+            (80, 2, 0, 1, [1, 1, 1]),
+            (84, 0, 1, 1, [4, 4, 1]),
+            (148, 1, 1, 1, [2, 2, 1]),
+            (164, 2, 1, 1, [1, 1, 1]),
+        ]);
+    }
+
+    #[test]
+    fn dxgi_dds_block_formats_round_levels_up_to_whole_blocks() {
+        let dds = Dds::new_dxgi(NewDxgiParams {
+            height: 4,
+            width: 8,
+            depth: None,
+            format: DxgiFormat::BC1_Typeless,
+            mipmap_levels: Some(3),
+            array_layers: None,
+            caps2: None,
+            is_cubemap: false,
+            resource_dimension: D3D10ResourceDimension::Texture2D,
+            alpha_mode: AlphaMode::Unknown,
+        }).unwrap();
+        // 2 blocks, then 1 block for both 4x2 and 2x1.
+        let bytes = dds_bytes(dds, 32);
+        let texture = TextureData::decode(&bytes, ColorSpace::Srgb).unwrap();
+        assert!(texture.format() == Format::BC1_RGBA_SRGB_BLOCK);
+        let regions = texture.regions().iter().map(region).collect::<Vec<_>>();
+        assert_eq!(regions, [(0, 0, 0, 1, [8, 4, 1]), (16, 1, 0, 1, [4, 2, 1]), (24, 2, 0, 1, [2, 1, 1])]);
+        assert_eq!(texture.data(), pattern(32));
+    }
+
+    #[test]
+    fn decodes_legacy_d3d_cubemaps_in_the_requested_color_space() {
+        let dds = Dds::new_d3d(NewD3dParams {
+            height: 2,
+            width: 2,
+            depth: None,
+            format: D3DFormat::A8R8G8B8,
+            mipmap_levels: None,
+            caps2: Some(Caps2::CUBEMAP | Caps2::CUBEMAP_ALLFACES),
+        }).unwrap();
+        let bytes = dds_bytes(dds, 6 * 16);
+        let texture = TextureData::decode(&bytes, ColorSpace::Linear).unwrap();
+        assert!(texture.format() == Format::B8G8R8A8_UNORM);
+        assert_eq!((texture.mip_levels(), texture.array_layers(), texture.is_cubemap()), (1, 6, true));
+        let regions = texture.regions().iter().map(region).collect::<Vec<_>>();
+        assert_eq!(regions, (0..6).map(|face| (face as u64 * 16, 0, face, 1, [2, 2, 1])).collect::<Vec<_>>());
+
+        let texture = TextureData::decode(&bytes, ColorSpace::Srgb).unwrap();
+        assert!(texture.format() == Format::B8G8R8A8_SRGB);
+    }
+
+    #[test]
+    fn rejects_cut_short_dds() {
+        let dds = Dds::new_d3d(NewD3dParams { height: 4, width: 4, depth: None, format: D3DFormat::A8B8G8R8, mipmap_levels: Some(3), caps2: None }).unwrap();
+        let bytes = dds_bytes(dds, 80);
+        assert_eq!(TextureData::decode(&bytes, ColorSpace::Srgb).err(), Some(NightfallError::TextureDecodeFailed("DDS file is cut short".to_owned())));
+    }
+
+    #[test]
+    fn decodes_ktx2_levels_relative_to_the_first_stored() {
+        let levels = [pattern(32), vec![7; 8]];
+        let bytes = ktx2_bytes(Format::R8G8B8A8_UNORM, [4, 2, 0], 0, &levels);
+        let texture = TextureData::decode(&bytes, ColorSpace::Srgb).unwrap();
+        assert!(texture.format() == Format::R8G8B8A8_UNORM);
+        assert_eq!([texture.width(), texture.height(), texture.depth()], [4, 2, 1]);
+        assert_eq!((texture.mip_levels(), texture.array_layers(), texture.is_cubemap()), (2, 1, false));
+        // the smaller level is stored first.
+        assert_eq!(texture.data(), [levels[1].clone(), levels[0].clone()].concat());
+        let regions = texture.regions().iter().map(region).collect::<Vec<_>>();
+        assert_eq!(regions, [(8, 0, 0, 1, [4, 2, 1]), (0, 1, 0, 1, [2, 1, 1])]);
+    }
+
+    #[test]
+    fn rejects_ktx2_arrays_of_3d_textures() {
+        let bytes = ktx2_bytes(Format::R8_UNORM, [2, 2, 2], 3, &[pattern(24)]);
+        assert_eq!(TextureData::decode(&bytes, ColorSpace::Srgb).err(), Some(NightfallError::UnsupportedTextureFormat("KTX2 arrays of 3D textures".to_owned())));
+        let bytes = ktx2_bytes(Format::R8_UNORM, [2, 2, 2], 0, &[pattern(8)]);
+        assert_eq!(TextureData::decode(&bytes, ColorSpace::Srgb).unwrap().depth(), 2);
+    }
+}
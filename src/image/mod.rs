@@ -7,11 +7,17 @@ use ash::vk::{self, ComponentMapping, ComponentSwizzle, Extent3D, TaggedStructur
 pub use texture::*;
 pub use sampler::*;
 pub use view::*;
+#[cfg(feature = "image-loading")]
+mod loader;
+#[cfg(feature = "image-loading")]
+pub use loader::*;
 mod definitions;
 pub use definitions::*;
-use crate::{commands::CommandPool, error::VulkanError, memory::DeviceMemory, swapchain::Format};
+mod downsample;
+pub use downsample::*;
+use crate::{commands::{CommandPool, ImageBlit}, error::{NightfallError, VulkanError}, memory::DeviceMemory, swapchain::Format};
 
-use super::{device::LogicalDevice, queue::Queue};
+use super::{device::{DeferredHandle, LogicalDevice}, queue::Queue};
 
 pub struct RawImage {
     image: vk::Image,
@@ -21,6 +27,8 @@ pub struct RawImage {
     queue: Option<Arc<Queue>>,
     width: u32,
     height: u32,
+    mip_levels: u32,
+    array_layers: u32,
 }
 pub struct RawImageBuilder {
     create_info: vk::ImageCreateInfo,
@@ -85,6 +93,12 @@ impl RawImageBuilder {
         self.create_info.mip_levels = mip_levels;
         self
     }
+    /// allocates every mip level down to 1x1 for the extent set so far.
+    pub const fn full_mip_chain(mut self) -> Self {
+        let extent = self.create_info.extent;
+        self.create_info.mip_levels = RawImage::max_mip_levels(extent.width, extent.height, extent.depth);
+        self
+    }
     pub const fn sharing_mode(mut self, sharing_mode: SharingMode) -> Self {
         self.create_info.sharing_mode = vk::SharingMode::from_raw(sharing_mode.0);
         self
@@ -104,6 +118,8 @@ impl RawImageBuilder {
                 queue,
                 width: self.create_info.extent.width,
                 height: self.create_info.extent.height,
+                mip_levels: self.create_info.mip_levels,
+                array_layers: self.create_info.array_layers,
             }
         ))
     }
@@ -124,6 +140,8 @@ impl RawImage {
                 queue,
                 width: create_info.extent.width,
                 height: create_info.extent.height,
+                mip_levels: create_info.mip_levels,
+                array_layers: create_info.array_layers,
             }
         ))
     }
@@ -138,9 +156,9 @@ impl RawImage {
             subresource_range: vk::ImageSubresourceRange {
                 aspect_mask: vk::ImageAspectFlags::COLOR,
                 base_mip_level: 0,
-                level_count: 1,
+                level_count: self.mip_levels,
                 base_array_layer: 0,
-                layer_count: 1
+                layer_count: self.array_layers
             },
             ..Default::default()
         };
@@ -196,10 +214,125 @@ impl RawImage {
         }
         Ok(())
     }
+    /// the number of mip levels in a full chain for an image of this extent.
+    pub const fn max_mip_levels(width: u32, height: u32, depth: u32) -> u32 {
+        let mut largest = 1;
+        if width > largest {
+            largest = width;
+        }
+        if height > largest {
+            largest = height;
+        }
+        if depth > largest {
+            largest = depth;
+        }
+        u32::BITS - largest.leading_zeros()
+    }
+    /// Fills mip levels `1..` by repeatedly blitting each level into the next
+    /// with linear filtering. Every level must be in `TRANSFER_DST_OPTIMAL`
+    /// with level 0 holding the image, afterwards they're all in `final_layout`
+    /// and visible to `dst_stage_mask`. Formats that can't be blitted with
+    /// linear filtering are downsampled by a compute shader instead, see
+    /// [`MipmapGeneration`] for what each needs, including the image usage.
+    pub fn generate_mipmaps(&self, pool: Arc<CommandPool>, final_layout: ImageLayout, dst_stage_mask: PipelineStageFlags) -> Result<(), NightfallError> {
+        let Some(queue) = &self.queue else {
+            panic!("No queue selected")
+        };
+        let device = queue.device();
+        match MipmapGeneration::for_format(&device, self.format) {
+            None => return Err(NightfallError::MipmapFormatUnsupported(self.format)),
+            // a single level only needs the transition to `final_layout`, which the blits record.
+            Some(MipmapGeneration::Compute) if self.mip_levels > 1 => {
+                let final_layout = vk::ImageLayout::from_raw(final_layout.0);
+                let dst_stage_mask = vk::PipelineStageFlags::from_raw(dst_stage_mask.0);
+                return self.downsample_mipmaps(queue, pool, final_layout, dst_stage_mask);
+            }
+            Some(_) => {}
+        }
+        let barrier = |level: u32, old_layout: vk::ImageLayout, new_layout: vk::ImageLayout, src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags| vk::ImageMemoryBarrier {
+            old_layout,
+            new_layout,
+            src_access_mask,
+            dst_access_mask,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: self.image,
+            subresource_range: vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: level,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            ..Default::default()
+        };
+        let final_layout = vk::ImageLayout::from_raw(final_layout.0);
+        let dst_stage_mask = vk::PipelineStageFlags::from_raw(dst_stage_mask.0);
+        let cmd = queue.single_time_commands(pool.clone())?;
+        let (mut width, mut height) = (self.width as i32, self.height as i32);
+        for level in 1..self.mip_levels {
+            let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+            unsafe {
+                device.device.cmd_pipeline_barrier(
+                    cmd.get_command_buffer(),
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(level - 1, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::TRANSFER_READ)],
+                )
+            };
+            let subresource = |mip_level| ImageSubresourceLayers { aspect_mask: ImageAspectFlags::COLOR, mip_level, base_array_layer: 0, layer_count: 1 };
+            cmd.blit_image(
+                self.image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[ImageBlit {
+                    src_subresource: subresource(level - 1),
+                    src_offsets: [[0, 0, 0], [width, height, 1]],
+                    dst_subresource: subresource(level),
+                    dst_offsets: [[0, 0, 0], [next_width, next_height, 1]],
+                }],
+                vk::Filter::LINEAR,
+            );
+            unsafe {
+                device.device.cmd_pipeline_barrier(
+                    cmd.get_command_buffer(),
+                    vk::PipelineStageFlags::TRANSFER,
+                    dst_stage_mask,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(level - 1, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, final_layout, vk::AccessFlags::TRANSFER_READ, vk::AccessFlags::SHADER_READ)],
+                )
+            };
+            (width, height) = (next_width, next_height);
+        }
+        unsafe {
+            device.device.cmd_pipeline_barrier(
+                cmd.get_command_buffer(),
+                vk::PipelineStageFlags::TRANSFER,
+                dst_stage_mask,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(self.mip_levels - 1, vk::ImageLayout::TRANSFER_DST_OPTIMAL, final_layout, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ)],
+            )
+        };
+        queue.end_single_time_commands(pool, &cmd);
+        Ok(())
+    }
     pub fn create_view(&self, subresource_range: vk::ImageSubresourceRange, components: ComponentMapping) -> ImageView {
+        self.create_view_of_type(ImageViewType::Type2D, subresource_range, components)
+    }
+    /// like [`create_view`](Self::create_view) for arrays, cubemaps and 3D
+    /// images, cube views need the image to be created `CUBE_COMPATIBLE`.
+    pub fn create_view_of_type(&self, view_type: ImageViewType, subresource_range: vk::ImageSubresourceRange, components: ComponentMapping) -> ImageView {
         let view_info = vk::ImageViewCreateInfo {
             image: self.image,
-            view_type: vk::ImageViewType::TYPE_2D,
+            view_type: vk::ImageViewType::from_raw(view_type.as_raw()),
             format: self.format,
             subresource_range,
             components,
@@ -230,12 +363,20 @@ impl RawImage {
     pub fn height(&self) -> usize {
         self.height as usize
     }
+    #[inline]
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+    #[inline]
+    pub fn array_layers(&self) -> u32 {
+        self.array_layers
+    }
     pub fn device(&self) -> Arc<LogicalDevice> {
         self.memory.device.clone()
     }
 }
 impl Drop for RawImage {
     fn drop(&mut self) {
-        unsafe { self.memory.device.device.destroy_image(self.image, None) };
+        self.memory.device.destroy_deferred(DeferredHandle::Image(self.image));
     }
 }
\ No newline at end of file
@@ -2,9 +2,9 @@ use std::sync::Arc;
 
 use ash::vk::{self, Extent3D, QUEUE_FAMILY_IGNORED, ImageSubresourceRange, ComponentMapping};
 
-use crate::{buffers::{Buffer, BufferCreateInfo, BufferUsageFlags, MemoryPropertyFlags}, commands::CommandPool, device::LogicalDevice, error::VulkanError, queue::Queue, swapchain::Format};
+use crate::{buffers::{Buffer, BufferCreateInfo, BufferUsageFlags, MemoryPropertyFlags}, commands::CommandPool, device::LogicalDevice, error::{NightfallError, VulkanError}, queue::Queue, swapchain::Format};
 
-use super::{ImageUsageFlags, ImageView, PipelineStageFlags, RawImage, Sampler, SamplerBuilder};
+use super::{ImageLayout, ImageUsageFlags, ImageView, ImageViewType, MipmapGeneration, PipelineStageFlags, RawImage, Sampler, SamplerBuilder};
 
 pub struct TextureImage {
     image: Arc<RawImage>,
@@ -15,23 +15,29 @@ pub struct TextureImage {
 impl TextureImage {
     pub fn new(queue: Arc<Queue>, width: u32, height: u32, usage: ImageUsageFlags) -> Result<Self, VulkanError> {
         let image = Self::raw_create_image(queue.clone(), width, height, vk::Format::R8G8B8A8_SRGB, vk::ImageUsageFlags::from_raw(usage.0))?;
+        Ok(Self::with_image(queue, image))
+    }
+    fn with_image(queue: Arc<Queue>, image: Arc<RawImage>) -> Self {
+        Self::with_view_type(queue, image, ImageViewType::Type2D)
+    }
+    pub(crate) fn with_view_type(queue: Arc<Queue>, image: Arc<RawImage>, view_type: ImageViewType) -> Self {
         let sampler = Self::create_sampler(queue.device());
-        let view = image.create_view(vk::ImageSubresourceRange {
+        let view = image.create_view_of_type(view_type, vk::ImageSubresourceRange {
             aspect_mask: vk::ImageAspectFlags::COLOR,
             base_mip_level: 0,
-            level_count: 1,
+            level_count: image.mip_levels(),
             base_array_layer: 0,
-            layer_count: 1,
+            layer_count: image.array_layers(),
             ..Default::default()
         }, ComponentMapping::default());
-        Ok(Self { image, view, sampler })
+        Self { image, view, sampler }
     }
     pub fn from_image(raw_image: Arc<RawImage>) -> Result<Self, VulkanError> {
         let sampler = Self::create_sampler(raw_image.device());
         let view = raw_image.create_view(vk::ImageSubresourceRange {
             aspect_mask: vk::ImageAspectFlags::COLOR,
             base_mip_level: 0,
-            level_count: 1,
+            level_count: raw_image.mip_levels(),
             base_array_layer: 0,
             layer_count: 1,
             ..Default::default()
@@ -58,6 +64,55 @@ impl TextureImage {
         this.buffer_to_image(pool, width, height, stage.buffer(), dst_pipeline)?;
         Ok(this)
     }
+    /// Like [`from_data`](Self::from_data) but allocates a full mip chain and
+    /// generates it on the GPU, see [`RawImage::generate_mipmaps`] for the
+    /// formats this supports. `pixels` must hold exactly the first level.
+    pub fn from_data_with_mipmaps(queue: Arc<Queue>, extent: vk::Extent2D, usage: ImageUsageFlags, format: Format, dst_pipeline: PipelineStageFlags, pool: Arc<CommandPool>, pixels: &[u8]) -> Result<Self, NightfallError> {
+        let vk::Extent2D { width, height } = extent;
+        let image_size = width as usize * height as usize * Self::format_size(format) as usize;
+        if pixels.len() != image_size {
+            return Err(NightfallError::PixelDataSizeMismatch(pixels.len(), image_size));
+        }
+        let format = vk::Format::from_raw(format.as_raw());
+        let generation = MipmapGeneration::for_format(&queue.device(), format).ok_or(NightfallError::MipmapFormatUnsupported(format))?;
+        let stage = Buffer::new(queue.device(), BufferCreateInfo {
+                size: image_size,
+                usage: BufferUsageFlags::TRANSFER_SRC,
+                properties: MemoryPropertyFlags::HOST_VISIBLE| MemoryPropertyFlags::HOST_COHERENT,
+                ..Default::default()
+            },
+        )?;
+        unsafe {
+            let guard = stage.raw_map::<u8>(image_size, 0)?;
+            std::ptr::copy_nonoverlapping(pixels.as_ptr(), guard, image_size);
+            stage.raw_unmap();
+        }
+        let create_info = vk::ImageCreateInfo {
+            image_type: vk::ImageType::TYPE_2D,
+            extent: Extent3D { width, height, depth: 1 },
+            mip_levels: RawImage::max_mip_levels(width, height, 1),
+            array_layers: 1,
+            format,
+            tiling: vk::ImageTiling::OPTIMAL,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            usage: vk::ImageUsageFlags::from_raw((generation.required_usage() | usage).0),
+            sharing_mode: vk::SharingMode::EXCLUSIVE,
+            samples: vk::SampleCountFlags::TYPE_1,
+            ..Default::default()
+        };
+        let image = RawImage::from_raw_info(queue.device(), Some(queue.clone()), &create_info)?;
+        let this = Self::with_image(queue, image);
+        this.image.transition(
+            pool.clone(),
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::AccessFlags::TRANSFER_WRITE,
+            None,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER)?;
+        this.copy_buffer_to_image(pool.clone(), stage.buffer(), width, height)?;
+        this.image.generate_mipmaps(pool, ImageLayout::SHADER_READ_ONLY_OPTIMAL, dst_pipeline)?;
+        Ok(this)
+    }
     pub fn buffer_to_image(&self, pool: Arc<CommandPool>, width: u32, height: u32, buffer: vk::Buffer, pipeline: PipelineStageFlags) -> Result<(), VulkanError> {
         self.image.transition(
             pool.clone(),
@@ -183,6 +238,7 @@ impl TextureImage {
             .set_all_filters(vk::Filter::LINEAR)
             .compare_op(vk::CompareOp::ALWAYS)
             .set_mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .set_max_lod(vk::LOD_CLAMP_NONE)
             .border_colors(vk::BorderColor::INT_OPAQUE_BLACK)
             .enable_anistropy(true)
             .build(device.clone()).unwrap()
@@ -207,4 +263,8 @@ impl TextureImage {
     pub fn height(&self) -> usize {
         self.image.height()
     }
+    #[inline]
+    pub fn image(&self) -> &Arc<RawImage> {
+        &self.image
+    }
 }
\ No newline at end of file
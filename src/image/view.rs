@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use ash::vk;
 
-use crate::device::LogicalDevice;
+use crate::device::{DeferredHandle, LogicalDevice};
 
 pub struct ImageView {
     device: Arc<LogicalDevice>,
@@ -25,6 +25,6 @@ impl ImageView {
 
 impl Drop for ImageView {
     fn drop(&mut self) {
-        unsafe { self.device.device.destroy_image_view(self.view, None) };
+        self.device.destroy_deferred(DeferredHandle::ImageView(self.view));
     }
 }
\ No newline at end of file
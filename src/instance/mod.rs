@@ -77,6 +77,12 @@ impl InstanceBuilder {
         self.extensions.khr_surface = true;
         self
     }
+    /// lets surfaces report HDR and wide gamut color spaces.
+    pub fn swapchain_colorspace(mut self) -> Self {
+        self.extensions.khr_surface = true;
+        self.extensions.ext_swapchain_colorspace = true;
+        self
+    }
     pub fn physical_device_property_extension(mut self) -> Self {
         self.extensions.khr_get_display_properties2 = true;
         self
@@ -164,6 +170,18 @@ impl Instance {
     pub fn surface_from_raw_handles(self: Arc<Self>, display: RawDisplayHandle, window: RawWindowHandle) -> Result<Surface, VulkanError> {
         unsafe { Surface::from_handles(self.clone(), display, window) }
     }
+    pub fn get_physical_device_surface_support(&self, physical_device: Arc<PhysicalDevice>, queue_family_index: u32, surface: SurfaceKHR) -> Result<bool, VulkanError> {
+        let mut supported = vk::FALSE;
+        unsafe {
+            (self.fns.khr_surface.get_physical_device_surface_support_khr)(
+                physical_device.handle,
+                queue_family_index,
+                surface,
+                &mut supported
+            ).result().map_err(VulkanError::from)?;
+        }
+        Ok(supported == vk::TRUE)
+    }
     pub fn get_physical_device_surface_capabilities(&self, physical_device: Arc<PhysicalDevice>, surface: SurfaceKHR) -> Result<vk::SurfaceCapabilitiesKHR, VulkanError> {
         let mut handle = MaybeUninit::uninit();
         unsafe {
@@ -1,4 +1,5 @@
 #![allow(unused)]
+extern crate self as nightfall_core;
 mod definitions;
 use ash::vk::{self, TaggedStructure};
 pub use definitions::*;
@@ -18,9 +19,12 @@ pub mod entry;
 pub mod error;
 pub mod render;
 pub mod vertex;
+pub mod glsl;
+pub mod compute;
+pub mod primitives;
 mod nfptr;
 pub use nfptr::*;
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Version {
     pub major: u32,   
     pub minor: u32,   
@@ -50,6 +54,11 @@ impl TryFrom<Version> for u32 {
         }
     }
 }
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
 impl Version {
     pub fn new(major: u32, minor: u32, patch: u32) -> Self {
         Self { major, minor, patch }
@@ -1,4 +1,4 @@
-use std::{cell::Cell, os::raw::c_void, sync::Arc};
+use std::{os::raw::c_void, sync::{Arc, Mutex}};
 
 use ash::{vk, vk_bitflags_wrapped};
 
@@ -7,7 +7,7 @@ mod pointer;
 pub use map::*;
 pub use pointer::*;
 
-use crate::{buffers::MemoryPropertyFlags, device::LogicalDevice, error::VulkanError};
+use crate::{buffers::MemoryPropertyFlags, device::{DeferredHandle, LogicalDevice}, error::VulkanError};
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkMemoryAllocateFlagBits.html>"]
@@ -63,7 +63,7 @@ pub struct DeviceMemory {
     pub(crate) device: Arc<LogicalDevice>,
     pub(crate) size: usize,
     pub(crate) memory_type_index: u32,
-    pub(crate) mapping_state: Cell<Option<MappingState>>,
+    pub(crate) mapping_state: Mutex<Option<MappingState>>,
 }
 
 impl DeviceMemory {
@@ -77,7 +77,7 @@ impl DeviceMemory {
         
         let handle = unsafe { device.device.allocate_memory(&allocate_info, None).map_err(VulkanError::from)? };
         Ok(
-            Self { handle, device, size, memory_type_index, mapping_state: Cell::new(None) }
+            Self { handle, device, size, memory_type_index, mapping_state: Mutex::new(None) }
         )
     }
     pub fn get_memory_type_index(device: Arc<LogicalDevice>, properties: MemoryPropertyFlags, requirements: vk::MemoryRequirements) -> u32 {
@@ -114,6 +114,6 @@ impl DeviceMemory {
 
 impl Drop for DeviceMemory {
     fn drop(&mut self) {
-        unsafe { self.device.device.free_memory(self.memory(), None) };
+        self.device.destroy_deferred(DeferredHandle::Memory(self.handle));
     }
 }
\ No newline at end of file
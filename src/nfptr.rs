@@ -2,7 +2,7 @@ use std::{marker::PhantomData, ops::{Add, Deref, DerefMut}};
 
 use ash::vk::{self, Handle};
 
-use crate::{barriers::BufferMemoryBarrier, buffers::BufferOffset, descriptors::DescriptorBufferInfo, error::{NightfallError, PointerError}, memory::{AccessFlags, DevicePointer}};
+use crate::{barriers::BufferMemoryBarrier, buffers::BufferOffset, descriptors::{DescriptorBufferInfo, UniformBufferInfo}, error::{NightfallError, PointerError}, glsl::{Std140, Std430}, memory::{AccessFlags, DevicePointer}};
 
 #[derive(Clone, Debug)]
 pub struct NfPtr {
@@ -107,6 +107,14 @@ impl<T> NfPtrType<T> {
     pub fn as_descriptor_buffer_info(&self) -> DescriptorBufferInfo {
         self.0.as_descriptor_buffer_info()
     }
+    /// only available when `T` has a std140 layout, the layout of uniform blocks.
+    pub fn as_uniform_buffer_info(&self) -> UniformBufferInfo where T: Std140 {
+        unsafe { UniformBufferInfo::from_raw(self.0.as_descriptor_buffer_info()) }
+    }
+    /// only available when `T` has a std430 layout, the default layout of storage blocks.
+    pub fn as_storage_buffer_info(&self) -> DescriptorBufferInfo where T: Std430 {
+        self.0.as_descriptor_buffer_info()
+    }
     pub fn fill_buffer_memory_barrier(&self, barrier: &mut vk::BufferMemoryBarrier) {
         self.0.fill_buffer_memory_barrier(barrier)
     }
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use ash::vk;
 
-use crate::{buffers::BufferOffset, device::LogicalDevice, error::VulkanError};
+use crate::{buffers::BufferOffset, device::{DeferredHandle, LogicalDevice}, error::VulkanError, glsl::Std430};
 
 use super::{layout::PipelineLayout, shader::{HasShaderStages, Shader}, VulkanPipeline};
 
@@ -15,6 +15,10 @@ pub struct DispatchIndirectCommand {
     pub y: u32,
     pub z: u32,
 }
+unsafe impl Std430 for DispatchIndirectCommand {
+    const ALIGN: usize = 4;
+    const SIZE: usize = std::mem::size_of::<Self>();
+}
 impl DispatchIndirectCommand {
     pub fn new(x: u32, y: u32, z: u32) -> Self {
         Self { x, y, z }
@@ -137,6 +141,6 @@ impl VulkanPipeline for ComputePipeline {
 }
 impl Drop for ComputePipeline {
     fn drop(&mut self) {
-        unsafe { self.shader.device.device.destroy_pipeline(self.pipeline, None); }
+        self.shader.device.destroy_deferred(DeferredHandle::Pipeline(self.pipeline));
     }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use ash::{vk::{self, GraphicsPipelineCreateInfo, PipelineBindPoint, PipelineDepthStencilStateCreateInfo, PipelineShaderStageCreateInfo, PipelineTessellationStateCreateInfo, PipelineVertexInputStateCreateInfo, PolygonMode, VertexInputAttributeDescription}, vk_bitflags_wrapped};
 
-use crate::{device::{self, LogicalDevice}, error::VulkanError, image::SampleCountFlags};
+use crate::{buffers::BufferOffset, device::{self, DeferredHandle, LogicalDevice}, glsl::Std430, error::VulkanError, image::SampleCountFlags, vertex::VertexLayout};
 
 use super::{cache::PipelineCache, compute::ComputePipeline, layout::PipelineLayout, shader::Shader, VulkanPipeline};
 
@@ -232,6 +232,9 @@ impl GraphicsPipelineBuilder {
         });
         self
     }
+    pub fn vertex_layout(self, layout: &VertexLayout) -> Self {
+        self.vertex_input_state(Some(layout.bindings()), Some(layout.attributes()))
+    }
     pub fn pipeline_layout(mut self, layout: vk::PipelineLayout) -> Self {
         self.data.layout.push(layout);
         self
@@ -366,6 +369,45 @@ impl GraphicsPipelineBuilder {
         Ok(pipelines)
     }
 }
+#[repr(C)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone, Default)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkDrawIndirectCommand.html>"]
+pub struct DrawIndirectCommand {
+    pub vertex_count: u32,
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
+}
+impl DrawIndirectCommand {
+    pub fn new(vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) -> Self {
+        Self { vertex_count, instance_count, first_vertex, first_instance }
+    }
+}
+unsafe impl Std430 for DrawIndirectCommand {
+    const ALIGN: usize = 4;
+    const SIZE: usize = std::mem::size_of::<Self>();
+}
+#[repr(C)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone, Default)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkDrawIndexedIndirectCommand.html>"]
+pub struct DrawIndexedIndirectCommand {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub first_instance: u32,
+}
+impl DrawIndexedIndirectCommand {
+    pub fn new(index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) -> Self {
+        Self { index_count, instance_count, first_index, vertex_offset, first_instance }
+    }
+}
+unsafe impl Std430 for DrawIndexedIndirectCommand {
+    const ALIGN: usize = 4;
+    const SIZE: usize = std::mem::size_of::<Self>();
+}
 pub struct GraphicsPipeline {
     pub(crate) layout: Arc<PipelineLayout>,
     pub(crate) shaders: Vec<Arc<Shader>>,
@@ -385,6 +427,26 @@ impl GraphicsPipeline {
     pub fn draw_indexed(&self, command_buffer: vk::CommandBuffer, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) {
         unsafe { self.layout.device.device.cmd_draw_indexed(command_buffer, index_count, instance_count, first_index, vertex_offset, first_instance) }
     }
+    /// draws `draw_count` tightly packed [`DrawIndirectCommand`]s starting at `indirect`.
+    pub fn draw_indirect(&self, command_buffer: vk::CommandBuffer, indirect: BufferOffset, draw_count: u32) {
+        debug_assert!(draw_count <= 1 || self.layout.device.enabled_features.multi_draw_indirect, "multi_draw_indirect is not enabled");
+        unsafe { self.layout.device.device.cmd_draw_indirect(command_buffer, indirect.handle, indirect.offset, draw_count, std::mem::size_of::<DrawIndirectCommand>() as u32) }
+    }
+    /// draws `draw_count` tightly packed [`DrawIndexedIndirectCommand`]s starting at `indirect`.
+    pub fn draw_indexed_indirect(&self, command_buffer: vk::CommandBuffer, indirect: BufferOffset, draw_count: u32) {
+        debug_assert!(draw_count <= 1 || self.layout.device.enabled_features.multi_draw_indirect, "multi_draw_indirect is not enabled");
+        unsafe { self.layout.device.device.cmd_draw_indexed_indirect(command_buffer, indirect.handle, indirect.offset, draw_count, std::mem::size_of::<DrawIndexedIndirectCommand>() as u32) }
+    }
+    /// like [`draw_indirect`](Self::draw_indirect) with the draw count read from `count`, at most `max_draw_count`.
+    pub fn draw_indirect_count(&self, command_buffer: vk::CommandBuffer, indirect: BufferOffset, count: BufferOffset, max_draw_count: u32) {
+        debug_assert!(self.layout.device.enabled_features.draw_indirect_count, "draw_indirect_count is not enabled");
+        unsafe { self.layout.device.cmd_draw_indirect_count(command_buffer, indirect, count, max_draw_count, std::mem::size_of::<DrawIndirectCommand>() as u32) }
+    }
+    /// like [`draw_indexed_indirect`](Self::draw_indexed_indirect) with the draw count read from `count`, at most `max_draw_count`.
+    pub fn draw_indexed_indirect_count(&self, command_buffer: vk::CommandBuffer, indirect: BufferOffset, count: BufferOffset, max_draw_count: u32) {
+        debug_assert!(self.layout.device.enabled_features.draw_indirect_count, "draw_indirect_count is not enabled");
+        unsafe { self.layout.device.cmd_draw_indexed_indirect_count(command_buffer, indirect, count, max_draw_count, std::mem::size_of::<DrawIndexedIndirectCommand>() as u32) }
+    }
 }
 impl VulkanPipeline for GraphicsPipeline {
     fn pipeline_handle(&self) -> vk::Pipeline {
@@ -393,6 +455,6 @@ impl VulkanPipeline for GraphicsPipeline {
 }
 impl Drop for GraphicsPipeline {
     fn drop(&mut self) {
-        unsafe { self.layout.device.device.destroy_pipeline(self.pipeline, None) }
+        self.layout.device.destroy_deferred(DeferredHandle::Pipeline(self.pipeline));
     }
 }
\ No newline at end of file
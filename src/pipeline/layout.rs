@@ -2,14 +2,77 @@ use std::sync::Arc;
 
 use ash::vk;
 
-use crate::device::LogicalDevice;
+use crate::{device::LogicalDevice, error::NightfallError};
 
 use super::shader::ShaderStageFlags;
 
+pub use nightfall_core_macros::PushConstants;
+
+/// A push constant block whose fields follow std430 and carry the shader
+/// stages that read them. Derive it with `#[derive(PushConstants)]` on a
+/// `#[repr(C)]` struct, with `#[push_constants(stages(...))]` on the struct
+/// for the default stages and on fields to override them.
+/// 
+/// # Safety
+/// `MEMBERS` must describe fields that lie inside `Self`.
+pub unsafe trait PushConstants: Sized {
+    /// the stages, offset and size of every field in declaration order.
+    const MEMBERS: &'static [vk::PushConstantRange];
+    /// one range per distinct span of the block, covering every field a stage
+    /// reads. No stage appears in more than one range.
+    fn ranges() -> Vec<vk::PushConstantRange> {
+        merge_ranges(Self::MEMBERS)
+    }
+    /// the ranges split wherever they overlap, each piece tagged with every
+    /// stage that sees it, as `vkCmdPushConstants` requires.
+    fn updates() -> Vec<vk::PushConstantRange> {
+        split_ranges(&Self::ranges(), 0, std::mem::size_of::<Self>() as u32)
+    }
+}
+
+/// merges `members` into one range per stage spanning everything it reads,
+/// stages sharing a span share a range.
+fn merge_ranges(members: &[vk::PushConstantRange]) -> Vec<vk::PushConstantRange> {
+    let mut ranges: Vec<vk::PushConstantRange> = Vec::new();
+    for bit in 0..u32::BITS {
+        let stage = vk::ShaderStageFlags::from_raw(1 << bit);
+        let (start, end) = members.iter()
+            .filter(|member| member.stage_flags.contains(stage))
+            .fold((u32::MAX, 0), |(start, end), member| (start.min(member.offset), end.max(member.offset + member.size)));
+        if start >= end {
+            continue;
+        }
+        match ranges.iter_mut().find(|range| range.offset == start && range.offset + range.size == end) {
+            Some(range) => range.stage_flags |= stage,
+            None => ranges.push(vk::PushConstantRange { stage_flags: stage, offset: start, size: end - start }),
+        }
+    }
+    ranges.sort_by_key(|range| range.offset);
+    ranges
+}
+
+/// the bytes `start..end` of `ranges` split wherever a range begins or ends,
+/// each piece tagged with the stages of every range overlapping it.
+fn split_ranges(ranges: &[vk::PushConstantRange], start: u32, end: u32) -> Vec<vk::PushConstantRange> {
+    let mut bounds = ranges.iter()
+        .flat_map(|range| [range.offset, range.offset + range.size])
+        .chain([start, end])
+        .filter(|bound| (start..=end).contains(bound))
+        .collect::<Vec<_>>();
+    bounds.sort_unstable();
+    bounds.dedup();
+    bounds.windows(2).filter_map(|bound| {
+        let stage_flags = ranges.iter()
+            .filter(|range| range.offset < bound[1] && range.offset + range.size > bound[0])
+            .fold(vk::ShaderStageFlags::empty(), |flags, range| flags | range.stage_flags);
+        (!stage_flags.is_empty()).then_some(vk::PushConstantRange { stage_flags, offset: bound[0], size: bound[1] - bound[0] })
+    }).collect()
+}
 
 pub struct PipelineLayout {
     pub(crate) device: Arc<LogicalDevice>,
     pub(crate) layout: vk::PipelineLayout,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
 }
 pub struct PipelineLayoutBuilder {
     push_constant_ranges: Vec<vk::PushConstantRange>,
@@ -31,21 +94,40 @@ impl PipelineLayoutBuilder {
         self.push_constant_ranges.push(push_constant_range);
         self
     }
+    /// adds the block `P` starting `offset` bytes into the push constants,
+    /// blocks for different stages sit next to each other at their own
+    /// offsets and match `layout(offset = ...)` on the first GLSL member.
+    #[inline]
+    pub fn add_push_constants<P: PushConstants>(mut self, offset: u32) -> Self {
+        debug_assert!(offset.is_multiple_of(4), "push constant offsets must be a multiple of 4");
+        self.push_constant_ranges.extend(P::MEMBERS.iter().map(|member| vk::PushConstantRange { offset: member.offset + offset, ..*member }));
+        self
+    }
     #[inline]
     pub fn add_descriptor_layout(mut self, layout: vk::DescriptorSetLayout) -> Self {
         self.descriptor_set_layouts.push(layout);
         self
     }
     pub fn build(&self, device: Arc<LogicalDevice>) -> Arc<PipelineLayout> {
+        self.try_build(device).unwrap()
+    }
+    /// like [`build`](Self::build) but checks the push constants against
+    /// `maxPushConstantsSize` and returns the error instead of panicking.
+    pub fn try_build(&self, device: Arc<LogicalDevice>) -> Result<Arc<PipelineLayout>, NightfallError> {
+        let push_constant_ranges = merge_ranges(&self.push_constant_ranges);
+        let max_size = device.physical_device.properties.limits.max_push_constants_size;
+        if let Some(range) = push_constant_ranges.iter().find(|range| range.offset + range.size > max_size) {
+            return Err(NightfallError::PushConstantsTooLarge(range.offset + range.size, max_size));
+        }
         let layout_info = vk::PipelineLayoutCreateInfo {
-            push_constant_range_count: self.push_constant_ranges.len() as u32,
-            p_push_constant_ranges: self.push_constant_ranges.as_ptr(),
+            push_constant_range_count: push_constant_ranges.len() as u32,
+            p_push_constant_ranges: push_constant_ranges.as_ptr(),
             p_set_layouts: self.descriptor_set_layouts.as_ptr(),
             set_layout_count: self.descriptor_set_layouts.len() as u32,
             ..Default::default()
         };
-        let layout = unsafe { device.device.create_pipeline_layout(&layout_info, None).unwrap() };
-        Arc::new(PipelineLayout { layout, device })
+        let layout = unsafe { device.device.create_pipeline_layout(&layout_info, None)? };
+        Ok(Arc::new(PipelineLayout { layout, device, push_constant_ranges }))
     }
 }
 
@@ -55,10 +137,90 @@ impl PipelineLayout {
         PipelineLayoutBuilder::new()
     }
     pub fn get_layout(&self) -> vk::PipelineLayout { self.layout }
+    /// the push constant ranges the layout was created with, one per stage.
+    pub fn push_constant_ranges(&self) -> &[vk::PushConstantRange] {
+        &self.push_constant_ranges
+    }
+    /// the `vkCmdPushConstants` calls needed to update `size` bytes at `offset`.
+    pub(crate) fn push_constant_updates(&self, offset: u32, size: u32) -> Vec<vk::PushConstantRange> {
+        split_ranges(&self.push_constant_ranges, offset, offset + size)
+    }
 }
 
 impl Drop for PipelineLayout {
     fn drop(&mut self) {
         unsafe { self.device.device.destroy_pipeline_layout(self.layout, None) };
     }
+}
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use crate::glsl::{Mat4, Vec4};
+
+    use super::{merge_ranges, split_ranges, PushConstants};
+
+    #[repr(C)]
+    #[derive(Clone, Copy, PushConstants)]
+    #[push_constants(stages(VERTEX))]
+    struct Transform {
+        model: Mat4,
+        #[push_constants(stages(VERTEX, FRAGMENT))]
+        tint: Vec4,
+        #[push_constants(stages(FRAGMENT))]
+        exposure: f32,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, PushConstants)]
+    #[push_constants(stages(FRAGMENT))]
+    struct Material {
+        albedo: Vec4,
+    }
+
+    fn range(stage_flags: vk::ShaderStageFlags, offset: u32, size: u32) -> vk::PushConstantRange {
+        vk::PushConstantRange { stage_flags, offset, size }
+    }
+    fn assert_ranges(actual: &[vk::PushConstantRange], expected: &[vk::PushConstantRange]) {
+        let key = |range: &vk::PushConstantRange| (range.stage_flags, range.offset, range.size);
+        assert_eq!(actual.iter().map(key).collect::<Vec<_>>(), expected.iter().map(key).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn one_range_per_stage() {
+        assert_ranges(&Transform::ranges(), &[
+            range(vk::ShaderStageFlags::VERTEX, 0, 80),
+            range(vk::ShaderStageFlags::FRAGMENT, 64, 20),
+        ]);
+    }
+    #[test]
+    fn updates_carry_every_overlapping_stage() {
+        assert_ranges(&Transform::updates(), &[
+            range(vk::ShaderStageFlags::VERTEX, 0, 64),
+            range(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT, 64, 16),
+            range(vk::ShaderStageFlags::FRAGMENT, 80, 4),
+        ]);
+    }
+    #[test]
+    fn stages_sharing_a_span_share_a_range() {
+        let members = [range(vk::ShaderStageFlags::VERTEX, 0, 16), range(vk::ShaderStageFlags::FRAGMENT, 0, 16)];
+        assert_ranges(&merge_ranges(&members), &[range(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT, 0, 16)]);
+    }
+    #[test]
+    fn blocks_at_offsets_merge_per_stage() {
+        let members = Transform::MEMBERS.iter().copied()
+            .chain(Material::MEMBERS.iter().map(|member| vk::PushConstantRange { offset: member.offset + 96, ..*member }))
+            .collect::<Vec<_>>();
+        let ranges = merge_ranges(&members);
+        assert_ranges(&ranges, &[
+            range(vk::ShaderStageFlags::VERTEX, 0, 80),
+            range(vk::ShaderStageFlags::FRAGMENT, 64, 48),
+        ]);
+        assert_ranges(&split_ranges(&ranges, 96, 112), &[range(vk::ShaderStageFlags::FRAGMENT, 96, 16)]);
+        assert_ranges(&split_ranges(&ranges, 0, 84), &[
+            range(vk::ShaderStageFlags::VERTEX, 0, 64),
+            range(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT, 64, 16),
+            range(vk::ShaderStageFlags::FRAGMENT, 80, 4),
+        ]);
+    }
 }
\ No newline at end of file
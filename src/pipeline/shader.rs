@@ -64,6 +64,10 @@ impl<'a> Iterator for VulkanShaderIter<'a> {
         Some(next)
     }
 }
+/// SPIR-V embedded with `include_bytes!`, which doesn't promise the 4 byte
+/// alignment SPIR-V words need.
+#[repr(C, align(4))]
+pub(crate) struct Spirv<T: ?Sized>(pub(crate) T);
 #[derive(Clone, Debug)]
 pub struct ShaderCreateInfo<'a> {
     pub entry: &'a str, 
@@ -103,6 +107,9 @@ impl Shader {
         &self.stage
     }
 }
+// the stage info is only read after creation, its pointers are never written through
+unsafe impl Send for Shader {}
+unsafe impl Sync for Shader {}
 impl Debug for Shader {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Shader")
@@ -0,0 +1,454 @@
+//! Parallel primitives over `u32` storage buffers: exclusive and inclusive
+//! scans, reductions, radix sorts of keys or key-value pairs and stream
+//! compaction.
+//!
+//! [`Primitives`] holds the pipelines. A primitive is prepared once for the
+//! buffers it works on, which allocates its scratch buffers and descriptor
+//! sets, and is then recorded like any other dispatch:
+//! ```ignore
+//! let primitives = Primitives::new(device.clone())?;
+//! let sort = primitives.sort_pairs(&keys, &values)?;
+//! let recorder = allocation.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT)?;
+//! sort.record(&recorder);
+//! ```
+//! The inputs and outputs of a primitive must not overlap, and the caller
+//! synchronizes the buffers with the commands before and after it.
+//!
+//! The shaders are precompiled from the GLSL in `shaders/`, the subgroup
+//! variants for Vulkan 1.1 with `SUBGROUPS` defined. After editing one,
+//! rebuild them with `scripts/compile_shaders.sh`.
+mod plan;
+
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    barriers::MemoryBarrier,
+    buffers::{Buffer, BufferCreateInfo, BufferUsageFlags, MemoryPropertyFlags},
+    commands::CommandRecorder,
+    descriptors::{DescriptorBufferInfo, DescriptorLayout, DescriptorPool, DescriptorSetAllocation, DescriptorType, DescriptorWriter},
+    device::{LogicalDevice, PhysicalDevice},
+    error::NightfallError,
+    image::PipelineStageFlags,
+    memory::{AccessFlags, DependencyFlags},
+    pipeline::{compute::ComputePipeline, layout::PipelineLayout, shader::{Shader, ShaderCreateInfo, ShaderStageFlags, Spirv}},
+    NfPtrType,
+};
+use plan::{Kernel, Params, Plan, Slot};
+
+static SCAN: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/scan.spv"));
+static SCAN_SUBGROUP: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/scan_subgroup.spv"));
+static ADD: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/add.spv"));
+static RADIX_COUNT: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/radix_count.spv"));
+static RADIX_SCATTER: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/radix_scatter.spv"));
+static RADIX_SCATTER_SUBGROUP: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/radix_scatter_subgroup.spv"));
+static COMPACT: &Spirv<[u8]> = &Spirv(*include_bytes!("shaders/compact.spv"));
+
+/// The storage buffers every primitive shader declares, bindings `0..5` of set 0.
+const BINDINGS: u32 = 5;
+
+/// The compiled pipelines of every primitive, shared by all the passes
+/// prepared from it.
+pub struct Primitives {
+    device: Arc<LogicalDevice>,
+    descriptor_layout: Arc<DescriptorLayout>,
+    layout: Arc<PipelineLayout>,
+    scan: Arc<ComputePipeline>,
+    add: Arc<ComputePipeline>,
+    radix_count: Arc<ComputePipeline>,
+    radix_scatter: Arc<ComputePipeline>,
+    compact: Arc<ComputePipeline>,
+    subgroups: bool,
+}
+impl Primitives {
+    /// uses the subgroup shaders when the device [supports them](Self::supports_subgroups).
+    pub fn new(device: Arc<LogicalDevice>) -> Result<Self, NightfallError> {
+        Self::with_subgroups(device, true)
+    }
+    /// only uses the subgroup shaders when `subgroups` is set and the device supports them.
+    pub fn with_subgroups(device: Arc<LogicalDevice>, subgroups: bool) -> Result<Self, NightfallError> {
+        let subgroups = subgroups && Self::supports_subgroups(&device.physical_device());
+        let descriptor_layout = (0..BINDINGS)
+            .fold(DescriptorLayout::builder(), |builder, binding| builder.add_binding(binding, DescriptorType::STORAGE_BUFFER, 1, ShaderStageFlags::COMPUTE))
+            .build(device.clone());
+        let layout = PipelineLayout::builder()
+            .add_descriptor_layout(descriptor_layout.layout())
+            .add_push_constant::<Params>(ShaderStageFlags::COMPUTE)
+            .try_build(device.clone())?;
+        let pipeline = |spirv: &Spirv<[u8]>| -> Result<Arc<ComputePipeline>, NightfallError> {
+            let shader = Shader::new(device.clone(), ShaderCreateInfo { entry: "main\0", stage: ShaderStageFlags::COMPUTE, data: &spirv.0 })?;
+            Ok(Arc::new(ComputePipeline::new(device.clone(), layout.clone(), shader)?))
+        };
+        Ok(Self {
+            scan: pipeline(if subgroups { SCAN_SUBGROUP } else { SCAN })?,
+            add: pipeline(ADD)?,
+            radix_count: pipeline(RADIX_COUNT)?,
+            radix_scatter: pipeline(if subgroups { RADIX_SCATTER_SUBGROUP } else { RADIX_SCATTER })?,
+            compact: pipeline(COMPACT)?,
+            device,
+            descriptor_layout,
+            layout,
+            subgroups,
+        })
+    }
+    /// whether compute shaders on the device can use basic and arithmetic
+    /// subgroup operations, which needs Vulkan 1.1.
+    pub fn supports_subgroups(physical_device: &PhysicalDevice) -> bool {
+        if !physical_device.api_version().supports_version_1_1() {
+            return false;
+        }
+        let properties = physical_device.query_subgroup_properties();
+        properties.supported_stages.contains(vk::ShaderStageFlags::COMPUTE)
+            && properties.supported_operations.contains(vk::SubgroupFeatureFlags::BASIC | vk::SubgroupFeatureFlags::ARITHMETIC)
+    }
+    #[inline]
+    pub fn uses_subgroups(&self) -> bool {
+        self.subgroups
+    }
+    /// writes the sum of the elements of `src` before each index to `dst`.
+    pub fn exclusive_scan(&self, src: &NfPtrType<u32>, dst: &NfPtrType<u32>) -> Result<PrimitivePass, NightfallError> {
+        let len = input_len(src)?;
+        check_len(dst, len as usize)?;
+        self.prepare(Plan::exclusive_scan(len), &[src, dst])
+    }
+    /// writes the sum of the elements of `src` up to and including each index to `dst`.
+    pub fn inclusive_scan(&self, src: &NfPtrType<u32>, dst: &NfPtrType<u32>) -> Result<PrimitivePass, NightfallError> {
+        let len = input_len(src)?;
+        check_len(dst, len as usize)?;
+        self.prepare(Plan::inclusive_scan(len), &[src, dst])
+    }
+    /// writes the sum of every element of `src` to the first element of `sum`.
+    pub fn reduce(&self, src: &NfPtrType<u32>, sum: &NfPtrType<u32>) -> Result<PrimitivePass, NightfallError> {
+        let len = input_len(src)?;
+        check_len(sum, 1)?;
+        self.prepare(Plan::reduce(len), &[src, sum])
+    }
+    /// sorts `keys` in place, in ascending order.
+    pub fn sort_keys(&self, keys: &NfPtrType<u32>) -> Result<PrimitivePass, NightfallError> {
+        let len = input_len(keys)?;
+        self.prepare(Plan::radix_sort(len, false), &[keys])
+    }
+    /// sorts `keys` in place, in ascending order, moving each value along with
+    /// its key. Values with equal keys keep their order.
+    pub fn sort_pairs(&self, keys: &NfPtrType<u32>, values: &NfPtrType<u32>) -> Result<PrimitivePass, NightfallError> {
+        let len = input_len(keys)?;
+        check_len(values, len as usize)?;
+        self.prepare(Plan::radix_sort(len, true), &[keys, values])
+    }
+    /// copies the elements of `src` whose flag is not zero to the front of
+    /// `dst`, in order, and writes how many there were to `count`.
+    pub fn compact(&self, src: &NfPtrType<u32>, flags: &NfPtrType<u32>, dst: &NfPtrType<u32>, count: &NfPtrType<u32>) -> Result<PrimitivePass, NightfallError> {
+        let len = input_len(src)?;
+        check_len(flags, len as usize)?;
+        check_len(dst, len as usize)?;
+        check_len(count, 1)?;
+        self.prepare(Plan::compact(len), &[src, flags, dst, count])
+    }
+
+    fn prepare(&self, plan: Plan, buffers: &[&NfPtrType<u32>]) -> Result<PrimitivePass, NightfallError> {
+        let scratch = plan.scratch.iter().map(|&len| {
+            Ok(Arc::new(Buffer::new(self.device.clone(), BufferCreateInfo {
+                size: len as usize * std::mem::size_of::<u32>(),
+                usage: BufferUsageFlags::STORAGE_BUFFER,
+                properties: MemoryPropertyFlags::DEVICE_LOCAL,
+                ..Default::default()
+            })?))
+        }).collect::<Result<Vec<_>, NightfallError>>()?;
+        let steps = plan.steps.len() as u32;
+        let descriptor_pool = DescriptorPool::builder()
+            .add_pool_size(DescriptorType::STORAGE_BUFFER, BINDINGS * steps)
+            .set_max_sets(steps)
+            .build(self.device.clone());
+        let layouts = vec![self.descriptor_layout.clone(); plan.steps.len()];
+        let sets = descriptor_pool.allocate(&layouts)?.map(Arc::new).collect::<Vec<_>>();
+
+        // the writer keeps pointers into the infos, they're collected up front so they don't move.
+        let infos = plan.steps.iter().map(|step| step.bindings.map(|slot| match slot {
+            Slot::User(index) => buffers[index].as_storage_buffer_info(),
+            Slot::Scratch(index) => DescriptorBufferInfo { buffer: scratch[index].handle(), offset: 0, range: vk::WHOLE_SIZE },
+        })).collect::<Vec<_>>();
+        sets.iter().zip(&infos)
+            .flat_map(|(set, infos)| infos.iter().enumerate().map(move |(binding, info)| (set, binding as u32, info)))
+            .fold(DescriptorWriter::new(), |writer, (set, binding, info)| writer.add_storage_buffer(set.set(), 1, binding, 0, info))
+            .write(self.device.clone());
+
+        let dispatches = plan.steps.iter().zip(sets).map(|(step, set)| Dispatch {
+            pipeline: match step.kernel {
+                Kernel::Scan => self.scan.clone(),
+                Kernel::Add => self.add.clone(),
+                Kernel::RadixCount => self.radix_count.clone(),
+                Kernel::RadixScatter => self.radix_scatter.clone(),
+                Kernel::Compact => self.compact.clone(),
+            },
+            set,
+            params: step.params,
+            groups: step.groups(),
+        }).collect();
+        Ok(PrimitivePass { layout: self.layout.clone(), dispatches, scratch })
+    }
+}
+
+fn input_len(src: &NfPtrType<u32>) -> Result<u32, NightfallError> {
+    let len = src.size() / std::mem::size_of::<u32>();
+    if len == 0 {
+        return Err(NightfallError::NoInputWasGiven);
+    }
+    u32::try_from(len).map_err(|_| NightfallError::TooManyElements(len))
+}
+fn check_len(buffer: &NfPtrType<u32>, len: usize) -> Result<(), NightfallError> {
+    let size = buffer.size() / std::mem::size_of::<u32>();
+    if size < len {
+        return Err(NightfallError::BufferTooSmall(size, len));
+    }
+    Ok(())
+}
+
+struct Dispatch {
+    pipeline: Arc<ComputePipeline>,
+    set: Arc<DescriptorSetAllocation>,
+    params: Params,
+    groups: [u32; 2],
+}
+
+/// A primitive prepared for its buffers, recorded with [`record`](Self::record).
+/// Its scratch buffers are shared by every recording, so a pass must not run
+/// on several queues at once.
+pub struct PrimitivePass {
+    layout: Arc<PipelineLayout>,
+    dispatches: Vec<Dispatch>,
+    scratch: Vec<Arc<Buffer>>,
+}
+impl PrimitivePass {
+    /// records every dispatch of the primitive with a barrier between each.
+    /// The buffers the pass was prepared with are not retained.
+    pub fn record(&self, recorder: &CommandRecorder) {
+        let layout = self.layout.get_layout();
+        for (index, dispatch) in self.dispatches.iter().enumerate() {
+            if index > 0 {
+                let barrier = MemoryBarrier { src_access_mask: AccessFlags::SHADER_WRITE, dst_access_mask: AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE };
+                recorder.pipeline_barrier(PipelineStageFlags::COMPUTE_SHADER, PipelineStageFlags::COMPUTE_SHADER, DependencyFlags::empty(), &[barrier], &[], &[]);
+            }
+            recorder.bind_compute_pipeline(&dispatch.pipeline);
+            recorder.bind_descriptor_sets(vk::PipelineBindPoint::COMPUTE, layout, 0, std::slice::from_ref(&dispatch.set), &[]);
+            recorder.push_constants(layout, ShaderStageFlags::COMPUTE, 0, &dispatch.params);
+            recorder.dispatch(dispatch.groups[0], dispatch.groups[1], 1);
+        }
+        for buffer in &self.scratch {
+            recorder.retain(buffer);
+        }
+    }
+    /// the number of dispatches the pass records.
+    #[inline]
+    pub fn dispatch_count(&self) -> usize {
+        self.dispatches.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ash::vk;
+
+    use crate::{
+        barriers::MemoryBarrier,
+        buffers::{Buffer, BufferCreateInfo, BufferUsageFlags, MemoryPropertyFlags},
+        commands::{CommandBufferLevel, CommandBufferUsageFlags, CommandPool, CommandPoolCreateFlags},
+        device::{LogicalDevice, LogicalDeviceBuilder},
+        image::PipelineStageFlags,
+        instance::Instance,
+        memory::{AccessFlags, DependencyFlags},
+        queue::Queue,
+        sync::Fence,
+        AsNfptr, NfPtrType, Version,
+    };
+
+    use super::{Primitives, PrimitivePass};
+
+    pub(super) fn exclusive_scan(values: &[u32]) -> Vec<u32> {
+        values.iter().scan(0u32, |sum, &value| {
+            let before = *sum;
+            *sum = sum.wrapping_add(value);
+            Some(before)
+        }).collect()
+    }
+    pub(super) fn inclusive_scan(values: &[u32]) -> Vec<u32> {
+        values.iter().scan(0u32, |sum, &value| {
+            *sum = sum.wrapping_add(value);
+            Some(*sum)
+        }).collect()
+    }
+    pub(super) fn reduce(values: &[u32]) -> u32 {
+        values.iter().fold(0, |sum, &value| sum.wrapping_add(value))
+    }
+    /// a stable sort of the pairs by key.
+    pub(super) fn sort_pairs(keys: &[u32], values: &[u32]) -> (Vec<u32>, Vec<u32>) {
+        let mut pairs = keys.iter().copied().zip(values.iter().copied()).collect::<Vec<_>>();
+        pairs.sort_by_key(|&(key, _)| key);
+        pairs.into_iter().unzip()
+    }
+    pub(super) fn compact(values: &[u32], flags: &[u32]) -> Vec<u32> {
+        values.iter().zip(flags).filter(|(_, &flag)| flag != 0).map(|(&value, _)| value).collect()
+    }
+    /// deterministic xorshift values, `bits` wide.
+    pub(super) fn random(len: usize, bits: u32, mut seed: u32) -> Vec<u32> {
+        (0..len).map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            if bits == 32 { seed } else { seed & ((1 << bits) - 1) }
+        }).collect()
+    }
+
+    struct Gpu {
+        device: Arc<LogicalDevice>,
+        queue: Arc<Queue>,
+    }
+    impl Gpu {
+        fn new() -> Self {
+            let instance = Instance::builder().set_version(Version::new(1, 1, 0)).build().unwrap();
+            let physical_device = instance.enumerate_physical_devices().unwrap().next().unwrap();
+            let (device, mut queues) = LogicalDeviceBuilder::new().compute_queues(1, 1.0).build_with_queues(physical_device).unwrap();
+            Self { device, queue: queues.compute.remove(0).into_inner() }
+        }
+        fn buffer(&self, data: &[u32]) -> Arc<Buffer> {
+            let size = std::mem::size_of_val(data);
+            let buffer = Buffer::new(self.device.clone(), BufferCreateInfo {
+                size,
+                usage: BufferUsageFlags::STORAGE_BUFFER,
+                properties: MemoryPropertyFlags::HOST_VISIBLE_COHERENT,
+                ..Default::default()
+            }).unwrap();
+            unsafe {
+                let ptr = buffer.raw_map::<u8>(size, 0).unwrap();
+                std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, ptr, size);
+                buffer.raw_unmap();
+            }
+            Arc::new(buffer)
+        }
+        fn read(&self, buffer: &Buffer, len: usize) -> Vec<u32> {
+            let mut data = vec![0u32; len];
+            unsafe {
+                let ptr = buffer.raw_map::<u8>(len * 4, 0).unwrap();
+                std::ptr::copy_nonoverlapping(ptr, data.as_mut_ptr() as *mut u8, len * 4);
+                buffer.raw_unmap();
+            }
+            data
+        }
+        fn run(&self, pass: &PrimitivePass) {
+            let pool = CommandPool::new(self.device.clone(), CommandPoolCreateFlags::RESET_COMMAND_BUFFER, self.queue.family_index()).unwrap();
+            let mut allocation = unsafe { pool.allocate_command_buffers(CommandBufferLevel::PRIMARY, 1).unwrap().next().unwrap() };
+            let recorder = allocation.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT).unwrap();
+            pass.record(&recorder);
+            let barrier = MemoryBarrier { src_access_mask: AccessFlags::SHADER_WRITE, dst_access_mask: AccessFlags::HOST_READ };
+            recorder.pipeline_barrier(PipelineStageFlags::COMPUTE_SHADER, PipelineStageFlags::HOST, DependencyFlags::empty(), &[barrier], &[], &[]);
+            let command_buffer = recorder.end().unwrap();
+            let fence = Fence::new(self.device.clone(), false);
+            let submit = vk::SubmitInfo {
+                command_buffer_count: 1,
+                p_command_buffers: &command_buffer.get_command_buffer(),
+                ..Default::default()
+            };
+            self.queue.submit_raw(&[submit], &fence).unwrap();
+            fence.wait_max().unwrap();
+        }
+    }
+    fn ptr(buffer: &Buffer) -> NfPtrType<u32> {
+        unsafe { buffer.as_nfptr() }.cast().unwrap()
+    }
+
+    const LENGTHS: [usize; 5] = [1, 255, 257, 65_537, 300_000];
+
+    fn each_variant(test: impl Fn(&Gpu, &Primitives)) {
+        let gpu = Gpu::new();
+        for subgroups in [false, true] {
+            let primitives = Primitives::with_subgroups(gpu.device.clone(), subgroups).unwrap();
+            if primitives.uses_subgroups() == subgroups {
+                test(&gpu, &primitives);
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn scans_match_the_reference() {
+        each_variant(|gpu, primitives| {
+            for len in LENGTHS {
+                let values = random(len, 32, len as u32);
+                let src = gpu.buffer(&values);
+                let dst = gpu.buffer(&vec![0; len]);
+                gpu.run(&primitives.exclusive_scan(&ptr(&src), &ptr(&dst)).unwrap());
+                assert_eq!(gpu.read(&dst, len), exclusive_scan(&values), "exclusive scan of {len}");
+                gpu.run(&primitives.inclusive_scan(&ptr(&src), &ptr(&dst)).unwrap());
+                assert_eq!(gpu.read(&dst, len), inclusive_scan(&values), "inclusive scan of {len}");
+            }
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn reduce_matches_the_reference() {
+        each_variant(|gpu, primitives| {
+            for len in LENGTHS {
+                let values = random(len, 32, len as u32);
+                let src = gpu.buffer(&values);
+                let sum = gpu.buffer(&[0]);
+                gpu.run(&primitives.reduce(&ptr(&src), &ptr(&sum)).unwrap());
+                assert_eq!(gpu.read(&sum, 1), [reduce(&values)], "reduce of {len}");
+            }
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn sorts_match_the_reference() {
+        each_variant(|gpu, primitives| {
+            for len in LENGTHS {
+                // few distinct keys so equal keys show whether the sort is stable.
+                let keys = random(len, if len > 1000 { 32 } else { 3 }, len as u32);
+                let values = (0..len as u32).collect::<Vec<_>>();
+                let (sorted_keys, sorted_values) = sort_pairs(&keys, &values);
+
+                let key_buffer = gpu.buffer(&keys);
+                gpu.run(&primitives.sort_keys(&ptr(&key_buffer)).unwrap());
+                assert_eq!(gpu.read(&key_buffer, len), sorted_keys, "keys of {len}");
+
+                let key_buffer = gpu.buffer(&keys);
+                let value_buffer = gpu.buffer(&values);
+                gpu.run(&primitives.sort_pairs(&ptr(&key_buffer), &ptr(&value_buffer)).unwrap());
+                assert_eq!(gpu.read(&key_buffer, len), sorted_keys, "pair keys of {len}");
+                assert_eq!(gpu.read(&value_buffer, len), sorted_values, "pair values of {len}");
+            }
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn compact_matches_the_reference() {
+        each_variant(|gpu, primitives| {
+            for len in LENGTHS {
+                let values = random(len, 32, len as u32);
+                let flags = random(len, 2, !(len as u32));
+                let kept = compact(&values, &flags);
+                let src = gpu.buffer(&values);
+                let flag_buffer = gpu.buffer(&flags);
+                let dst = gpu.buffer(&vec![0; len]);
+                let count = gpu.buffer(&[0]);
+                gpu.run(&primitives.compact(&ptr(&src), &ptr(&flag_buffer), &ptr(&dst), &ptr(&count)).unwrap());
+                assert_eq!(gpu.read(&count, 1), [kept.len() as u32], "count of {len}");
+                assert_eq!(gpu.read(&dst, kept.len()), kept, "compaction of {len}");
+            }
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn rejects_mismatched_buffers() {
+        let gpu = Gpu::new();
+        let primitives = Primitives::new(gpu.device.clone()).unwrap();
+        let src = gpu.buffer(&[1, 2, 3, 4]);
+        let dst = gpu.buffer(&[0, 0]);
+        assert!(matches!(primitives.exclusive_scan(&ptr(&src), &ptr(&dst)), Err(crate::error::NightfallError::BufferTooSmall(2, 4))));
+    }
+}
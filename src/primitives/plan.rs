@@ -0,0 +1,311 @@
+use bytemuck::{Pod, Zeroable};
+
+/// The number of elements a workgroup of any primitive shader handles.
+pub(crate) const BLOCK_SIZE: u32 = 256;
+/// The guaranteed minimum of `maxComputeWorkGroupCount`, larger dispatches
+/// continue along y.
+const MAX_GROUPS_X: u32 = 65535;
+/// Radix sorts go through the keys 4 bits at a time.
+const RADIX_BITS: u32 = 4;
+const RADIX: u32 = 1 << RADIX_BITS;
+
+// the mode flags, matching the defines in the shaders.
+const SCAN_INCLUSIVE: u32 = 1;
+const SCAN_PREDICATE: u32 = 2;
+const SCAN_TOTALS_ONLY: u32 = 4;
+const SCATTER_VALUES: u32 = 1;
+
+/// The push constants every primitive shader declares.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Params {
+    pub len: u32,
+    pub mode: u32,
+    pub shift: u32,
+    pub blocks: u32,
+}
+unsafe impl Zeroable for Params {}
+unsafe impl Pod for Params {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Kernel {
+    Scan,
+    Add,
+    RadixCount,
+    RadixScatter,
+    Compact,
+}
+
+/// A buffer bound to a step, either one the caller passed, by position, or
+/// one of the plan's scratch buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Slot {
+    User(usize),
+    Scratch(usize),
+}
+
+/// One dispatch of a primitive, bindings 0 to 4 of the shared set layout.
+#[derive(Clone, Debug)]
+pub(crate) struct Step {
+    pub kernel: Kernel,
+    pub bindings: [Slot; 5],
+    pub params: Params,
+}
+impl Step {
+    fn new(kernel: Kernel, bindings: [Slot; 5], len: u32, mode: u32, shift: u32) -> Self {
+        Self { kernel, bindings, params: Params { len, mode, shift, blocks: len.div_ceil(BLOCK_SIZE) } }
+    }
+    /// the workgroups to dispatch along x and y.
+    pub fn groups(&self) -> [u32; 2] {
+        let x = self.params.blocks.min(MAX_GROUPS_X);
+        [x, self.params.blocks.div_ceil(x)]
+    }
+}
+
+/// The dispatches making up a primitive and the scratch buffers they need,
+/// worked out without a device. Steps run in order with a barrier between each.
+#[derive(Debug, Default)]
+pub(crate) struct Plan {
+    pub steps: Vec<Step>,
+    /// the length of each scratch buffer in `u32`s.
+    pub scratch: Vec<u32>,
+}
+impl Plan {
+    pub fn exclusive_scan(len: u32) -> Self {
+        let mut plan = Self::default();
+        plan.scan(Slot::User(0), Slot::User(1), len, 0);
+        plan
+    }
+    pub fn inclusive_scan(len: u32) -> Self {
+        let mut plan = Self::default();
+        plan.scan(Slot::User(0), Slot::User(1), len, SCAN_INCLUSIVE);
+        plan
+    }
+    pub fn reduce(len: u32) -> Self {
+        let mut plan = Self::default();
+        plan.reduce_into(Slot::User(0), Slot::User(1), len);
+        plan
+    }
+    /// sorts the keys in `User(0)`, along with the values in `User(1)` when `values` is set.
+    pub fn radix_sort(len: u32, values: bool) -> Self {
+        let mut plan = Self::default();
+        let counts = plan.scratch(RADIX * len.div_ceil(BLOCK_SIZE));
+        let offsets = plan.scratch(RADIX * len.div_ceil(BLOCK_SIZE));
+        let mut src = (Slot::User(0), values.then_some(Slot::User(1)));
+        let mut dst = (plan.scratch(len), values.then(|| plan.scratch(len)));
+        let mode = if values { SCATTER_VALUES } else { 0 };
+        // the counts have the same length every pass, so the steps scanning them and their scratch are reused.
+        let mut scan_steps: Option<Vec<Step>> = None;
+        for shift in (0..u32::BITS).step_by(RADIX_BITS as usize) {
+            plan.steps.push(Step::new(Kernel::RadixCount, [src.0, src.0, counts, src.0, src.0], len, 0, shift));
+            match &scan_steps {
+                Some(steps) => plan.steps.extend_from_slice(steps),
+                None => {
+                    let first = plan.steps.len();
+                    plan.scan(counts, offsets, RADIX * len.div_ceil(BLOCK_SIZE), 0);
+                    scan_steps = Some(plan.steps[first..].to_vec());
+                }
+            }
+            let bindings = [src.0, dst.0, offsets, src.1.unwrap_or(src.0), dst.1.unwrap_or(dst.0)];
+            plan.steps.push(Step::new(Kernel::RadixScatter, bindings, len, mode, shift));
+            std::mem::swap(&mut src, &mut dst);
+        }
+        plan
+    }
+    /// moves the values of `User(0)` with a non-zero flag in `User(1)` to
+    /// `User(2)` and writes how many there were to `User(3)`.
+    pub fn compact(len: u32) -> Self {
+        let mut plan = Self::default();
+        let indices = plan.scratch(len);
+        plan.scan(Slot::User(1), indices, len, SCAN_PREDICATE);
+        let bindings = [Slot::User(0), Slot::User(2), indices, Slot::User(1), Slot::User(3)];
+        plan.steps.push(Step::new(Kernel::Compact, bindings, len, 0, 0));
+        plan
+    }
+
+    fn scratch(&mut self, len: u32) -> Slot {
+        self.scratch.push(len.max(1));
+        Slot::Scratch(self.scratch.len() - 1)
+    }
+    /// scans each block, then scans the block totals and adds them back when there is more than one.
+    fn scan(&mut self, src: Slot, dst: Slot, len: u32, mode: u32) {
+        let blocks = len.div_ceil(BLOCK_SIZE);
+        let sums = self.scratch(blocks);
+        self.steps.push(Step::new(Kernel::Scan, [src, dst, sums, src, src], len, mode, 0));
+        if blocks > 1 {
+            let offsets = self.scratch(blocks);
+            self.scan(sums, offsets, blocks, 0);
+            self.steps.push(Step::new(Kernel::Add, [dst, dst, offsets, dst, dst], len, 0, 0));
+        }
+    }
+    /// sums each block until a single block writes its total to `dst`.
+    fn reduce_into(&mut self, src: Slot, dst: Slot, len: u32) {
+        let blocks = len.div_ceil(BLOCK_SIZE);
+        let sums = if blocks == 1 { dst } else { self.scratch(blocks) };
+        self.steps.push(Step::new(Kernel::Scan, [src, src, sums, src, src], len, SCAN_TOTALS_ONLY, 0));
+        if blocks > 1 {
+            self.reduce_into(sums, dst, blocks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::tests::{compact, exclusive_scan, inclusive_scan, random, reduce, sort_pairs};
+
+    /// runs each step on the CPU the way its shader does, block by block.
+    fn replay(plan: &Plan, user: &mut [Vec<u32>]) {
+        // scratch starts out as garbage, like fresh device memory.
+        let mut scratch = plan.scratch.iter().map(|&len| vec![0xdead_beef; len as usize]).collect::<Vec<_>>();
+        for step in &plan.steps {
+            let Params { len, mode, shift, blocks } = step.params;
+            let mut buffers = step.bindings.map(|slot| match slot {
+                Slot::User(index) => user[index].clone(),
+                Slot::Scratch(index) => scratch[index].clone(),
+            });
+            let block_range = |block: u32| (block * BLOCK_SIZE) as usize..len.min((block + 1) * BLOCK_SIZE) as usize;
+            let digit = |key: u32| ((key >> shift) % RADIX) as usize;
+            let written: &[usize] = match step.kernel {
+                Kernel::Scan => {
+                    for block in 0..blocks {
+                        let mut total = 0u32;
+                        for index in block_range(block) {
+                            let value = if mode & SCAN_PREDICATE != 0 { (buffers[0][index] != 0) as u32 } else { buffers[0][index] };
+                            if mode & SCAN_TOTALS_ONLY == 0 {
+                                buffers[1][index] = if mode & SCAN_INCLUSIVE != 0 { total.wrapping_add(value) } else { total };
+                            }
+                            total = total.wrapping_add(value);
+                        }
+                        buffers[2][block as usize] = total;
+                    }
+                    if mode & SCAN_TOTALS_ONLY != 0 { &[2] } else { &[1, 2] }
+                }
+                Kernel::Add => {
+                    for index in 0..len as usize {
+                        buffers[1][index] = buffers[1][index].wrapping_add(buffers[2][index / BLOCK_SIZE as usize]);
+                    }
+                    &[1]
+                }
+                Kernel::RadixCount => {
+                    for block in 0..blocks {
+                        let mut histogram = [0; RADIX as usize];
+                        for index in block_range(block) {
+                            histogram[digit(buffers[0][index])] += 1;
+                        }
+                        for (d, count) in histogram.into_iter().enumerate() {
+                            buffers[2][d * blocks as usize + block as usize] = count;
+                        }
+                    }
+                    &[2]
+                }
+                Kernel::RadixScatter => {
+                    for block in 0..blocks {
+                        let mut ranks = [0; RADIX as usize];
+                        for index in block_range(block) {
+                            let d = digit(buffers[0][index]);
+                            let destination = (buffers[2][d * blocks as usize + block as usize] + ranks[d]) as usize;
+                            ranks[d] += 1;
+                            buffers[1][destination] = buffers[0][index];
+                            if mode & SCATTER_VALUES != 0 {
+                                buffers[4][destination] = buffers[3][index];
+                            }
+                        }
+                    }
+                    if mode & SCATTER_VALUES != 0 { &[1, 4] } else { &[1] }
+                }
+                Kernel::Compact => {
+                    for index in 0..len as usize {
+                        if buffers[3][index] != 0 {
+                            let destination = buffers[2][index] as usize;
+                            buffers[1][destination] = buffers[0][index];
+                        }
+                    }
+                    let last = len as usize - 1;
+                    buffers[4][0] = buffers[2][last] + (buffers[3][last] != 0) as u32;
+                    &[1, 4]
+                }
+            };
+            for &binding in written {
+                match step.bindings[binding] {
+                    Slot::User(index) => user[index] = buffers[binding].clone(),
+                    Slot::Scratch(index) => scratch[index] = buffers[binding].clone(),
+                }
+            }
+        }
+    }
+
+    const LENGTHS: [usize; 6] = [1, 255, 256, 257, 65_537, 300_000];
+
+    #[test]
+    fn scans_match_the_reference() {
+        for len in LENGTHS {
+            let values = random(len, 32, len as u32);
+            let mut buffers = [values.clone(), vec![0; len]];
+            replay(&Plan::exclusive_scan(len as u32), &mut buffers);
+            assert_eq!(buffers[1], exclusive_scan(&values), "exclusive scan of {len}");
+            replay(&Plan::inclusive_scan(len as u32), &mut buffers);
+            assert_eq!(buffers[1], inclusive_scan(&values), "inclusive scan of {len}");
+            assert_eq!(buffers[0], values);
+        }
+    }
+
+    #[test]
+    fn reduce_matches_the_reference() {
+        for len in LENGTHS {
+            let values = random(len, 32, len as u32);
+            let mut buffers = [values.clone(), vec![0]];
+            replay(&Plan::reduce(len as u32), &mut buffers);
+            assert_eq!(buffers[1], [reduce(&values)], "reduce of {len}");
+        }
+    }
+
+    #[test]
+    fn sorts_match_the_reference() {
+        for len in LENGTHS {
+            for bits in [3, 32] {
+                let keys = random(len, bits, len as u32);
+                let values = (0..len as u32).collect::<Vec<_>>();
+                let (sorted_keys, sorted_values) = sort_pairs(&keys, &values);
+                let mut buffers = [keys.clone()];
+                replay(&Plan::radix_sort(len as u32, false), &mut buffers);
+                assert_eq!(buffers[0], sorted_keys, "keys of {len}");
+                let mut buffers = [keys, values];
+                replay(&Plan::radix_sort(len as u32, true), &mut buffers);
+                assert_eq!(buffers[0], sorted_keys, "pair keys of {len}");
+                assert_eq!(buffers[1], sorted_values, "pair values of {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn compact_matches_the_reference() {
+        for len in LENGTHS {
+            let values = random(len, 32, len as u32);
+            let flags = random(len, 2, !(len as u32));
+            let kept = compact(&values, &flags);
+            let mut buffers = [values, flags, vec![0; len], vec![0]];
+            replay(&Plan::compact(len as u32), &mut buffers);
+            assert_eq!(buffers[3], [kept.len() as u32], "count of {len}");
+            assert_eq!(buffers[2][..kept.len()], kept, "compaction of {len}");
+        }
+    }
+
+    #[test]
+    fn large_dispatches_continue_along_y() {
+        let step = |len| Step::new(Kernel::Scan, [Slot::User(0); 5], len, 0, 0);
+        assert_eq!(step(1).groups(), [1, 1]);
+        assert_eq!(step(MAX_GROUPS_X * BLOCK_SIZE).groups(), [MAX_GROUPS_X, 1]);
+        assert_eq!(step(MAX_GROUPS_X * BLOCK_SIZE + 1).groups(), [MAX_GROUPS_X, 2]);
+        assert_eq!(step(u32::MAX).groups(), [MAX_GROUPS_X, 257]);
+    }
+
+    #[test]
+    fn radix_sorts_end_in_the_keys() {
+        let plan = Plan::radix_sort(1000, true);
+        let last = plan.steps.last().unwrap();
+        assert_eq!(last.kernel, Kernel::RadixScatter);
+        assert_eq!(last.bindings[1], Slot::User(0));
+        assert_eq!(last.bindings[4], Slot::User(1));
+    }
+}
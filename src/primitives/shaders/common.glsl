@@ -0,0 +1,21 @@
+// Shared by every primitive: one element per invocation, 256 invocations per
+// workgroup and workgroups laid out over x and y so more than 65535 blocks fit
+// in a dispatch.
+#define WORKGROUP_SIZE 256u
+
+layout(local_size_x = 256) in;
+
+layout(push_constant) uniform Params {
+    // the number of elements.
+    uint len;
+    // flags picking a variant of the primitive.
+    uint mode;
+    // the bit offset of the radix sort digit.
+    uint shift;
+    // the number of blocks `len` is split into.
+    uint blocks;
+} params;
+
+uint block_index() {
+    return gl_WorkGroupID.y * gl_NumWorkGroups.x + gl_WorkGroupID.x;
+}
@@ -0,0 +1,84 @@
+// Exclusive scan of one value per invocation across the workgroup. Compiled
+// with SUBGROUPS the subgroups scan their values and only their sums go
+// through shared memory, otherwise the scan runs entirely in shared memory.
+// `element_index` is the position of the invocation's value in the scan, it is
+// called once at the start of `main`, outside of any branch.
+
+#ifdef SUBGROUPS
+#extension GL_KHR_shader_subgroup_basic : require
+#extension GL_KHR_shader_subgroup_arithmetic : require
+
+// a slot per subgroup, partial subgroups can make that one per invocation, then the total.
+#define TOTAL_SLOT WORKGROUP_SIZE
+shared uint subgroup_sums[TOTAL_SLOT + 1u];
+
+// returns the sum of the values before this invocation's and the sum of all values.
+uvec2 workgroup_scan(uint value) {
+    uint inclusive = subgroupInclusiveAdd(value);
+    uint subgroup_total = subgroupAdd(value);
+    if (subgroupElect()) {
+        subgroup_sums[gl_SubgroupID] = subgroup_total;
+    }
+    barrier();
+    if (gl_SubgroupID == 0u) {
+        // the first subgroup may be partial too, so it steps over the sums by
+        // its active invocations rather than by the subgroup size.
+        uint lane = subgroupExclusiveAdd(1u);
+        uint width = subgroupAdd(1u);
+        uint carry = 0u;
+        for (uint base = 0u; base < gl_NumSubgroups; base += width) {
+            uint slot = base + lane;
+            uint sum = 0u;
+            if (slot < gl_NumSubgroups) {
+                sum = subgroup_sums[slot];
+            }
+            uint prefix = subgroupExclusiveAdd(sum);
+            uint chunk = subgroupAdd(sum);
+            if (slot < gl_NumSubgroups) {
+                subgroup_sums[slot] = carry + prefix;
+            }
+            carry += chunk;
+        }
+        if (subgroupElect()) {
+            subgroup_sums[TOTAL_SLOT] = carry;
+        }
+    }
+    barrier();
+    uvec2 result = uvec2(inclusive - value + subgroup_sums[gl_SubgroupID], subgroup_sums[TOTAL_SLOT]);
+    // the sums are overwritten by the next scan.
+    barrier();
+    return result;
+}
+
+// subgroups need not be full or made of consecutive invocations, so the
+// elements are numbered in the order the scan itself goes through them.
+uint element_index() {
+    return workgroup_scan(1u).x;
+}
+#else
+shared uint scan_values[WORKGROUP_SIZE];
+
+uint element_index() {
+    return gl_LocalInvocationID.x;
+}
+
+// returns the sum of the values before this invocation's and the sum of all values.
+uvec2 workgroup_scan(uint value) {
+    uint index = gl_LocalInvocationID.x;
+    scan_values[index] = value;
+    barrier();
+    for (uint offset = 1u; offset < WORKGROUP_SIZE; offset <<= 1u) {
+        uint previous = 0u;
+        if (index >= offset) {
+            previous = scan_values[index - offset];
+        }
+        barrier();
+        scan_values[index] += previous;
+        barrier();
+    }
+    uvec2 result = uvec2(scan_values[index] - value, scan_values[WORKGROUP_SIZE - 1u]);
+    // the values are overwritten by the next scan.
+    barrier();
+    return result;
+}
+#endif
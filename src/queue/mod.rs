@@ -1,11 +1,13 @@
-use std::{mem::MaybeUninit, sync::Arc};
+use std::{mem::MaybeUninit, sync::{Arc, Mutex, MutexGuard, PoisonError}};
 
 use ash::{vk, vk_bitflags_wrapped};
+mod select;
 mod submit;
 use smallvec::SmallVec;
+pub use select::*;
 pub use submit::*;
 
-use crate::{commands::{CommandBufferLevel, CommandPool, CommandPoolAllocation}, device::LogicalDevice, error::VulkanError, sync::Fence};
+use crate::{commands::{CommandBufferLevel, CommandPool, CommandPoolAllocation}, device::LogicalDevice, error::VulkanError, sync::{Fence, FenceWait}};
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkDeviceQueueCreateFlagBits.html>"]
 pub struct DeviceQueueCreateFlags(pub(crate) u32);
@@ -68,8 +70,14 @@ impl From<&ash::vk::QueueFamilyProperties> for QueueFamilyProperties {
         }
     }
 }
+/// A device queue. Vulkan requires calls on a queue to be externally synchronized,
+/// so every submission and present goes through [`Queue::lock`], which makes
+/// `Arc<Queue>` safe to share between threads. Queues wrapping the same `VkQueue`
+/// share the lock.
 pub struct Queue {
     handle: ash::vk::Queue,
+    lock: Arc<Mutex<()>>,
+    retired: Arc<RetiredSubmissions>,
     device: Arc<LogicalDevice>,
     queue_family_index: u32,
     queue_flags: QueueFlags,
@@ -97,6 +105,8 @@ impl Queue {
         };
         Arc::new(
             Self {
+                lock: device.queue_lock(handle),
+                retired: Arc::default(),
                 device,
                 handle,
                 idx,
@@ -105,7 +115,7 @@ impl Queue {
             }
         )
     }
-    pub fn single_time_commands(&self, pool: Arc<CommandPool>) -> Result<CommandPoolAllocation, VulkanError> {
+    pub(crate) fn single_time_commands(&self, pool: Arc<CommandPool>) -> Result<CommandPoolAllocation, VulkanError> {
         let cmd = unsafe { pool.allocate_command_buffers(CommandBufferLevel::PRIMARY, 1)?.next().unwrap() };
         let begin_info = vk::CommandBufferBeginInfo {
             flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
@@ -114,73 +124,63 @@ impl Queue {
         unsafe { self.device.device.begin_command_buffer(cmd.get_command_buffer(), &begin_info).unwrap() };
         Ok(cmd)
     }
-    pub fn end_single_time_commands(&self, pool: Arc<CommandPool>, command_buffer: &CommandPoolAllocation) {
+    pub(crate) fn end_single_time_commands(&self, pool: Arc<CommandPool>, command_buffer: &CommandPoolAllocation) {
         unsafe { self.device.device.end_command_buffer(command_buffer.get_command_buffer()).unwrap() };
         let info = vk::SubmitInfo {
             command_buffer_count: 1,
             p_command_buffers: &command_buffer.get_command_buffer(),
             ..Default::default()
         };
+        self.submit_locked(&[info], vk::Fence::null()).unwrap();
+        self.wait_idle().unwrap();
         unsafe { 
-            self.device.device.queue_submit(self.handle, &[info], vk::Fence::null()).unwrap();
-            self.device.device.queue_wait_idle(self.handle).unwrap();
             self.device.device.free_command_buffers(pool.command_pool, &[command_buffer.get_command_buffer()]);
         }
     }
-    pub fn submit_raw(&self, submits: &[vk::SubmitInfo], fence: &Fence) -> Result<(), VulkanError> {
-        unsafe { 
-            self.device.device.queue_submit(
-                self.handle,
-                &submits,
-                fence.get()
-            ).map_err(VulkanError::from)
+    /// locks the queue, blocking until no other thread is submitting or presenting to it.
+    pub fn lock(&self) -> QueueGuard<'_> {
+        QueueGuard {
+            handle: self.handle,
+            _guard: self.lock.lock().unwrap_or_else(PoisonError::into_inner),
         }
     }
-    pub fn submit(&self, submit: &[&Submission]) -> Result<SubmissionCache, VulkanError> {
-        let cache = Submission::cached(submit);
-        self.submit_cached(&cache)?;
-        Ok(cache)
+    fn submit_locked(&self, submits: &[vk::SubmitInfo], fence: vk::Fence) -> Result<(), VulkanError> {
+        let queue = self.lock();
+        unsafe { self.device.device.queue_submit(queue.handle(), submits, fence).map_err(VulkanError::from) }
     }
-    pub fn submit_with_fence(&self, submit: &[&Submission]) -> Result<Arc<Fence>, VulkanError> {
+    pub fn wait_idle(&self) -> Result<(), VulkanError> {
+        let queue = self.lock();
+        unsafe { self.device.device.queue_wait_idle(queue.handle()).map_err(VulkanError::from) }
+    }
+    pub fn submit_raw(&self, submits: &[vk::SubmitInfo], fence: &Fence) -> Result<(), VulkanError> {
+        self.submit_locked(submits, fence.get())
+    }
+    /// submits the command buffers, returning a guard which keeps every resource
+    /// they retained alive until the GPU has finished executing them. Resources
+    /// of earlier submissions whose guards were dropped are released here once
+    /// those submissions completed.
+    pub fn submit(&self, submit: &[&Submission]) -> Result<SubmissionGuard, VulkanError> {
+        self.retired.collect();
         let cache = Submission::cached(submit);
-        let fence = Arc::new(Fence::new(self.device.clone(), false));
-        unsafe { 
-            self.device.device.queue_submit(
-                self.handle,
-                &cache.submits,
-                fence.get()
-            ).map_err(VulkanError::from)?;
-        };
-        Ok(fence)
+        let fence = Fence::new(self.device.clone(), false);
+        self.submit_locked(&cache.submits, fence.get())?;
+        Ok(SubmissionGuard::new(fence, submit, self.retired.clone()))
+    }
+    /// submits the command buffers with a new fence, the returned handle can be
+    /// awaited or turned back into the fence with [`FenceWait::into_inner`].
+    /// The queue keeps what the submissions retained until the fence signals.
+    pub fn submit_with_fence(&self, submit: &[&Submission]) -> Result<FenceWait, VulkanError> {
+        Ok(self.submit(submit)?.into_wait())
     }
     pub fn submit_cached(&self, cache: &SubmissionCache) -> Result<(), VulkanError> {
-        unsafe {
-            self.device.device.queue_submit(
-                self.handle,
-                &cache.submits,
-                vk::Fence::null()
-            ).map_err(VulkanError::from)?;
-        };
-        Ok(())
+        self.submit_locked(&cache.submits, vk::Fence::null())
     }
     pub fn submit_cached_external_fence(&self, cache: &SubmissionCache, fence: &Fence) -> Result<(), VulkanError> {
-        unsafe {
-            self.device.device.queue_submit(
-                self.handle,
-                &cache.submits,
-                fence.get()
-            ).map_err(VulkanError::from)
-        }
+        self.submit_locked(&cache.submits, fence.get())
     }
     pub fn submit_cached_with_fence(&self, cache: &SubmissionCache) -> Result<Arc<Fence>, VulkanError> {
         let fence = Arc::new(Fence::new(self.device.clone(), false));
-        unsafe {
-            self.device.device.queue_submit(
-                self.handle,
-                &cache.submits,
-                fence.get()
-            ).map_err(VulkanError::from)?;
-        };
+        self.submit_locked(&cache.submits, fence.get())?;
         Ok(fence)
     }
     pub fn queue_flags(&self) -> QueueFlags {
@@ -202,4 +202,20 @@ impl Queue {
     pub fn idx(&self) -> u32 {
         self.idx
     }
-}
\ No newline at end of file
+}
+/// Exclusive access to a [`Queue`], for calling into Vulkan with the raw handle.
+/// Other threads block on [`Queue::lock`] until the guard is dropped.
+pub struct QueueGuard<'a> {
+    handle: vk::Queue,
+    _guard: MutexGuard<'a, ()>,
+}
+impl QueueGuard<'_> {
+    #[inline]
+    pub fn handle(&self) -> vk::Queue {
+        self.handle
+    }
+}
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Queue>();
+};
\ No newline at end of file
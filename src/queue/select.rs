@@ -0,0 +1,298 @@
+use std::{collections::{BTreeMap, HashMap}, ops::Deref, sync::Arc};
+
+use ash::vk;
+
+use crate::{device::{LogicalDevice, PhysicalDevice}, error::VulkanError};
+
+use super::{DeviceQueueCreateFlags, Queue, QueueFlags};
+
+/// How many queues of a kind to create and the priority they are created with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueueRequest {
+    pub count: u32,
+    pub priority: f32,
+}
+impl QueueRequest {
+    pub const fn new(count: u32, priority: f32) -> Self {
+        Self { count, priority }
+    }
+}
+/// The queues requested through [`LogicalDeviceBuilder`](crate::device::LogicalDeviceBuilder),
+/// `present` restricts the graphics queues to families able to present to the surface.
+#[derive(Clone, Debug, Default)]
+pub struct QueueRequests {
+    pub graphics: Option<QueueRequest>,
+    pub present: Option<vk::SurfaceKHR>,
+    pub compute: Option<QueueRequest>,
+    pub transfer: Option<QueueRequest>,
+}
+impl QueueRequests {
+    pub fn is_empty(&self) -> bool {
+        self.graphics.is_none() && self.compute.is_none() && self.transfer.is_none()
+    }
+}
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum QueueKind {
+    Graphics,
+    Compute,
+    Transfer,
+}
+impl QueueKind {
+    fn flags(self) -> QueueFlags {
+        match self {
+            Self::Graphics => QueueFlags::GRAPHICS,
+            Self::Compute => QueueFlags::COMPUTE,
+            Self::Transfer => QueueFlags::TRANSFER,
+        }
+    }
+}
+/// Places where the selected queues are worse than what was asked for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum QueueFallback {
+    /// the queues use their own family, but it is not dedicated to the kind
+    /// (e.g. a compute family that also supports graphics).
+    NotDedicated { kind: QueueKind, family: u32 },
+    /// no other family was available so the queues share a family
+    /// with queues of another kind.
+    SharedFamily { kind: QueueKind, family: u32 },
+    /// the family ran out of queues, `granted` distinct queues were created and
+    /// the remaining handles alias them.
+    SharedQueue { kind: QueueKind, family: u32, requested: u32, granted: u32 },
+}
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct QueueAssignment {
+    pub kind: QueueKind,
+    pub family: u32,
+    /// queue indices within the family, one per requested queue.
+    pub indices: Vec<u32>,
+}
+/// The queue families chosen for a set of [`QueueRequests`].
+#[derive(Clone, Debug, Default)]
+pub struct QueueSelection {
+    pub assignments: Vec<QueueAssignment>,
+    pub fallbacks: Vec<QueueFallback>,
+    pub(crate) priorities: BTreeMap<u32, Vec<f32>>,
+}
+impl QueueSelection {
+    pub fn select(physical_device: &Arc<PhysicalDevice>, requests: &QueueRequests) -> Result<Self, VulkanError> {
+        let families = physical_device.enumerate_queue_family_properties();
+        let present = match requests.present {
+            Some(surface) => (0..families.len() as u32)
+                .map(|family| physical_device.instance.get_physical_device_surface_support(physical_device.clone(), family, surface))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![true; families.len()],
+        };
+        let flags = families.iter().map(|family| family.queue_flags).collect::<Vec<_>>();
+        let capacity = families.iter().map(|family| family.queue_count).collect::<Vec<_>>();
+        Self::select_families(&flags, &capacity, &present, requests)
+    }
+    /// picks families given their flags, queue counts and whether they can present.
+    fn select_families(flags: &[QueueFlags], capacity: &[u32], present: &[bool], requests: &QueueRequests) -> Result<Self, VulkanError> {
+        let mut selection = Self::default();
+        if let Some(request) = requests.graphics {
+            let family = (0..flags.len())
+                .find(|&family| flags[family].contains(QueueFlags::GRAPHICS) && present[family])
+                .ok_or_else(|| if flags.iter().any(|flags| flags.contains(QueueFlags::GRAPHICS)) {
+                    VulkanError::NoPresentQueueFamily
+                } else {
+                    VulkanError::NoSuitableQueueFamily(QueueFlags::GRAPHICS)
+                })?;
+            selection.assign(QueueKind::Graphics, family as u32, request, capacity);
+        }
+        if let Some(request) = requests.compute {
+            // dedicated async compute, then any other family, then sharing
+            let (family, rank) = selection.best_family(flags, |family_flags, used| {
+                if !family_flags.contains(QueueFlags::COMPUTE) {
+                    None
+                } else if !family_flags.contains(QueueFlags::GRAPHICS) && !used {
+                    Some(0)
+                } else if !used {
+                    Some(1)
+                } else {
+                    Some(2)
+                }
+            }).ok_or(VulkanError::NoSuitableQueueFamily(QueueFlags::COMPUTE))?;
+            selection.note_rank(QueueKind::Compute, family, rank);
+            selection.assign(QueueKind::Compute, family, request, capacity);
+        }
+        if let Some(request) = requests.transfer {
+            // graphics and compute families can always transfer
+            let (family, rank) = selection.best_family(flags, |family_flags, used| {
+                let graphics = family_flags.contains(QueueFlags::GRAPHICS);
+                let compute = family_flags.contains(QueueFlags::COMPUTE);
+                if !family_flags.contains(QueueFlags::TRANSFER) && !graphics && !compute {
+                    None
+                } else if !graphics && !compute && !used {
+                    Some(0)
+                } else if !used {
+                    Some(1)
+                } else {
+                    Some(2)
+                }
+            }).ok_or(VulkanError::NoSuitableQueueFamily(QueueFlags::TRANSFER))?;
+            selection.note_rank(QueueKind::Transfer, family, rank);
+            selection.assign(QueueKind::Transfer, family, request, capacity);
+        }
+        Ok(selection)
+    }
+    fn best_family(&self, flags: &[QueueFlags], rank: impl Fn(QueueFlags, bool) -> Option<u32>) -> Option<(u32, u32)> {
+        (0..flags.len() as u32)
+            .filter_map(|family| rank(flags[family as usize], self.priorities.contains_key(&family)).map(|rank| (family, rank)))
+            .min_by_key(|(_, rank)| *rank)
+    }
+    fn note_rank(&mut self, kind: QueueKind, family: u32, rank: u32) {
+        match rank {
+            0 => {}
+            1 => self.fallbacks.push(QueueFallback::NotDedicated { kind, family }),
+            _ => self.fallbacks.push(QueueFallback::SharedFamily { kind, family }),
+        }
+    }
+    fn assign(&mut self, kind: QueueKind, family: u32, request: QueueRequest, capacity: &[u32]) {
+        let priorities = self.priorities.entry(family).or_default();
+        let used = priorities.len() as u32;
+        let granted = request.count.min(capacity[family as usize] - used);
+        priorities.extend(std::iter::repeat_n(request.priority, granted as usize));
+        let indices = if granted == request.count {
+            (used..used + granted).collect()
+        } else {
+            self.fallbacks.push(QueueFallback::SharedQueue { kind, family, requested: request.count, granted });
+            // alias the queues created for this request, or the family's existing queues if none were left
+            let (first, len) = if granted > 0 { (used, granted) } else { (0, used) };
+            (0..request.count).map(|i| first + i % len).collect()
+        };
+        self.assignments.push(QueueAssignment { kind, family, indices });
+    }
+    /// whether every request got distinct, dedicated queues.
+    pub fn is_ideal(&self) -> bool {
+        self.fallbacks.is_empty()
+    }
+}
+
+macro_rules! typed_queue {
+    ($name:ident, $kind:ident, $doc:literal) => {
+        #[doc = $doc]
+        #[derive(Clone)]
+        pub struct $name(Arc<Queue>);
+        impl $name {
+            pub const KIND: QueueKind = QueueKind::$kind;
+            #[inline]
+            pub fn queue(&self) -> &Arc<Queue> {
+                &self.0
+            }
+            #[inline]
+            pub fn into_inner(self) -> Arc<Queue> {
+                self.0
+            }
+        }
+        impl Deref for $name {
+            type Target = Queue;
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+    };
+}
+typed_queue!(GraphicsQueue, Graphics, "A queue from a family supporting graphics, and presentation if a surface was given.");
+typed_queue!(ComputeQueue, Compute, "A queue from a family supporting compute, preferably without graphics.");
+typed_queue!(TransferQueue, Transfer, "A queue able to transfer, preferably from a dedicated transfer family.");
+
+/// The queues created by [`LogicalDeviceBuilder::build_with_queues`](crate::device::LogicalDeviceBuilder::build_with_queues).
+pub struct DeviceQueues {
+    pub graphics: Vec<GraphicsQueue>,
+    pub compute: Vec<ComputeQueue>,
+    pub transfer: Vec<TransferQueue>,
+    pub selection: QueueSelection,
+}
+impl DeviceQueues {
+    pub(crate) fn new(device: &Arc<LogicalDevice>, selection: QueueSelection) -> Self {
+        let families = device.physical_device.enumerate_queue_family_properties();
+        let mut created: HashMap<(u32, u32), Arc<Queue>> = HashMap::new();
+        let mut queues = Self { graphics: vec![], compute: vec![], transfer: vec![], selection: QueueSelection::default() };
+        for assignment in &selection.assignments {
+            for &idx in &assignment.indices {
+                let queue = created.entry((assignment.family, idx)).or_insert_with(|| unsafe {
+                    Queue::new(device.clone(), DeviceQueueCreateFlags::empty(), families[assignment.family as usize].queue_flags, assignment.family, idx)
+                }).clone();
+                debug_assert!(queue.queue_flags().intersects(assignment.kind.flags()) || assignment.kind == QueueKind::Transfer);
+                match assignment.kind {
+                    QueueKind::Graphics => queues.graphics.push(GraphicsQueue(queue)),
+                    QueueKind::Compute => queues.compute.push(ComputeQueue(queue)),
+                    QueueKind::Transfer => queues.transfer.push(TransferQueue(queue)),
+                }
+            }
+        }
+        queues.selection = selection;
+        queues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::VulkanError, queue::QueueFlags};
+
+    use super::{QueueAssignment, QueueFallback, QueueKind, QueueRequest, QueueRequests, QueueSelection};
+
+    const GRAPHICS: QueueFlags = QueueFlags::GRAPHICS;
+    const COMPUTE: QueueFlags = QueueFlags::COMPUTE;
+    const TRANSFER: QueueFlags = QueueFlags::TRANSFER;
+
+    fn requests(graphics: u32, compute: u32, transfer: u32) -> QueueRequests {
+        let request = |count| (count > 0).then_some(QueueRequest::new(count, 1.0));
+        QueueRequests { graphics: request(graphics), present: None, compute: request(compute), transfer: request(transfer) }
+    }
+
+    fn families(selection: &QueueSelection) -> Vec<(QueueKind, u32, Vec<u32>)> {
+        selection.assignments.iter().map(|QueueAssignment { kind, family, indices }| (*kind, *family, indices.clone())).collect()
+    }
+
+    #[test]
+    fn dedicated_families_are_preferred() {
+        let flags = [GRAPHICS | COMPUTE | TRANSFER, TRANSFER, COMPUTE | TRANSFER];
+        let selection = QueueSelection::select_families(&flags, &[16, 2, 8], &[true; 3], &requests(1, 2, 1)).unwrap();
+        assert_eq!(
+            families(&selection),
+            [(QueueKind::Graphics, 0, vec![0]), (QueueKind::Compute, 2, vec![0, 1]), (QueueKind::Transfer, 1, vec![0])]
+        );
+        assert!(selection.is_ideal());
+        assert_eq!(selection.priorities.get(&2).map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn a_single_family_is_shared_and_aliased() {
+        let flags = [GRAPHICS | COMPUTE | TRANSFER];
+        let selection = QueueSelection::select_families(&flags, &[2], &[true], &requests(1, 2, 1)).unwrap();
+        assert_eq!(
+            families(&selection),
+            [(QueueKind::Graphics, 0, vec![0]), (QueueKind::Compute, 0, vec![1, 1]), (QueueKind::Transfer, 0, vec![0])]
+        );
+        assert_eq!(
+            selection.fallbacks,
+            [
+                QueueFallback::SharedFamily { kind: QueueKind::Compute, family: 0 },
+                QueueFallback::SharedQueue { kind: QueueKind::Compute, family: 0, requested: 2, granted: 1 },
+                QueueFallback::SharedFamily { kind: QueueKind::Transfer, family: 0 },
+                QueueFallback::SharedQueue { kind: QueueKind::Transfer, family: 0, requested: 1, granted: 0 },
+            ]
+        );
+        assert_eq!(selection.priorities[&0].len(), 2);
+    }
+
+    #[test]
+    fn compute_on_a_graphics_family_is_not_dedicated() {
+        let flags = [GRAPHICS | COMPUTE | TRANSFER, GRAPHICS | COMPUTE];
+        let selection = QueueSelection::select_families(&flags, &[1, 1], &[true; 2], &requests(1, 1, 0)).unwrap();
+        assert_eq!(families(&selection), [(QueueKind::Graphics, 0, vec![0]), (QueueKind::Compute, 1, vec![0])]);
+        assert_eq!(selection.fallbacks, [QueueFallback::NotDedicated { kind: QueueKind::Compute, family: 1 }]);
+    }
+
+    #[test]
+    fn graphics_needs_a_family_able_to_present() {
+        let flags = [GRAPHICS | COMPUTE, GRAPHICS];
+        let selection = QueueSelection::select_families(&flags, &[1, 1], &[false, true], &requests(1, 0, 0)).unwrap();
+        assert_eq!(families(&selection), [(QueueKind::Graphics, 1, vec![0])]);
+        let error = QueueSelection::select_families(&flags, &[1, 1], &[false, false], &requests(1, 0, 0)).unwrap_err();
+        assert_eq!(error, VulkanError::NoPresentQueueFamily);
+        let error = QueueSelection::select_families(&[TRANSFER], &[1], &[true], &requests(0, 1, 0)).unwrap_err();
+        assert_eq!(error, VulkanError::NoSuitableQueueFamily(COMPUTE));
+    }
+}
@@ -1,23 +1,33 @@
-use std::sync::Arc;
+use std::{sync::{Arc, Mutex, PoisonError}, time::Duration};
 
 use ash::vk;
 use smallvec::SmallVec;
 
-use crate::{image::PipelineStageFlags, sync::Semaphore};
+use crate::{commands::{ExecutableCommandBuffer, RecordedCommandBuffer, TrackedResource}, error::VulkanError, image::PipelineStageFlags, sync::{Fence, FenceWait, Semaphore}};
 
 pub struct Submission {
     pub(crate) wait_semaphores: Vec<Arc<Semaphore>>,
     pub(crate) command_buffers: Vec<vk::CommandBuffer>,
     pub(crate) signal_semaphores: Vec<Arc<Semaphore>>,
+    pub(crate) resources: Vec<TrackedResource>,
 }
 
 impl Submission {
     pub fn new() -> Self {
-        Self { wait_semaphores: vec![], command_buffers: vec![], signal_semaphores: vec![] }
+        Self { wait_semaphores: vec![], command_buffers: vec![], signal_semaphores: vec![], resources: vec![] }
     }
     pub fn add_command_buffer(&mut self, command_buffer: vk::CommandBuffer) {
         self.command_buffers.push(command_buffer);
     }
+    /// adds the command buffer along with the resources it retains.
+    pub fn add_executable(&mut self, command_buffer: ExecutableCommandBuffer<'_>) {
+        self.command_buffers.push(command_buffer.get_command_buffer());
+        self.resources.extend(command_buffer.allocation.retained_resources());
+    }
+    pub fn add_recorded(&mut self, command_buffer: RecordedCommandBuffer<'_>) {
+        self.command_buffers.push(command_buffer.get_command_buffer());
+        self.resources.extend(command_buffer.resources);
+    }
     pub fn add_wait_semaphore(&mut self, wait: Arc<Semaphore>) {
         self.wait_semaphores.push(wait.clone());
     }
@@ -65,3 +75,112 @@ pub struct SubmissionCache {
     pub(crate) waits: SmallVec<[SmallVec<[vk::Semaphore; 4]>; 4]>, 
     pub(crate) signals: SmallVec<[SmallVec<[vk::Semaphore; 4]>; 4]>
 }
+
+/// What a submission retained, held until its fence signals.
+struct RetainedSubmission {
+    fence: Arc<Fence>,
+    resources: Vec<TrackedResource>,
+    semaphores: Vec<Arc<Semaphore>>,
+}
+impl RetainedSubmission {
+    fn is_complete(&self) -> bool {
+        // a lost device won't ever signal, there's nothing left to protect.
+        self.fence.status(0).unwrap_or(true)
+    }
+}
+
+/// Submissions whose guards were dropped before the GPU finished them. The
+/// queue releases them as their fences signal, and whoever drops the list
+/// last waits for the rest.
+#[derive(Default)]
+pub(crate) struct RetiredSubmissions {
+    pending: Mutex<Vec<RetainedSubmission>>,
+}
+impl RetiredSubmissions {
+    fn retire(&self, submission: RetainedSubmission) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        pending.retain(|submission| !submission.is_complete());
+        pending.push(submission);
+    }
+    /// releases the resources of every retired submission which completed.
+    pub(crate) fn collect(&self) {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).retain(|submission| !submission.is_complete());
+    }
+}
+impl Drop for RetiredSubmissions {
+    fn drop(&mut self) {
+        for submission in self.pending.get_mut().unwrap_or_else(PoisonError::into_inner).iter() {
+            let _ = submission.fence.wait_max();
+        }
+    }
+}
+
+/// Keeps the resources and semaphores of a submission alive until its fence
+/// signals. Dropping the guard never blocks: if the GPU isn't done yet, the
+/// queue takes over what it retained and releases it once a later submission
+/// finds the fence signaled.
+#[must_use = "dropping the guard hands its resources to the queue, wait on it to know when the submission completed"]
+pub struct SubmissionGuard {
+    retained: Option<RetainedSubmission>,
+    retired: Arc<RetiredSubmissions>,
+}
+impl SubmissionGuard {
+    pub(crate) fn new(fence: Fence, submit: &[&Submission], retired: Arc<RetiredSubmissions>) -> Self {
+        let retained = RetainedSubmission {
+            fence: Arc::new(fence),
+            resources: submit.iter().flat_map(|submission| submission.resources.iter().cloned()).collect(),
+            semaphores: submit.iter()
+                .flat_map(|submission| submission.wait_semaphores.iter().chain(submission.signal_semaphores.iter()).cloned())
+                .collect(),
+        };
+        Self { retained: Some(retained), retired }
+    }
+    #[inline]
+    fn retained(&self) -> &RetainedSubmission {
+        // only taken by drop and release.
+        self.retained.as_ref().unwrap()
+    }
+    #[inline]
+    pub fn fence(&self) -> &Fence {
+        &self.retained().fence
+    }
+    /// a future resolving once the submission completes, the resources are
+    /// released when the guard is dropped.
+    pub fn wait_async(&self) -> FenceWait {
+        self.retained().fence.wait_async()
+    }
+    pub fn is_complete(&self) -> bool {
+        self.fence().status(0).unwrap_or(false)
+    }
+    pub fn wait(&self, timeout: Duration) -> Result<(), VulkanError> {
+        self.fence().wait(timeout)
+    }
+    /// hands what the submission retained to the queue, which releases it
+    /// once the fence signals, and returns a future for that fence.
+    pub(crate) fn into_wait(mut self) -> FenceWait {
+        let retained = self.retained.take().unwrap();
+        let wait = retained.fence.wait_async();
+        if !retained.is_complete() {
+            self.retired.retire(retained);
+        }
+        wait
+    }
+    /// waits for the submission and releases what it retained.
+    pub fn release(mut self) -> Result<(), VulkanError> {
+        let retained = self.retained.take().unwrap();
+        if let Err(err) = retained.fence.wait_max() {
+            self.retired.retire(retained);
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+impl Drop for SubmissionGuard {
+    fn drop(&mut self) {
+        if let Some(retained) = self.retained.take() {
+            if !retained.is_complete() {
+                self.retired.retire(retained);
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{commands::{CommandBufferLevel, CommandBufferResetFlags, CommandBufferUsageFlags, CommandPool, CommandPoolAllocation, CommandPoolCreateFlags, CommandRecorder}, device::LogicalDevice, error::VulkanError, queue::Queue, swapchain::Swapchain, sync::{Fence, Semaphore}};
+
+use super::{Framebuffer, RenderPass};
+
+/// called with the new swapchain after the loop recreates it.
+type RecreateCallback = Box<dyn FnMut(&Arc<Swapchain>)>;
+
+struct FrameResources {
+    image_available: Semaphore,
+    in_flight: Fence,
+    command_buffer: CommandPoolAllocation,
+    /// the deletion point closed when this frame was last submitted.
+    submitted: Option<u64>,
+}
+
+/// What a frame records into, handed to the closure given to [`FrameLoop::frame`].
+pub struct Frame<'a> {
+    /// the frame's command buffer, ended and submitted once the closure returns.
+    pub recorder: CommandRecorder<'a>,
+    pub swapchain: &'a Arc<Swapchain>,
+    /// the framebuffer for the acquired image, when the loop has a render pass.
+    pub framebuffer: Option<&'a Arc<Framebuffer>>,
+    /// the swapchain image being rendered to.
+    pub image_index: u32,
+    /// which of the frames in flight is being recorded.
+    pub frame_index: usize,
+}
+
+/// Owns everything needed to keep `N` frames in flight on a swapchain: an
+/// acquire semaphore, fence and command buffer per frame and a present
+/// semaphore per swapchain image. The swapchain, and the framebuffers made
+/// from it, are recreated whenever presentation reports them out of date.
+pub struct FrameLoop {
+    device: Arc<LogicalDevice>,
+    queue: Arc<Queue>,
+    swapchain: Arc<Swapchain>,
+    render_pass: Option<Arc<RenderPass>>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+    render_finished: Vec<Semaphore>,
+    frames: Vec<FrameResources>,
+    on_recreate: Option<RecreateCallback>,
+    extent: [u32; 2],
+    needs_recreate: bool,
+    current: usize,
+    // declared last so the command buffers above are freed first.
+    pool: CommandPool,
+}
+
+impl FrameLoop {
+    pub fn new(queue: Arc<Queue>, swapchain: Arc<Swapchain>, frames_in_flight: usize) -> Result<Self, VulkanError> {
+        let device = queue.device();
+        let pool = CommandPool::new(device.clone(), CommandPoolCreateFlags::RESET_COMMAND_BUFFER, queue.family_index())?;
+        let command_buffers = unsafe { pool.allocate_command_buffers(CommandBufferLevel::PRIMARY, frames_in_flight as u32)? };
+        let frames = command_buffers.map(|command_buffer| FrameResources {
+            image_available: Semaphore::new(device.clone()),
+            in_flight: Fence::new(device.clone(), true),
+            command_buffer,
+            submitted: None,
+        }).collect();
+        let render_finished = (0..swapchain.image_count()).map(|_| Semaphore::new(device.clone())).collect();
+        Ok(Self {
+            extent: swapchain.extent(),
+            device,
+            queue,
+            swapchain,
+            render_pass: None,
+            framebuffers: vec![],
+            render_finished,
+            frames,
+            on_recreate: None,
+            needs_recreate: false,
+            current: 0,
+            pool,
+        })
+    }
+    /// creates a framebuffer per swapchain image for `render_pass`, rebuilt
+    /// along with the swapchain.
+    pub fn with_render_pass(mut self, render_pass: Arc<RenderPass>) -> Self {
+        self.framebuffers = Framebuffer::from_swapchain(self.swapchain.clone(), render_pass.clone());
+        self.render_pass = Some(render_pass);
+        self
+    }
+    /// called after every swapchain recreation, for resources that depend on
+    /// the swapchain extent such as depth buffers.
+    pub fn on_recreate(mut self, callback: impl FnMut(&Arc<Swapchain>) + 'static) -> Self {
+        self.on_recreate = Some(Box::new(callback));
+        self
+    }
+    /// the window was resized, the swapchain is recreated before the next frame.
+    pub fn resize(&mut self, extent: [u32; 2]) {
+        self.extent = extent;
+        self.needs_recreate = true;
+    }
+    #[inline]
+    pub fn swapchain(&self) -> &Arc<Swapchain> {
+        &self.swapchain
+    }
+    #[inline]
+    pub fn framebuffers(&self) -> &[Arc<Framebuffer>] {
+        &self.framebuffers
+    }
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+    fn recreate(&mut self) -> Result<(), VulkanError> {
+        self.device.wait()?;
+        self.swapchain = self.swapchain.recreate(self.extent)?;
+        if let Some(render_pass) = &self.render_pass {
+            self.framebuffers = Framebuffer::from_swapchain(self.swapchain.clone(), render_pass.clone());
+        }
+        if self.render_finished.len() != self.swapchain.image_count() {
+            self.render_finished = (0..self.swapchain.image_count()).map(|_| Semaphore::new(self.device.clone())).collect();
+        }
+        if let Some(callback) = &mut self.on_recreate {
+            callback(&self.swapchain);
+        }
+        self.needs_recreate = false;
+        Ok(())
+    }
+    /// Waits for the oldest frame in flight, acquires an image, lets `record`
+    /// fill the frame's command buffer, then submits and presents it. Returns
+    /// `None` when the swapchain had to be recreated before anything could be
+    /// recorded; the caller should simply try again next frame.
+    pub fn frame<R>(&mut self, record: impl FnOnce(&mut Frame) -> R) -> Result<Option<R>, VulkanError> {
+        self.frames[self.current].in_flight.wait_max()?;
+        if let Some(submitted) = self.frames[self.current].submitted {
+            self.device.collect_deletions(submitted);
+        }
+        if self.needs_recreate {
+            self.recreate()?;
+        }
+        let frame = &mut self.frames[self.current];
+        match self.swapchain.next_image(Some(&frame.image_available), None) {
+            Ok(suboptimal) => self.needs_recreate |= suboptimal,
+            Err(VulkanError::OutOfDate) => {
+                self.recreate()?;
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        }
+        let image_index = self.swapchain.get_image_index();
+        frame.command_buffer.reset(CommandBufferResetFlags::empty())?;
+        let mut recording = Frame {
+            recorder: frame.command_buffer.record(CommandBufferUsageFlags::ONE_TIME_SUBMIT)?,
+            swapchain: &self.swapchain,
+            framebuffer: self.framebuffers.get(image_index as usize),
+            image_index,
+            frame_index: self.current,
+        };
+        let output = record(&mut recording);
+        let command_buffer = recording.recorder.end()?.get_command_buffer();
+
+        let render_finished = self.render_finished[image_index as usize].get();
+        let wait_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
+        let submit = vk::SubmitInfo {
+            wait_semaphore_count: 1,
+            p_wait_semaphores: &frame.image_available.get(),
+            p_wait_dst_stage_mask: &wait_stage,
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer,
+            signal_semaphore_count: 1,
+            p_signal_semaphores: &render_finished,
+            ..Default::default()
+        };
+        // reset only now so every early return leaves the fence signaled for the next wait.
+        frame.in_flight.reset();
+        if let Err(err) = self.queue.submit_raw(&[submit], &frame.in_flight) {
+            frame.in_flight = Fence::new(self.device.clone(), true);
+            return Err(err);
+        }
+        self.frames[self.current].submitted = Some(self.device.advance_deletion_point());
+        match self.swapchain.present(&self.queue, &[render_finished], None) {
+            Ok(suboptimal) => self.needs_recreate |= suboptimal,
+            Err(VulkanError::OutOfDate) | Err(VulkanError::Suboptimal) => self.needs_recreate = true,
+            Err(err) => return Err(err),
+        }
+        self.current = (self.current + 1) % self.frames.len();
+        Ok(Some(output))
+    }
+}
+
+impl Drop for FrameLoop {
+    fn drop(&mut self) {
+        let _ = self.device.wait();
+    }
+}
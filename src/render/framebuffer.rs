@@ -46,6 +46,10 @@ impl Framebuffer {
     pub fn handle(&self) -> vk::Framebuffer {
         self.framebuffer
     }
+    #[inline]
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
 }
 
 impl Drop for Framebuffer {
@@ -1,5 +1,7 @@
 mod framebuffer;
 mod renderpass;
+mod frame;
 
 pub use framebuffer::*;
-pub use renderpass::*;
\ No newline at end of file
+pub use renderpass::*;
+pub use frame::*;
\ No newline at end of file
@@ -290,6 +290,27 @@ impl ColorSpaceKHR {
 }
 impl ColorSpaceKHR {
     pub const SRGB_NONLINEAR: Self = Self(0);
+    pub const DISPLAY_P3_NONLINEAR_EXT: Self = Self(1_000_104_001);
+    pub const EXTENDED_SRGB_LINEAR_EXT: Self = Self(1_000_104_002);
+    pub const DISPLAY_P3_LINEAR_EXT: Self = Self(1_000_104_003);
+    pub const DCI_P3_NONLINEAR_EXT: Self = Self(1_000_104_004);
+    pub const BT709_LINEAR_EXT: Self = Self(1_000_104_005);
+    pub const BT709_NONLINEAR_EXT: Self = Self(1_000_104_006);
+    pub const BT2020_LINEAR_EXT: Self = Self(1_000_104_007);
+    pub const HDR10_ST2084_EXT: Self = Self(1_000_104_008);
+    pub const DOLBYVISION_EXT: Self = Self(1_000_104_009);
+    pub const HDR10_HLG_EXT: Self = Self(1_000_104_010);
+    pub const ADOBERGB_LINEAR_EXT: Self = Self(1_000_104_011);
+    pub const ADOBERGB_NONLINEAR_EXT: Self = Self(1_000_104_012);
+    pub const PASS_THROUGH_EXT: Self = Self(1_000_104_013);
+    pub const EXTENDED_SRGB_NONLINEAR_EXT: Self = Self(1_000_104_014);
+    pub const DISPLAY_NATIVE_AMD: Self = Self(1_000_213_000);
+}
+impl ColorSpaceKHR {
+    /// color spaces with a high dynamic range transfer function or extended range.
+    pub const fn is_hdr(self) -> bool {
+        matches!(self, Self::EXTENDED_SRGB_LINEAR_EXT | Self::EXTENDED_SRGB_NONLINEAR_EXT | Self::HDR10_ST2084_EXT | Self::HDR10_HLG_EXT | Self::DOLBYVISION_EXT | Self::BT2020_LINEAR_EXT)
+    }
 }
 
 #[repr(transparent)]
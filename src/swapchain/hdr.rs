@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{device::LogicalDevice, error::VulkanError};
+
+use super::{ColorSpaceKHR, Format, PresentModeKHR};
+
+/// The formats and color spaces used for each kind of HDR or wide gamut
+/// output, in the order they are preferred.
+pub const HDR10_FORMATS: [(Format, ColorSpaceKHR); 2] = [
+    (Format::A2B10G10R10_UNORM_PACK32, ColorSpaceKHR::HDR10_ST2084_EXT),
+    (Format::A2R10G10B10_UNORM_PACK32, ColorSpaceKHR::HDR10_ST2084_EXT),
+];
+pub const SCRGB_FORMATS: [(Format, ColorSpaceKHR); 1] = [
+    (Format::R16G16B16A16_SFLOAT, ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT),
+];
+pub const DISPLAY_P3_FORMATS: [(Format, ColorSpaceKHR); 4] = [
+    (Format::A2B10G10R10_UNORM_PACK32, ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT),
+    (Format::A2R10G10B10_UNORM_PACK32, ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT),
+    (Format::B8G8R8A8_UNORM, ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT),
+    (Format::R8G8B8A8_UNORM, ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT),
+];
+
+#[repr(C)]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Copy, Clone, Default, PartialEq)]
+#[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkHdrMetadataEXT.html>"]
+pub struct HdrMetadata {
+    /// CIE 1931 xy chromaticity of the mastering display's primaries.
+    pub display_primary_red: [f32; 2],
+    pub display_primary_green: [f32; 2],
+    pub display_primary_blue: [f32; 2],
+    pub white_point: [f32; 2],
+    /// in nits.
+    pub max_luminance: f32,
+    pub min_luminance: f32,
+    pub max_content_light_level: f32,
+    pub max_frame_average_light_level: f32,
+}
+impl HdrMetadata {
+    /// BT.2020 primaries with a D65 white point, as used by HDR10 content.
+    pub const fn bt2020(max_luminance: f32, min_luminance: f32, max_content_light_level: f32, max_frame_average_light_level: f32) -> Self {
+        Self {
+            display_primary_red: [0.708, 0.292],
+            display_primary_green: [0.170, 0.797],
+            display_primary_blue: [0.131, 0.046],
+            white_point: [0.3127, 0.3290],
+            max_luminance,
+            min_luminance,
+            max_content_light_level,
+            max_frame_average_light_level,
+        }
+    }
+}
+impl From<&HdrMetadata> for vk::HdrMetadataEXT {
+    fn from(value: &HdrMetadata) -> Self {
+        let xy = |[x, y]: [f32; 2]| vk::XYColorEXT { x, y };
+        Self {
+            display_primary_red: xy(value.display_primary_red),
+            display_primary_green: xy(value.display_primary_green),
+            display_primary_blue: xy(value.display_primary_blue),
+            white_point: xy(value.white_point),
+            max_luminance: value.max_luminance,
+            min_luminance: value.min_luminance,
+            max_content_light_level: value.max_content_light_level,
+            max_frame_average_light_level: value.max_frame_average_light_level,
+            ..Default::default()
+        }
+    }
+}
+
+/// Everything a surface can present, for deciding between SDR, HDR and wide
+/// gamut output. Color spaces other than sRGB are only reported when the
+/// instance enables `VK_EXT_swapchain_colorspace`.
+pub struct SurfaceSupport {
+    pub formats: Vec<(Format, ColorSpaceKHR)>,
+    pub present_modes: Vec<PresentModeKHR>,
+    pub capabilities: vk::SurfaceCapabilitiesKHR,
+    /// whether the device enabled `VK_EXT_hdr_metadata`.
+    pub hdr_metadata: bool,
+}
+impl SurfaceSupport {
+    pub fn query(device: &Arc<LogicalDevice>, surface: vk::SurfaceKHR) -> Result<Self, VulkanError> {
+        let physical_device = device.physical_device.clone();
+        let capabilities = device.instance.get_physical_device_surface_capabilities(physical_device.clone(), surface)?;
+        let formats = device.instance.get_physical_device_surface_formats(physical_device.clone(), surface)?
+            .iter()
+            .map(|format| (Format::from_raw(format.format.as_raw()), ColorSpaceKHR::from_raw(format.color_space.as_raw())))
+            .collect();
+        let present_modes = device.instance.get_physical_device_surface_present_modes(physical_device, surface)?
+            .iter()
+            .map(|mode| PresentModeKHR::from_raw(mode.as_raw()))
+            .collect();
+        Ok(Self { formats, present_modes, capabilities, hdr_metadata: device.enabled_extensions.ext_hdr_metadata })
+    }
+    pub fn supports(&self, format: Format, color_space: ColorSpaceKHR) -> bool {
+        self.formats.contains(&(format, color_space))
+    }
+    fn first_of(&self, candidates: &[(Format, ColorSpaceKHR)]) -> Option<(Format, ColorSpaceKHR)> {
+        candidates.iter().copied().find(|(format, color_space)| self.supports(*format, *color_space))
+    }
+    /// the preferred 10 bit HDR10 (ST.2084) format, if any.
+    pub fn hdr10(&self) -> Option<(Format, ColorSpaceKHR)> {
+        self.first_of(&HDR10_FORMATS)
+    }
+    /// the FP16 extended linear sRGB format, if supported.
+    pub fn scrgb(&self) -> Option<(Format, ColorSpaceKHR)> {
+        self.first_of(&SCRGB_FORMATS)
+    }
+    pub fn display_p3(&self) -> Option<(Format, ColorSpaceKHR)> {
+        self.first_of(&DISPLAY_P3_FORMATS)
+    }
+    pub fn is_hdr_capable(&self) -> bool {
+        self.formats.iter().any(|(_, color_space)| color_space.is_hdr())
+    }
+}
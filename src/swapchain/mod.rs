@@ -1,12 +1,16 @@
-use std::{cell::Cell, mem::MaybeUninit, sync::Arc};
+use std::{mem::MaybeUninit, sync::{atomic::{AtomicU32, Ordering}, Arc, Mutex, MutexGuard, PoisonError}, time::Duration};
 
 use ash::{prelude::VkResult, vk::{self, ComponentMapping, ComponentSwizzle, SurfaceKHR}};
 
 mod definitions;
+mod hdr;
+mod present;
 pub mod surface;
 pub use definitions::*;
+pub use hdr::*;
+pub use present::*;
 
-use crate::{device::LogicalDevice, error::VulkanError, image::ImageUsageFlags, sync::{Fence, Semaphore}};
+use crate::{device::LogicalDevice, error::VulkanError, image::ImageUsageFlags, queue::Queue, sync::{Fence, Semaphore}, PNext};
 #[derive(Default, Clone)]
 pub struct SwapchainBuilder {
     pub sharing_queues: Vec<u32>,
@@ -19,9 +23,20 @@ pub struct SwapchainBuilder {
     pub image_usage: ImageUsageFlags,
     pub pre_transform: SurfaceTransformFlagsKHR,
     pub composite_alpha: CompositeAlphaFlagsKHR,
-    pub present_mode: PresentModeKHR,
     pub clipped: bool,
-    pub old: Option<vk::SwapchainKHR>
+    pub old: Option<vk::SwapchainKHR>,
+    pub preferred_formats: Vec<(Format, ColorSpaceKHR)>,
+    pub preferred_present_modes: Vec<PresentModeKHR>,
+}
+/// What [`SwapchainBuilder`] settled on after comparing its preferences with
+/// what the surface supports.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SwapchainChoices {
+    pub format: Format,
+    pub color_space: ColorSpaceKHR,
+    pub present_mode: PresentModeKHR,
+    pub extent: [u32; 2],
+    pub image_count: u32,
 }
 pub(crate) unsafe fn read_into_uninitialized_vector<N: Copy + Default + TryInto<usize>, T>(
     f: impl Fn(&mut N, *mut T) -> vk::Result,
@@ -56,11 +71,45 @@ impl SwapchainBuilder {
             image_usage: ImageUsageFlags::empty(),
             pre_transform: SurfaceTransformFlagsKHR::empty(),
             composite_alpha: CompositeAlphaFlagsKHR::OPAQUE,
-            present_mode: PresentModeKHR::FIFO,
             clipped: false,
-            old: None
+            old: None,
+            preferred_formats: vec![],
+            preferred_present_modes: vec![],
         }
     }
+    /// present modes tried when none are preferred, lowest latency first.
+    pub const DEFAULT_PRESENT_MODES: [PresentModeKHR; 3] = [PresentModeKHR::MAILBOX, PresentModeKHR::IMMEDIATE, PresentModeKHR::FIFO];
+    /// adds a format to try, in the order given. The format from
+    /// [`Self::set_format`] is tried after every preferred one.
+    pub fn prefer_format(mut self, image_format: Format, image_color_space: ColorSpaceKHR) -> Self {
+        self.preferred_formats.push((image_format, image_color_space));
+        self
+    }
+    /// adds a present mode to try, in the order given. Without any,
+    /// [`Self::DEFAULT_PRESENT_MODES`] is used. FIFO is always available and
+    /// is the last resort.
+    pub fn prefer_present_mode(mut self, present_mode: PresentModeKHR) -> Self {
+        self.preferred_present_modes.push(present_mode);
+        self
+    }
+    /// prefers HDR10 and then scRGB output, keeping any formats already
+    /// preferred and the SDR format as fallbacks. Needs `VK_EXT_swapchain_colorspace`
+    /// on the instance for the surface to report HDR color spaces.
+    pub fn prefer_hdr(mut self) -> Self {
+        self.preferred_formats.extend(HDR10_FORMATS);
+        self.preferred_formats.extend(SCRGB_FORMATS);
+        self
+    }
+    /// prefers Display-P3 output, see [`Self::prefer_hdr`].
+    pub fn prefer_wide_gamut(mut self) -> Self {
+        self.preferred_formats.extend(DISPLAY_P3_FORMATS);
+        self
+    }
+    /// only uses `present_mode`, falling back to FIFO if the surface does not support it.
+    pub fn set_present_mode(mut self, present_mode: PresentModeKHR) -> Self {
+        self.preferred_present_modes = vec![present_mode];
+        self
+    }
     pub fn old_swapchain(mut self, old: vk::SwapchainKHR) -> Self {
         self.old = Some(old);
         self
@@ -95,15 +144,65 @@ impl SwapchainBuilder {
         if capabilities.current_extent.width != std::u32::MAX {
             return [capabilities.current_extent.width, capabilities.current_extent.height];
         }
-        let mut actual_extent = extent;
-        actual_extent[0] = std::cmp::max(
-			capabilities.min_image_extent.width,
-			std::cmp::min(capabilities.min_image_extent.width, actual_extent[0]));
-            actual_extent[1] = std::cmp::max(
-			capabilities.min_image_extent.height,
-			std::cmp::min(capabilities.min_image_extent.height, actual_extent[1])
-        );
-        actual_extent
+        [
+            extent[0].clamp(capabilities.min_image_extent.width, capabilities.max_image_extent.width),
+            extent[1].clamp(capabilities.min_image_extent.height, capabilities.max_image_extent.height),
+        ]
+    }
+    /// one more image than the minimum unless a count was set, within what the surface allows.
+    fn choose_image_count(capabilities: &vk::SurfaceCapabilitiesKHR, requested: u32) -> u32 {
+        let max = if capabilities.max_image_count == 0 { u32::MAX } else { capabilities.max_image_count };
+        let requested = if requested == 0 { capabilities.min_image_count + 1 } else { requested };
+        requested.clamp(capabilities.min_image_count, max)
+    }
+    fn choose_format(&self, surface_formats: &[vk::SurfaceFormatKHR]) -> (Format, ColorSpaceKHR) {
+        let candidates = self.preferred_formats.iter().copied().chain(std::iter::once((self.image_format, self.image_color_space)));
+        // a single undefined format means the surface has no preference.
+        if let [only] = surface_formats {
+            if only.format == vk::Format::UNDEFINED {
+                return self.preferred_formats.first().copied().unwrap_or((self.image_format, self.image_color_space));
+            }
+        }
+        for (format, color_space) in candidates {
+            let wanted = vk::SurfaceFormatKHR { format: vk::Format::from_raw(format.0), color_space: vk::ColorSpaceKHR::from_raw(color_space.0) };
+            if Self::format_is_present(wanted, surface_formats) {
+                return (format, color_space);
+            }
+        }
+        surface_formats.first()
+            .map(|first| (Format(first.format.as_raw()), ColorSpaceKHR(first.color_space.as_raw())))
+            .unwrap_or((self.image_format, self.image_color_space))
+    }
+    fn choose_present_mode(&self, present_modes: &[vk::PresentModeKHR]) -> PresentModeKHR {
+        let candidates = if self.preferred_present_modes.is_empty() {
+            &Self::DEFAULT_PRESENT_MODES[..]
+        } else {
+            &self.preferred_present_modes[..]
+        };
+        candidates.iter()
+            .copied()
+            .find(|mode| present_modes.contains(&vk::PresentModeKHR::from_raw(mode.0)))
+            .unwrap_or(PresentModeKHR::FIFO)
+    }
+    /// queries the surface and resolves every preference without creating the swapchain.
+    pub fn negotiate(&self, device: &Arc<LogicalDevice>, surface: SurfaceKHR) -> Result<SwapchainChoices, VulkanError> {
+        Ok(self.query_surface(device, surface)?.0)
+    }
+    /// the choices along with the capabilities they were made from.
+    fn query_surface(&self, device: &Arc<LogicalDevice>, surface: SurfaceKHR) -> Result<(SwapchainChoices, vk::SurfaceCapabilitiesKHR), VulkanError> {
+        let physical_device = device.physical_device.clone();
+        let capabilities = device.instance.get_physical_device_surface_capabilities(physical_device.clone(), surface)?;
+        let formats = device.instance.get_physical_device_surface_formats(physical_device.clone(), surface)?;
+        let present_modes = device.instance.get_physical_device_surface_present_modes(physical_device, surface)?;
+        let (format, color_space) = self.choose_format(&formats);
+        let choices = SwapchainChoices {
+            format,
+            color_space,
+            present_mode: self.choose_present_mode(&present_modes),
+            extent: Self::choose_extent(&capabilities, self.image_extent),
+            image_count: Self::choose_image_count(&capabilities, self.min_image_count),
+        };
+        Ok((choices, capabilities))
     }
     fn format_is_present(format: vk::SurfaceFormatKHR, surface_formats: &[vk::SurfaceFormatKHR]) -> bool {
         for f in surface_formats {
@@ -142,17 +241,9 @@ impl SwapchainBuilder {
         views
     }
     pub fn build(self, device: Arc<LogicalDevice>, surface: SurfaceKHR) -> Result<Arc<Swapchain>, VulkanError> {
-        let surface_capabilities: vk::SurfaceCapabilitiesKHR = device.instance.get_physical_device_surface_capabilities(
-            device.physical_device.clone(), 
-            surface
-        )?;
-        let image_extent = Self::choose_extent(&surface_capabilities, self.image_extent);
-        let min_image_count = if !(self.min_image_count > surface_capabilities.min_image_count && 
-            self.min_image_count < surface_capabilities.max_image_count) {
-                surface_capabilities.min_image_count
-        } else {
-            self.min_image_count
-        };
+        let (choices, surface_capabilities) = self.query_surface(&device, surface)?;
+        let image_extent = choices.extent;
+        let min_image_count = choices.image_count;
         let image_sharing_mode = if self.sharing_queues.len() > 1 {
             vk::SharingMode::CONCURRENT
         } else {
@@ -166,16 +257,16 @@ impl SwapchainBuilder {
             composite_alpha: vk::CompositeAlphaFlagsKHR::from_raw(self.composite_alpha.0),
             flags: vk::SwapchainCreateFlagsKHR::from_raw(self.flags.0),
             image_array_layers,
-            image_color_space: vk::ColorSpaceKHR::from_raw(self.image_color_space.0),
+            image_color_space: vk::ColorSpaceKHR::from_raw(choices.color_space.0),
             image_extent: vk::Extent2D { width: image_extent[0], height: image_extent[1] },
-            image_format: vk::Format::from_raw(self.image_format.0),
+            image_format: vk::Format::from_raw(choices.format.0),
             image_sharing_mode: image_sharing_mode,
             image_usage: vk::ImageUsageFlags::from_raw(self.image_usage.0),
             min_image_count: min_image_count,
             p_queue_family_indices: self.sharing_queues.as_ptr(),
             queue_family_index_count: self.sharing_queues.len() as u32,
             pre_transform,
-            present_mode: vk::PresentModeKHR::from_raw(self.present_mode.0),
+            present_mode: vk::PresentModeKHR::from_raw(choices.present_mode.0),
             old_swapchain: self.old.map_or(vk::SwapchainKHR::null(), |v|{v}),
             ..Default::default()
         };
@@ -208,37 +299,36 @@ impl SwapchainBuilder {
         //     ).result().map_err(VulkanError::from)?;
         //     images.set_len(count as usize) 
         // };
-        let views = Self::create_image_views(min_image_count, &images, vk::Format::from_raw(self.image_format.0), &device);
+        let views = Self::create_image_views(min_image_count, &images, vk::Format::from_raw(choices.format.0), &device);
         Ok(Arc::new(Swapchain {  
             handle,
             surface,
             device,
-            image_idx: Cell::new(0),
+            image_idx: AtomicU32::new(0),
             images,
             views,
-            image_format: self.image_format,
+            image_format: choices.format,
             image_usage: self.image_usage,
-            color_space: self.image_color_space,
+            color_space: choices.color_space,
             extent: image_extent,
-            cache: SwapchainCached { present_info: vk::PresentInfoKHR::default() },
+            choices,
+            timings: Mutex::new(PresentTimings::default()),
         }))
     }
 }
-pub struct SwapchainCached {
-    present_info: vk::PresentInfoKHR
-}
 pub struct Swapchain {
     handle: vk::SwapchainKHR,
     surface: vk::SurfaceKHR,
     device: Arc<LogicalDevice>,
-    image_idx: Cell<u32>,
+    image_idx: AtomicU32,
     images: Vec<vk::Image>,
     views: Vec<vk::ImageView>,
     image_format: Format,
     image_usage: ImageUsageFlags,
     color_space: ColorSpaceKHR,
     extent: [u32; 2],
-    cache: SwapchainCached,
+    choices: SwapchainChoices,
+    timings: Mutex<PresentTimings>,
 }
 
 impl Swapchain {
@@ -260,7 +350,7 @@ impl Swapchain {
                 fence.map(|v| v.get() ).unwrap_or_default(),
                 &mut idx
             );
-            self.image_idx.set(idx);
+            self.image_idx.store(idx, Ordering::Release);
             return match err_code {
                 vk::Result::SUCCESS => Ok(false),
                 vk::Result::SUBOPTIMAL_KHR => Ok(true),
@@ -271,7 +361,7 @@ impl Swapchain {
     pub fn get_images(&self) -> &Vec<vk::Image> { 
         &self.images
     }
-    pub fn get_image_index(&self) -> u32 { self.image_idx.get() }
+    pub fn get_image_index(&self) -> u32 { self.image_idx.load(Ordering::Acquire) }
     pub fn recreate(&self, extent: [u32; 2]) -> Result<Arc<Swapchain>, VulkanError> {
         self.device.wait();
         if self.handle == vk::SwapchainKHR::null() {
@@ -280,6 +370,8 @@ impl Swapchain {
                 .set_extent(extent)
                 .set_image_usage(self.image_usage)
                 .set_format(self.image_format, self.color_space)
+                .set_present_mode(self.choices.present_mode)
+                .set_image_count(self.choices.image_count)
                 .build(self.device.clone(), self.surface)
         } else {
             SwapchainBuilder::new()
@@ -288,14 +380,40 @@ impl Swapchain {
                 .set_image_usage(self.image_usage)
                 .old_swapchain(self.handle)
                 .set_format(self.image_format, self.color_space)
+                .set_present_mode(self.choices.present_mode)
+                .set_image_count(self.choices.image_count)
                 .build(self.device.clone(), self.surface)
         }
     }
-    pub fn present(&self, queue: vk::Queue, wait: &[vk::Semaphore]) -> Result<bool, VulkanError> {
+    /// presents the current image. `present_id` tags the present so it can be
+    /// waited on with [`Self::wait_for_present`], ids must increase with every
+    /// present and require the `present_id` feature, see
+    /// [`LogicalDeviceBuilder::enable_present_wait`](crate::device::LogicalDeviceBuilder::enable_present_wait).
+    /// the queue is locked while presenting, so this may be called while other
+    /// threads submit to the same queue.
+    pub fn present(&self, queue: &Queue, wait: &[vk::Semaphore], present_id: Option<u64>) -> Result<bool, VulkanError> {
         let swapchains = [self.handle];
-        let idx = self.image_idx.get();
-        
+        let idx = self.get_image_index();
+        let ids = present_id.map(|id| [id]);
+        let mut p_next = PNext::new();
+        let present_ids = match &ids {
+            Some(ids) => {
+                if !self.device.enabled_features.present_id {
+                    return Err(VulkanError::FeatureNotPresent(vec!["present_id"]));
+                }
+                Some(vk::PresentIdKHR {
+                    swapchain_count: 1,
+                    p_present_ids: ids.as_ptr(),
+                    ..Default::default()
+                })
+            }
+            None => None,
+        };
+        if let Some(present_ids) = &present_ids {
+            p_next.push_back(present_ids);
+        }
         let present_info = vk::PresentInfoKHR {
+            p_next: p_next.use_p_next(),
             wait_semaphore_count: wait.len() as u32,
             p_wait_semaphores: wait.as_ptr(),
             swapchain_count: 1,
@@ -303,13 +421,45 @@ impl Swapchain {
             p_image_indices: &idx as *const u32,
             ..Default::default()
         };
-        let err_code = unsafe { (self.device.fns.khr_swapchain.queue_present_khr)(queue, &present_info) };
+        let err_code = {
+            let queue = queue.lock();
+            unsafe { (self.device.fns.khr_swapchain.queue_present_khr)(queue.handle(), &present_info) }
+        };
+        if let (Some(id), vk::Result::SUCCESS | vk::Result::SUBOPTIMAL_KHR) = (present_id, err_code) {
+            self.timings().presented(id);
+        }
         match err_code {
             vk::Result::SUCCESS => Ok(false),
             vk::Result::SUBOPTIMAL_KHR => Ok(true),
             _ => Err(VulkanError::from(err_code)),
         }
     }
+    /// waits until the present tagged with `present_id` has been displayed,
+    /// returning `false` if `timeout` elapsed first. Requires the `present_wait` feature.
+    pub fn wait_for_present(&self, present_id: u64, timeout: Duration) -> Result<bool, VulkanError> {
+        if !self.device.enabled_features.present_wait {
+            return Err(VulkanError::FeatureNotPresent(vec!["present_wait"]));
+        }
+        let err_code = unsafe { (self.device.fns.khr_present_wait.wait_for_present_khr)(self.device.handle(), self.handle, present_id, timeout.as_nanos() as u64) };
+        match err_code {
+            vk::Result::SUCCESS | vk::Result::SUBOPTIMAL_KHR => {
+                self.timings().displayed(present_id);
+                Ok(true)
+            }
+            vk::Result::TIMEOUT => Ok(false),
+            _ => Err(VulkanError::from(err_code)),
+        }
+    }
+    /// latency and frame pacing of the presents waited on so far.
+    pub fn present_stats(&self) -> PresentStats {
+        self.timings().stats
+    }
+    pub fn reset_present_stats(&self) {
+        self.timings().stats = PresentStats::default();
+    }
+    fn timings(&self) -> MutexGuard<'_, PresentTimings> {
+        self.timings.lock().unwrap_or_else(PoisonError::into_inner)
+    }
     pub fn get_current_image(&self) -> vk::Image {
         self.images[self.get_image_index() as usize]
     }
@@ -331,6 +481,21 @@ impl Swapchain {
     #[inline]
     pub fn extent(&self) -> [u32; 2] { self.extent }
     #[inline]
+    pub fn is_hdr(&self) -> bool { self.color_space.is_hdr() }
+    /// sets the mastering display and content light levels of the images,
+    /// requires `VK_EXT_hdr_metadata` to be enabled on the device.
+    pub fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), VulkanError> {
+        if !self.device.enabled_extensions.ext_hdr_metadata {
+            return Err(VulkanError::ExtensionNotPresent(vec!["VK_EXT_hdr_metadata"]));
+        }
+        let metadata = vk::HdrMetadataEXT::from(metadata);
+        unsafe { (self.device.fns.ext_hdr_metadata.set_hdr_metadata_ext)(self.device.handle(), 1, &self.handle, &metadata) };
+        Ok(())
+    }
+    /// the format, present mode, extent and image count negotiated at creation.
+    #[inline]
+    pub fn choices(&self) -> SwapchainChoices { self.choices }
+    #[inline]
     pub fn width(&self) -> u32 { self.extent[0] }
     #[inline]
     pub fn height(&self) -> u32 { self.extent[1] }
@@ -339,7 +504,7 @@ impl Swapchain {
     #[inline]
     pub fn image_count(&self) -> usize { self.images.len() }
     #[inline]
-    pub fn image_index(&self) -> usize { self.image_idx.get() as usize }
+    pub fn image_index(&self) -> usize { self.get_image_index() as usize }
 }
 
 impl Drop for Swapchain {
@@ -350,3 +515,75 @@ impl Drop for Swapchain {
         unsafe { (self.device.fns.khr_swapchain.destroy_swapchain_khr)(self.device.handle(), self.handle, std::ptr::null()) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::{ColorSpaceKHR, Format, PresentModeKHR, Swapchain, SwapchainBuilder, HDR10_FORMATS};
+
+    fn capabilities(current: [u32; 2], min_count: u32, max_count: u32) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            current_extent: vk::Extent2D { width: current[0], height: current[1] },
+            min_image_extent: vk::Extent2D { width: 16, height: 16 },
+            max_image_extent: vk::Extent2D { width: 4096, height: 2048 },
+            min_image_count: min_count,
+            max_image_count: max_count,
+            ..Default::default()
+        }
+    }
+
+    fn surface_format(format: Format, color_space: ColorSpaceKHR) -> vk::SurfaceFormatKHR {
+        vk::SurfaceFormatKHR { format: vk::Format::from_raw(format.0), color_space: vk::ColorSpaceKHR::from_raw(color_space.0) }
+    }
+
+    #[test]
+    fn swapchains_can_be_shared_between_threads() {
+        fn shared<T: Send + Sync>() {}
+        shared::<Swapchain>();
+        shared::<crate::queue::Queue>();
+    }
+
+    #[test]
+    fn extent_follows_the_surface_unless_it_is_undefined() {
+        let fixed = capabilities([800, 600], 2, 3);
+        assert_eq!(SwapchainBuilder::choose_extent(&fixed, [1920, 1080]), [800, 600]);
+        let free = capabilities([u32::MAX, u32::MAX], 2, 3);
+        assert_eq!(SwapchainBuilder::choose_extent(&free, [1920, 1080]), [1920, 1080]);
+        assert_eq!(SwapchainBuilder::choose_extent(&free, [8192, 4]), [4096, 16]);
+    }
+
+    #[test]
+    fn image_count_is_one_above_the_minimum_within_limits() {
+        assert_eq!(SwapchainBuilder::choose_image_count(&capabilities([1, 1], 2, 3), 0), 3);
+        assert_eq!(SwapchainBuilder::choose_image_count(&capabilities([1, 1], 3, 3), 0), 3);
+        assert_eq!(SwapchainBuilder::choose_image_count(&capabilities([1, 1], 2, 0), 8), 8);
+        assert_eq!(SwapchainBuilder::choose_image_count(&capabilities([1, 1], 2, 4), 8), 4);
+        assert_eq!(SwapchainBuilder::choose_image_count(&capabilities([1, 1], 2, 4), 1), 2);
+    }
+
+    #[test]
+    fn formats_are_tried_in_preference_order() {
+        let sdr = (Format::B8G8R8A8_SRGB, ColorSpaceKHR::SRGB_NONLINEAR);
+        let builder = SwapchainBuilder::new().prefer_hdr().set_format(sdr.0, sdr.1);
+        let surface = [surface_format(sdr.0, sdr.1), surface_format(HDR10_FORMATS[1].0, HDR10_FORMATS[1].1)];
+        assert!(builder.choose_format(&surface) == HDR10_FORMATS[1]);
+        assert!(builder.choose_format(&surface[..1]) == sdr);
+        // nothing wanted is supported, the surface's first format is used.
+        let other = (Format::R16G16B16A16_SFLOAT, ColorSpaceKHR::SRGB_NONLINEAR);
+        assert!(builder.choose_format(&[surface_format(other.0, other.1)]) == other);
+        // a lone undefined format leaves the choice to us.
+        let undefined = vk::SurfaceFormatKHR { format: vk::Format::UNDEFINED, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR };
+        assert!(builder.choose_format(&[undefined]) == HDR10_FORMATS[0]);
+    }
+
+    #[test]
+    fn present_modes_fall_back_to_fifo() {
+        let supported = [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::IMMEDIATE];
+        assert!(SwapchainBuilder::new().choose_present_mode(&supported) == PresentModeKHR::IMMEDIATE);
+        let builder = SwapchainBuilder::new().set_present_mode(PresentModeKHR::MAILBOX);
+        assert!(builder.choose_present_mode(&supported) == PresentModeKHR::FIFO);
+        let builder = SwapchainBuilder::new().prefer_present_mode(PresentModeKHR::MAILBOX).prefer_present_mode(PresentModeKHR::IMMEDIATE);
+        assert!(builder.choose_present_mode(&supported) == PresentModeKHR::IMMEDIATE);
+    }
+}
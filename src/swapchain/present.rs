@@ -0,0 +1,154 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+/// Running minimum, maximum and average of a series of durations.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DurationStats {
+    pub samples: u64,
+    pub last: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    total: Duration,
+}
+impl DurationStats {
+    pub(crate) fn record(&mut self, sample: Duration) {
+        if self.samples == 0 {
+            self.min = sample;
+            self.max = sample;
+        } else {
+            self.min = self.min.min(sample);
+            self.max = self.max.max(sample);
+        }
+        self.samples += 1;
+        self.last = sample;
+        self.total += sample;
+    }
+    pub fn average(&self) -> Duration {
+        if self.samples == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.samples as f64)
+        }
+    }
+}
+
+/// Timings gathered from presents made with an id and later waited on with
+/// [`Swapchain::wait_for_present`](crate::swapchain::Swapchain::wait_for_present).
+///
+/// Both are measured when the wait returns on the CPU, not when the image
+/// reached the display, so they include the time the waiting thread took to
+/// wake up. A wait that starts after the image was already displayed records
+/// the time of the wait instead, so wait for presents soon after making them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PresentStats {
+    /// time from `present` being called until the wait for it returned, an
+    /// upper bound of the presentation latency.
+    pub latency: DurationStats,
+    /// time between images being displayed. When presents are skipped rather
+    /// than waited on, the time between two waits is split evenly over every
+    /// present displayed in between.
+    pub frame_interval: DurationStats,
+}
+
+#[derive(Default)]
+pub(crate) struct PresentTimings {
+    pending: VecDeque<(u64, Instant)>,
+    last_displayed: Option<Instant>,
+    pub(crate) stats: PresentStats,
+}
+impl PresentTimings {
+    /// ids older than the last few hundred presents are never going to be
+    /// waited on, so they are dropped to keep the queue bounded.
+    const MAX_PENDING: usize = 256;
+    pub(crate) fn presented(&mut self, id: u64) {
+        self.presented_at(id, Instant::now());
+    }
+    fn presented_at(&mut self, id: u64, at: Instant) {
+        if self.pending.len() == Self::MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((id, at));
+    }
+    pub(crate) fn displayed(&mut self, id: u64) {
+        self.displayed_at(id, Instant::now());
+    }
+    /// once present `id` is displayed every earlier id is as well, so they
+    /// count towards the frames shown since the last wait.
+    fn displayed_at(&mut self, id: u64, now: Instant) {
+        let mut submitted = None;
+        let mut frames = 0u32;
+        while let Some(&(pending, at)) = self.pending.front() {
+            if pending > id {
+                break;
+            }
+            if pending == id {
+                submitted = Some(at);
+            }
+            frames += 1;
+            self.pending.pop_front();
+        }
+        let Some(submitted) = submitted else {
+            return;
+        };
+        self.stats.latency.record(now - submitted);
+        if let Some(last) = self.last_displayed {
+            self.stats.frame_interval.record((now - last) / frames);
+        }
+        self.last_displayed = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::PresentTimings;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn latency_is_measured_from_present_to_wait() {
+        let start = Instant::now();
+        let mut timings = PresentTimings::default();
+        timings.presented_at(1, start);
+        timings.presented_at(2, start + ms(16));
+        timings.displayed_at(1, start + ms(30));
+        timings.displayed_at(2, start + ms(50));
+        let stats = timings.stats;
+        assert_eq!(stats.latency.samples, 2);
+        assert_eq!((stats.latency.min, stats.latency.max), (ms(30), ms(34)));
+        assert_eq!(stats.latency.average(), ms(32));
+        assert_eq!(stats.frame_interval.samples, 1);
+        assert_eq!(stats.frame_interval.last, ms(20));
+    }
+
+    #[test]
+    fn skipped_presents_split_the_frame_interval() {
+        let start = Instant::now();
+        let mut timings = PresentTimings::default();
+        for id in 1..=5 {
+            timings.presented_at(id, start + ms(10 * id));
+        }
+        timings.displayed_at(1, start + ms(20));
+        // 2 to 4 are displayed along with 5 without being waited on.
+        timings.displayed_at(5, start + ms(80));
+        assert_eq!(timings.stats.frame_interval.last, ms(15));
+        assert!(timings.pending.is_empty());
+    }
+
+    #[test]
+    fn unknown_ids_are_ignored() {
+        let start = Instant::now();
+        let mut timings = PresentTimings::default();
+        timings.presented_at(3, start);
+        timings.displayed_at(2, start + ms(5));
+        assert_eq!(timings.stats.latency.samples, 0);
+        assert_eq!(timings.pending.len(), 1);
+        // ids already dropped from the queue don't record anything either.
+        timings.displayed_at(3, start + ms(10));
+        timings.displayed_at(3, start + ms(20));
+        assert_eq!(timings.stats.latency.samples, 1);
+        assert_eq!(timings.stats.frame_interval.samples, 0);
+    }
+}
@@ -1,9 +1,12 @@
 #![allow(unused)]
+mod reactor;
 use std::{mem::MaybeUninit, sync::Arc, time::Duration};
 
 use ash::{prelude::VkResult, vk};
 
 use crate::error::VulkanError;
+pub use reactor::{FenceWait, SemaphoreWait};
+pub(crate) use reactor::Reactor;
 
 use super::device::LogicalDevice;
 #[derive(Clone)]
@@ -31,6 +34,10 @@ impl Fence {
     }
     pub fn status(&self, idx: usize) -> VkResult<bool> { unsafe { self.device.device.get_fence_status(self.fence) } }
     pub fn get(&self) -> vk::Fence { self.fence }
+    /// a future resolving once the fence signals, without blocking the thread.
+    pub fn wait_async(self: &Arc<Self>) -> FenceWait {
+        FenceWait::new(self.clone())
+    }
 }
 
 pub struct Semaphore {
@@ -47,6 +54,20 @@ impl Semaphore {
         let semaphore = unsafe { device.device.create_semaphore(&create_info, None).unwrap() };
         Self { semaphore, device }
     }
+    /// creates a timeline semaphore, requires the `timeline_semaphore` feature.
+    pub fn timeline(device: Arc<LogicalDevice>, initial_value: u64) -> Self {
+        let mut type_info = vk::SemaphoreTypeCreateInfo {
+            semaphore_type: vk::SemaphoreType::TIMELINE,
+            initial_value,
+            ..Default::default()
+        };
+        let create_info = vk::SemaphoreCreateInfo {
+            p_next: &mut type_info as *mut _ as *const _,
+            ..Default::default()
+        };
+        let semaphore = unsafe { device.device.create_semaphore(&create_info, None).unwrap() };
+        Self { semaphore, device }
+    }
     pub fn signal(&self, value: u64) {
         let signal_info = vk::SemaphoreSignalInfo {
             semaphore: self.semaphore,
@@ -59,6 +80,10 @@ impl Semaphore {
         unsafe { self.device.device.get_semaphore_counter_value(self.semaphore) }
     }
     pub fn get(&self) -> vk::Semaphore { self.semaphore }
+    /// a future resolving once this timeline semaphore reaches `value`.
+    pub fn wait_async(self: &Arc<Self>, value: u64) -> SemaphoreWait {
+        SemaphoreWait::new(self.clone(), value)
+    }
 }
 
 impl Drop for Fence {
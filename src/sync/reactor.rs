@@ -0,0 +1,248 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::{Arc, Condvar, Mutex}, task::{Context, Poll, Waker}, thread::JoinHandle};
+
+use ash::vk;
+use smallvec::SmallVec;
+
+use crate::error::VulkanError;
+
+use super::{Fence, Semaphore};
+
+/// how long the reactor blocks on the device before picking up new waiters.
+const POLL_TIMEOUT_NS: u64 = 1_000_000;
+
+/// Waiters own their fence or semaphore, so a future dropped while the reactor
+/// waits can't destroy the handle under it.
+#[derive(Clone)]
+enum WaitTarget {
+    Fence(Arc<Fence>),
+    Timeline(Arc<Semaphore>, u64),
+}
+impl WaitTarget {
+    /// errors count as complete, the future reports them when it polls the handle itself.
+    fn is_complete(&self) -> bool {
+        match self {
+            Self::Fence(fence) => fence.status(0).unwrap_or(true),
+            Self::Timeline(semaphore, value) => semaphore.counter(0).map_or(true, |counter| counter >= *value),
+        }
+    }
+}
+
+struct Waiter {
+    target: WaitTarget,
+    waker: Waker,
+}
+
+#[derive(Default)]
+struct ReactorState {
+    next_key: u64,
+    waiters: HashMap<u64, Waiter>,
+    shutdown: bool,
+}
+
+struct ReactorShared {
+    state: Mutex<ReactorState>,
+    wake: Condvar,
+}
+
+/// A thread per device which waits on every fence and timeline semaphore an
+/// async task is waiting for, waking the tasks as they signal. It's started the
+/// first time something is awaited and stopped when the device is dropped.
+pub(crate) struct Reactor {
+    shared: Arc<ReactorShared>,
+    thread: Option<JoinHandle<()>>,
+}
+impl Reactor {
+    pub(crate) fn start(device: ash::Device) -> Self {
+        let shared = Arc::new(ReactorShared { state: Mutex::new(ReactorState::default()), wake: Condvar::new() });
+        let thread = {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name("nightfall-reactor".into())
+                .spawn(move || Self::run(&device, &shared))
+                .expect("failed to spawn the fence reactor thread")
+        };
+        Self { shared, thread: Some(thread) }
+    }
+    fn run(device: &ash::Device, shared: &ReactorShared) {
+        loop {
+            // the targets are cloned out so registering and dropping futures never wait on the device.
+            let targets = {
+                let mut state = shared.state.lock().unwrap();
+                while state.waiters.is_empty() && !state.shutdown {
+                    state = shared.wake.wait(state).unwrap();
+                }
+                if state.shutdown {
+                    return;
+                }
+                state.waiters.values().map(|waiter| waiter.target.clone()).collect::<SmallVec<[_; 8]>>()
+            };
+            let fences = targets.iter().filter_map(|target| match target {
+                WaitTarget::Fence(fence) => Some(fence.get()),
+                _ => None,
+            }).collect::<SmallVec<[_; 8]>>();
+            let (semaphores, values): (SmallVec<[_; 8]>, SmallVec<[_; 8]>) = targets.iter().filter_map(|target| match target {
+                WaitTarget::Timeline(semaphore, value) => Some((semaphore.get(), *value)),
+                _ => None,
+            }).unzip();
+            // fences and semaphores can't be waited on together, so they split the timeout.
+            let timeout = if fences.is_empty() || semaphores.is_empty() { POLL_TIMEOUT_NS } else { POLL_TIMEOUT_NS / 2 };
+            if !fences.is_empty() {
+                let _ = unsafe { device.wait_for_fences(&fences, false, timeout) };
+            }
+            if !semaphores.is_empty() {
+                let wait = vk::SemaphoreWaitInfo {
+                    flags: vk::SemaphoreWaitFlags::ANY,
+                    semaphore_count: semaphores.len() as u32,
+                    p_semaphores: semaphores.as_ptr(),
+                    p_values: values.as_ptr(),
+                    ..Default::default()
+                };
+                let _ = unsafe { device.wait_semaphores(&wait, timeout) };
+            }
+            let mut wakers = SmallVec::<[Waker; 8]>::new();
+            shared.state.lock().unwrap().waiters.retain(|_, waiter| {
+                let complete = waiter.target.is_complete();
+                if complete {
+                    wakers.push(waiter.waker.clone());
+                }
+                !complete
+            });
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+    /// registers or refreshes a waiter, returning its key.
+    fn register(&self, key: Option<u64>, target: WaitTarget, waker: &Waker) -> u64 {
+        let mut state = self.shared.state.lock().unwrap();
+        let key = key.unwrap_or_else(|| {
+            state.next_key += 1;
+            state.next_key
+        });
+        state.waiters.insert(key, Waiter { target, waker: waker.clone() });
+        self.shared.wake.notify_one();
+        key
+    }
+    fn deregister(&self, key: u64) {
+        let waiter = self.shared.state.lock().unwrap().waiters.remove(&key);
+        // the target may hold the last reference to its handle, it's dropped outside the lock.
+        drop(waiter);
+    }
+}
+impl Drop for Reactor {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.wake.notify_one();
+        if let Some(thread) = self.thread.take() {
+            // the reactor may drop the last handle keeping the device alive itself,
+            // it then exits on its own once this returns.
+            if thread.thread().id() != std::thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Resolves once the fence signals. Dropping it stops the wait, the fence is
+/// left as is.
+pub struct FenceWait {
+    fence: Arc<Fence>,
+    key: Option<u64>,
+}
+impl FenceWait {
+    pub fn new(fence: Arc<Fence>) -> Self {
+        Self { fence, key: None }
+    }
+    #[inline]
+    pub fn fence(&self) -> &Fence {
+        &self.fence
+    }
+    pub fn into_inner(mut self) -> Arc<Fence> {
+        self.deregister();
+        self.fence.clone()
+    }
+    fn deregister(&mut self) {
+        if let Some(key) = self.key.take() {
+            if let Some(reactor) = self.fence.device.reactor.get() {
+                reactor.deregister(key);
+            }
+        }
+    }
+}
+impl Future for FenceWait {
+    type Output = Result<(), VulkanError>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.fence.status(0) {
+            Ok(true) => {
+                self.deregister();
+                Poll::Ready(Ok(()))
+            }
+            Ok(false) => {
+                let key = self.fence.device.reactor().register(self.key, WaitTarget::Fence(self.fence.clone()), cx.waker());
+                self.key = Some(key);
+                Poll::Pending
+            }
+            Err(err) => {
+                self.deregister();
+                Poll::Ready(Err(VulkanError::from(err)))
+            }
+        }
+    }
+}
+impl Drop for FenceWait {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
+
+/// Resolves once a timeline semaphore reaches a value.
+pub struct SemaphoreWait {
+    semaphore: Arc<Semaphore>,
+    value: u64,
+    key: Option<u64>,
+}
+impl SemaphoreWait {
+    pub fn new(semaphore: Arc<Semaphore>, value: u64) -> Self {
+        Self { semaphore, value, key: None }
+    }
+    #[inline]
+    pub fn semaphore(&self) -> &Semaphore {
+        &self.semaphore
+    }
+    #[inline]
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+    fn deregister(&mut self) {
+        if let Some(key) = self.key.take() {
+            if let Some(reactor) = self.semaphore.device.reactor.get() {
+                reactor.deregister(key);
+            }
+        }
+    }
+}
+impl Future for SemaphoreWait {
+    type Output = Result<(), VulkanError>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.semaphore.counter(0) {
+            Ok(counter) if counter >= self.value => {
+                self.deregister();
+                Poll::Ready(Ok(()))
+            }
+            Ok(_) => {
+                let key = self.semaphore.device.reactor().register(self.key, WaitTarget::Timeline(self.semaphore.clone(), self.value), cx.waker());
+                self.key = Some(key);
+                Poll::Pending
+            }
+            Err(err) => {
+                self.deregister();
+                Poll::Ready(Err(VulkanError::from(err)))
+            }
+        }
+    }
+}
+impl Drop for SemaphoreWait {
+    fn drop(&mut self) {
+        self.deregister();
+    }
+}
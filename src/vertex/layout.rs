@@ -0,0 +1,178 @@
+use ash::vk;
+
+use crate::{device::PhysicalDevice, error::NightfallError, swapchain::Format};
+
+use super::Vertex;
+
+/// Combines several [`Vertex`] types into one vertex input state. Each type
+/// gets the next binding number and its locations are moved past the ones
+/// already taken, so a per-vertex and a per-instance struct can both start
+/// counting from location 0.
+#[derive(Clone, Default)]
+pub struct VertexLayout {
+    bindings: Vec<vk::VertexInputBindingDescription>,
+    attributes: Vec<vk::VertexInputAttributeDescription>,
+    next_location: u32,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// adds `V` with the input rate it was declared with.
+    pub fn push<V: Vertex>(self) -> Self {
+        self.push_with_rate::<V>(V::INPUT_RATE)
+    }
+    pub fn push_with_rate<V: Vertex>(mut self, rate: vk::VertexInputRate) -> Self {
+        let binding = self.bindings.len() as u32;
+        let base = self.next_location;
+        self.bindings.push(V::binding(binding, rate));
+        for mut attribute in V::attributes(binding) {
+            attribute.location += base;
+            self.next_location = self.next_location.max(attribute.location + Self::location_count(attribute.format));
+            self.attributes.push(attribute);
+        }
+        self
+    }
+    /// 64 bit formats with three or four components take up two locations.
+    fn location_count(format: vk::Format) -> u32 {
+        match format {
+            vk::Format::R64G64B64_SFLOAT | vk::Format::R64G64B64A64_SFLOAT |
+            vk::Format::R64G64B64_UINT | vk::Format::R64G64B64A64_UINT |
+            vk::Format::R64G64B64_SINT | vk::Format::R64G64B64A64_SINT => 2,
+            _ => 1,
+        }
+    }
+    #[inline]
+    pub fn bindings(&self) -> &Vec<vk::VertexInputBindingDescription> {
+        &self.bindings
+    }
+    #[inline]
+    pub fn attributes(&self) -> &Vec<vk::VertexInputAttributeDescription> {
+        &self.attributes
+    }
+    /// the first location not used by any attribute.
+    #[inline]
+    pub fn location_end(&self) -> u32 {
+        self.next_location
+    }
+    /// checks every attribute format can be read from a vertex buffer and
+    /// that the device supports this many bindings and attributes.
+    pub fn validate(&self, physical_device: &PhysicalDevice) -> Result<(), NightfallError> {
+        let limits = &physical_device.properties().limits;
+        if self.bindings.len() as u32 > limits.max_vertex_input_bindings {
+            return Err(NightfallError::TooManyVertexBindings(self.bindings.len() as u32, limits.max_vertex_input_bindings));
+        }
+        if self.next_location > limits.max_vertex_input_attributes {
+            return Err(NightfallError::TooManyVertexAttributes(self.next_location, limits.max_vertex_input_attributes));
+        }
+        for attribute in &self.attributes {
+            let properties = physical_device.get_format_properties(Format::from_raw(attribute.format.as_raw()));
+            if !properties.buffer_features.contains(vk::FormatFeatureFlags::VERTEX_BUFFER) {
+                return Err(NightfallError::UnsupportedVertexFormat(attribute.location, attribute.format));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    use super::VertexLayout;
+    use crate::vertex::Vertex;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Vertex)]
+    struct Position {
+        position: nightfall_core::glsl::Vec3,
+        uv: [f32; 2],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Vertex)]
+    #[per_instance]
+    struct Transform {
+        model: nightfall_core::glsl::Mat4,
+        extent: [f64; 3],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Vertex)]
+    struct Color {
+        #[normalized]
+        color: [u8; 4],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Vertex)]
+    struct Sparse {
+        weight: f32,
+        #[location = 4]
+        bone: u32,
+    }
+
+    fn bindings(layout: &VertexLayout) -> Vec<(u32, u32, vk::VertexInputRate)> {
+        layout.bindings().iter().map(|b| (b.binding, b.stride, b.input_rate)).collect()
+    }
+    fn attributes(layout: &VertexLayout) -> Vec<(u32, u32, u32, vk::Format)> {
+        layout.attributes().iter().map(|a| (a.location, a.binding, a.offset, a.format)).collect()
+    }
+
+    #[test]
+    fn each_type_gets_the_next_binding() {
+        let layout = VertexLayout::new().push::<Position>().push::<Transform>().push::<Color>();
+        assert_eq!(
+            bindings(&layout),
+            [
+                (0, 20, vk::VertexInputRate::VERTEX),
+                (1, 88, vk::VertexInputRate::INSTANCE),
+                (2, 4, vk::VertexInputRate::VERTEX),
+            ]
+        );
+    }
+    #[test]
+    fn locations_continue_across_types() {
+        let layout = VertexLayout::new().push::<Position>().push::<Transform>().push::<Color>();
+        assert_eq!(
+            attributes(&layout),
+            [
+                (0, 0, 0, vk::Format::R32G32B32_SFLOAT),
+                (1, 0, 12, vk::Format::R32G32_SFLOAT),
+                (2, 1, 0, vk::Format::R32G32B32A32_SFLOAT),
+                (3, 1, 16, vk::Format::R32G32B32A32_SFLOAT),
+                (4, 1, 32, vk::Format::R32G32B32A32_SFLOAT),
+                (5, 1, 48, vk::Format::R32G32B32A32_SFLOAT),
+                (6, 1, 64, vk::Format::R64G64B64_SFLOAT),
+                // the dvec3 above takes two locations.
+                (8, 2, 0, vk::Format::R8G8B8A8_UNORM),
+            ]
+        );
+        assert_eq!(layout.location_end(), 9);
+    }
+    #[test]
+    fn explicit_locations_move_the_next_type() {
+        let layout = VertexLayout::new().push::<Sparse>().push::<Position>();
+        assert_eq!(
+            attributes(&layout),
+            [
+                (0, 0, 0, vk::Format::R32_SFLOAT),
+                (4, 0, 4, vk::Format::R32_UINT),
+                (5, 1, 0, vk::Format::R32G32B32_SFLOAT),
+                (6, 1, 12, vk::Format::R32G32_SFLOAT),
+            ]
+        );
+    }
+    #[test]
+    fn rates_can_be_overridden() {
+        let layout = VertexLayout::new()
+            .push_with_rate::<Position>(vk::VertexInputRate::INSTANCE)
+            .push_with_rate::<Transform>(vk::VertexInputRate::VERTEX);
+        assert_eq!(
+            bindings(&layout),
+            [(0, 20, vk::VertexInputRate::INSTANCE), (1, 88, vk::VertexInputRate::VERTEX)]
+        );
+        assert_eq!(layout.location_end(), 8);
+    }
+}
@@ -1,6 +1,88 @@
 use ash::vk;
+mod layout;
+pub use layout::*;
 pub use nightfall_core_macros::Vertex;
+/// Usually derived. Locations are assigned in field order unless a field has
+/// `#[location = N]`, and formats are inferred from the field types: scalars,
+/// arrays of up to four scalars, the [`crate::glsl`] vectors and matrices, and
+/// `[[f32; N]; M]` matrices which take one location per column. The GLSL
+/// types have to be written by their full path, `nightfall_core::glsl::Vec3`,
+/// since a derive can't tell them apart from other types named `Vec3`. Integer
+/// fields marked `#[normalized]` use UNORM/SNORM formats, and `#[format(...)]`
+/// sets the format explicitly, which any other type needs. `#[per_instance]`
+/// on the struct makes it advance per instance instead of per vertex.
 pub trait Vertex {
+    const INPUT_RATE: vk::VertexInputRate = vk::VertexInputRate::VERTEX;
     fn binding(binding: u32, rate: ash::vk::VertexInputRate) -> vk::VertexInputBindingDescription;
     fn attributes(binding: u32) -> Vec<vk::VertexInputAttributeDescription>;
-}
\ No newline at end of file
+    /// the binding description using the input rate the type was declared with.
+    fn binding_description(binding: u32) -> vk::VertexInputBindingDescription {
+        Self::binding(binding, Self::INPUT_RATE)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::Vertex;
+    use ash::vk;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Vertex)]
+    struct Basic {
+        position: nightfall_core::glsl::Vec3,
+        uv: [f32; 2],
+        #[normalized]
+        color: [u8; 4],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Vertex)]
+    #[per_instance]
+    struct Instance {
+        model: nightfall_core::glsl::Mat4,
+        #[location = 8]
+        tint: [f32; 4],
+        id: u32,
+        extent: [f64; 3],
+        scale: f32,
+        #[format(R16G16_SFLOAT)]
+        packed: [u16; 2],
+    }
+
+    fn summary<V: Vertex>(binding: u32) -> Vec<(u32, u32, u32, vk::Format)> {
+        V::attributes(binding).iter().map(|a| (a.location, a.binding, a.offset, a.format)).collect()
+    }
+
+    #[test]
+    fn locations_follow_field_order() {
+        assert_eq!(
+            summary::<Basic>(2),
+            [
+                (0, 2, 0, vk::Format::R32G32B32_SFLOAT),
+                (1, 2, 12, vk::Format::R32G32_SFLOAT),
+                (2, 2, 20, vk::Format::R8G8B8A8_UNORM),
+            ]
+        );
+        let binding = Basic::binding_description(2);
+        assert_eq!((binding.binding, binding.stride, binding.input_rate), (2, 24, vk::VertexInputRate::VERTEX));
+    }
+
+    #[test]
+    fn matrices_and_doubles_take_several_locations() {
+        assert_eq!(
+            summary::<Instance>(0),
+            [
+                (0, 0, 0, vk::Format::R32G32B32A32_SFLOAT),
+                (1, 0, 16, vk::Format::R32G32B32A32_SFLOAT),
+                (2, 0, 32, vk::Format::R32G32B32A32_SFLOAT),
+                (3, 0, 48, vk::Format::R32G32B32A32_SFLOAT),
+                (8, 0, 64, vk::Format::R32G32B32A32_SFLOAT),
+                (9, 0, 80, vk::Format::R32_UINT),
+                (10, 0, 88, vk::Format::R64G64B64_SFLOAT),
+                (12, 0, 112, vk::Format::R32_SFLOAT),
+                (13, 0, 116, vk::Format::R16G16_SFLOAT),
+            ]
+        );
+        let binding = Instance::binding_description(0);
+        assert_eq!((binding.stride, binding.input_rate), (120, vk::VertexInputRate::INSTANCE));
+    }
+}